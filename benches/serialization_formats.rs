@@ -0,0 +1,192 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::hint::black_box;
+use time_travel_debugger::runtime::delta::SnapshotDelta;
+use time_travel_debugger::runtime::persistence::{CompressedStateSerializer, StateSerializer};
+use time_travel_debugger::runtime::serialization::JSValue;
+use time_travel_debugger::runtime::snapshot_store::{FileSnapshotStore, SnapshotStore};
+use time_travel_debugger::runtime::{ExecutionState, FunctionCall, VariableSnapshot};
+
+const SNAPSHOT_COUNT: usize = 10_000;
+
+/// The recorded history in `ExecutionState::serialize_to_json`'s document; the JSON export
+/// has no reader of its own
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct JsonHistory {
+    function_call_history: Vec<FunctionCall>,
+    variable_snapshots: Vec<VariableSnapshot>,
+}
+
+/// Build a state with snapshots shaped like a typical `captureScope` call
+fn build_state() -> ExecutionState {
+    let mut state = ExecutionState::default();
+
+    for i in 0..SNAPSHOT_COUNT {
+        let address = JSValue::Object(HashMap::from([
+            ("street".to_string(), JSValue::String(format!("{} Main St", i))),
+            ("zip".to_string(), JSValue::Number(12345.0)),
+        ]));
+        let user = JSValue::Object(HashMap::from([
+            ("id".to_string(), JSValue::Number(i as f64)),
+            ("name".to_string(), JSValue::String("Alice".to_string())),
+            ("address".to_string(), address),
+            ("tags".to_string(), JSValue::Array(vec![
+                JSValue::String("admin".to_string()),
+                JSValue::Boolean(true),
+                JSValue::Null,
+            ])),
+        ]));
+
+        state.variable_snapshots.push(VariableSnapshot {
+            timestamp: i as f64,
+            function_name: format!("handler{}", i % 16),
+            call_depth: i % 8,
+            variables: HashMap::from([
+                ("user".to_string(), user),
+                ("index".to_string(), JSValue::Number(i as f64)),
+            ]),
+            snapshot_type: "entry".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        });
+    }
+
+    state
+}
+
+fn bench_serialization_formats(c: &mut Criterion) {
+    let state = build_state();
+
+    let json = state.serialize_to_json().unwrap();
+    let protobuf = state.serialize_to_protobuf().unwrap();
+    let bincode = StateSerializer::encode(&state).unwrap();
+    println!("{} snapshots: json = {} bytes, protobuf = {} bytes, bincode = {} bytes",
+        SNAPSHOT_COUNT, json.len(), protobuf.len(), bincode.len());
+
+    let mut group = c.benchmark_group("serialize_10k_snapshots");
+    group.sample_size(10);
+    group.bench_function("json", |b| b.iter(|| black_box(state.serialize_to_json().unwrap())));
+    group.bench_function("protobuf", |b| b.iter(|| black_box(state.serialize_to_protobuf().unwrap())));
+    group.bench_function("bincode", |b| b.iter(|| black_box(StateSerializer::encode(&state).unwrap())));
+    group.finish();
+
+    let mut group = c.benchmark_group("deserialize_10k_snapshots");
+    group.sample_size(10);
+    group.bench_function("json", |b| {
+        b.iter(|| black_box(serde_json::from_slice::<JsonHistory>(&json).unwrap()))
+    });
+    group.bench_function("protobuf", |b| {
+        b.iter(|| black_box(ExecutionState::deserialize_from_protobuf(&protobuf).unwrap()))
+    });
+    group.bench_function("bincode", |b| b.iter(|| black_box(StateSerializer::decode(&bincode).unwrap())));
+    group.finish();
+}
+
+fn bench_state_files(c: &mut Criterion) {
+    let state = build_state();
+    let dir = std::env::temp_dir();
+    let plain = dir.join(format!("ttd_bench_{}.ttd", std::process::id()));
+    let compressed = dir.join(format!("ttd_bench_{}.ttd.lz4", std::process::id()));
+
+    StateSerializer::save(&state, &plain).unwrap();
+    CompressedStateSerializer::save(&state, &compressed).unwrap();
+    println!("{} snapshots: bincode = {} bytes, bincode+lz4 = {} bytes", SNAPSHOT_COUNT,
+        std::fs::metadata(&plain).unwrap().len(), std::fs::metadata(&compressed).unwrap().len());
+
+    let mut group = c.benchmark_group("state_file_roundtrip_10k_snapshots");
+    group.sample_size(10);
+    group.bench_function("bincode", |b| b.iter(|| {
+        StateSerializer::save(&state, &plain).unwrap();
+        black_box(StateSerializer::load(&plain).unwrap())
+    }));
+    group.bench_function("bincode_lz4", |b| b.iter(|| {
+        CompressedStateSerializer::save(&state, &compressed).unwrap();
+        black_box(CompressedStateSerializer::load(&compressed).unwrap())
+    }));
+    group.finish();
+
+    let _ = std::fs::remove_file(&plain);
+    let _ = std::fs::remove_file(&compressed);
+}
+
+const FILE_SNAPSHOT_COUNT: usize = 100_000;
+/// `DebuggerConfig::write_batch_size`'s default
+const WRITE_BATCH_SIZE: usize = 100;
+
+fn bench_snapshot_file_writes(c: &mut Criterion) {
+    let state = build_state();
+    let snapshots: Vec<VariableSnapshot> = (0..FILE_SNAPSHOT_COUNT)
+        .map(|i| VariableSnapshot { timestamp: i as f64, ..state.variable_snapshots[i % SNAPSHOT_COUNT].clone() })
+        .collect();
+    let path = std::env::temp_dir().join(format!("ttd_bench_{}.ttdl", std::process::id()));
+    let create = || FileSnapshotStore::create(&path).unwrap();
+
+    let mut group = c.benchmark_group("snapshot_file_100k_snapshots");
+    group.sample_size(10);
+    group.bench_function("append", |b| b.iter_batched(create, |mut store| {
+        for snapshot in &snapshots {
+            store.append(snapshot).unwrap();
+        }
+        black_box(store)
+    }, BatchSize::PerIteration));
+    group.bench_function("append_batch", |b| b.iter_batched(create, |mut store| {
+        for batch in snapshots.chunks(WRITE_BATCH_SIZE) {
+            store.append_batch(batch).unwrap();
+        }
+        black_box(store)
+    }, BatchSize::PerIteration));
+    group.finish();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+/// 1000 snapshots of a tight loop: only the counter and accumulator change per iteration
+fn build_loop_state() -> ExecutionState {
+    let mut state = ExecutionState::default();
+    let items = JSValue::Array((0..64).map(|i| JSValue::Number(i as f64)).collect());
+    let options = JSValue::Object(HashMap::from([
+        ("label".to_string(), JSValue::String("sum of squares".to_string())),
+        ("precision".to_string(), JSValue::Number(2.0)),
+    ]));
+
+    for i in 0..1000 {
+        state.variable_snapshots.push(VariableSnapshot {
+            timestamp: i as f64,
+            function_name: "sumSquares".to_string(),
+            call_depth: 1,
+            variables: HashMap::from([
+                ("i".to_string(), JSValue::Number(i as f64)),
+                ("total".to_string(), JSValue::Number((i * i) as f64)),
+                ("items".to_string(), items.clone()),
+                ("options".to_string(), options.clone()),
+            ]),
+            snapshot_type: "custom".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        });
+    }
+    state
+}
+
+fn bench_delta_chain(c: &mut Criterion) {
+    let state = build_loop_state();
+    let full: usize = state.variable_snapshots.iter().map(VariableSnapshot::estimated_bytes).sum();
+    let chain = build_loop_state().compress_delta_chain();
+    let delta: usize = chain.iter().map(SnapshotDelta::size_estimate).sum();
+    println!("1000 loop snapshots: full = {} bytes, delta chain = {} bytes ({:.1}% saved)",
+        full, delta, 100.0 * (1.0 - delta as f64 / full as f64));
+
+    let mut group = c.benchmark_group("delta_chain_1k_loop_snapshots");
+    group.sample_size(10);
+    group.bench_function("compress", |b| b.iter_batched(build_loop_state,
+        |mut state| black_box(state.compress_delta_chain()), BatchSize::LargeInput));
+    group.bench_function("expand", |b| b.iter(|| black_box(SnapshotDelta::expand_chain(&chain))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_serialization_formats, bench_state_files, bench_snapshot_file_writes, bench_delta_chain);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+fn main() -> std::io::Result<()> {
+    // Use the vendored protoc so contributors don't need it installed
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        // SAFETY: build scripts are single threaded
+        unsafe { std::env::set_var("PROTOC", protoc) };
+    }
+
+    println!("cargo:rerun-if-changed=proto/snapshot.proto");
+    println!("cargo:rerun-if-changed=proto/otlp.proto");
+    prost_build::compile_protos(&["proto/snapshot.proto", "proto/otlp.proto"], &["proto"])
+}
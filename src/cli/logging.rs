@@ -0,0 +1,40 @@
+use anyhow::{Result, anyhow};
+use tracing::level_filters::LevelFilter;
+
+/// Layout of the runtime's log records, chosen with `--log-format`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per record, for log aggregation
+    Json,
+    Compact,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            "compact" => Ok(LogFormat::Compact),
+            _ => Err(anyhow!("Unknown log format: {} (expected text, json or compact)", value)),
+        }
+    }
+}
+
+/// Install the global subscriber, writing to stderr so logs stay apart from the reports
+/// on stdout. `verbose` lowers the level from info to debug.
+pub fn init(format: LogFormat, verbose: bool) {
+    let level = if verbose { LevelFilter::DEBUG } else { LevelFilter::INFO };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+
+    // A subscriber may already be installed when embedding the CLI; keep that one
+    let _ = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+        LogFormat::Compact => builder.compact().try_init(),
+    };
+}
@@ -1,17 +1,143 @@
-use crate::runtime::{TimeDebuggerRuntime, DebuggerConfig};
+pub mod repl;
+pub mod logging;
+pub mod output;
+
+use crate::runtime::{TimeDebuggerRuntime, DebuggerConfig, EvictionPolicy, OutputFormat};
+use repl::DebuggerRepl;
+use logging::LogFormat;
+use output::Output;
+use crate::dap::DapServer;
+use crate::runtime::memory::MemoryPressureMode;
+use crate::runtime::coverage::line_coverage_json;
+use crate::runtime::compare::{RegressionDetector, TraceComparator};
+use crate::runtime::persistence::{StateSerializer, TraceMerger};
+use crate::runtime::expectations::TestExpectations;
+use crate::runtime::purity::PurityAnalyzer;
+use crate::runtime::snapshot_store::FileSnapshotStore;
 use anyhow::{Result, anyhow};
+use std::collections::HashSet;
 use std::env;
-use std::path::Path;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Output format used by `--export`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Protobuf,
+}
+
+impl ExportFormat {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "json" => Ok(ExportFormat::Json),
+            "protobuf" => Ok(ExportFormat::Protobuf),
+            _ => Err(anyhow!("Unknown export format: {} (expected json or protobuf)", value)),
+        }
+    }
+}
+
+/// Calls beyond this make Mermaid diagrams too large to render usefully
+const SEQUENCE_DIAGRAM_MAX_CALLS: usize = 200;
+
+/// Functions listed in the "hottest functions" section of every run's output
+const HOT_FUNCTIONS_SHOWN: usize = 10;
+
+/// Number of largest snapshots listed by `--size-analysis`
+const SIZE_ANALYSIS_TOP_N: usize = 10;
+
+/// Where `--merge` writes the combined trace unless `--merge-output` is given
+const DEFAULT_MERGE_OUTPUT: &str = "merged.ttd";
+
+/// Config file read automatically from the working directory when `--config` isn't given
+const DEFAULT_CONFIG_FILE: &str = ".time-travel.toml";
+
+/// Width of the attached terminal, if stdout is one
+fn detect_terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
 
 /// Command line interface for the time travel debugger
 pub struct DebuggerCli {
     config: DebuggerConfig,
+    export_path: Option<String>,
+    export_format: ExportFormat,
+    cluster_threshold: Option<f64>,
+    sequence_diagram_path: Option<String>,
+    call_graph_path: Option<String>,
+    module_graph_path: Option<PathBuf>,
+    flamegraph_path: Option<PathBuf>,
+    line_coverage_path: Option<PathBuf>,
+    cpu_profile_path: Option<PathBuf>,
+    trace_output_path: Option<PathBuf>,
+    size_analysis: bool,
+    analyze_purity: bool,
+    leak_growth_rate: Option<f64>,
+    coverage_output: Option<PathBuf>,
+    otlp_path: Option<String>,
+    report_path: Option<PathBuf>,
+    coupling_top_n: Option<usize>,
+    repl: bool,
+    dap: bool,
+    load_trace: Option<PathBuf>,
+    /// Saved traces `--merge` combines instead of running a script
+    merge_paths: Vec<PathBuf>,
+    /// `--cat OUTPUT INPUT...`: output first
+    cat_paths: Vec<PathBuf>,
+    merge_output: PathBuf,
+    log_format: LogFormat,
+    /// Config file the settings (and `--test-mode` expectations) came from
+    config_path: Option<PathBuf>,
+    test_mode: bool,
+    replay_baseline: Option<PathBuf>,
+    record_baseline: Option<PathBuf>,
+    compare_baseline: Option<PathBuf>,
+}
+
+impl Default for DebuggerCli {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DebuggerCli {
     pub fn new() -> Self {
+        let config = DebuggerConfig {
+            output_width: detect_terminal_width().unwrap_or(DebuggerConfig::default().output_width),
+            ..Default::default()
+        };
+
         Self {
-            config: DebuggerConfig::default(),
+            config,
+            export_path: None,
+            export_format: ExportFormat::Json,
+            cluster_threshold: None,
+            sequence_diagram_path: None,
+            call_graph_path: None,
+            module_graph_path: None,
+            flamegraph_path: None,
+            line_coverage_path: None,
+            cpu_profile_path: None,
+            trace_output_path: None,
+            size_analysis: false,
+            analyze_purity: false,
+            leak_growth_rate: None,
+            coverage_output: None,
+            otlp_path: None,
+            report_path: None,
+            coupling_top_n: None,
+            repl: false,
+            dap: false,
+            load_trace: None,
+            merge_paths: Vec::new(),
+            cat_paths: Vec::new(),
+            merge_output: PathBuf::from(DEFAULT_MERGE_OUTPUT),
+            log_format: LogFormat::default(),
+            config_path: None,
+            test_mode: false,
+            replay_baseline: None,
+            record_baseline: None,
+            compare_baseline: None,
         }
     }
 
@@ -27,6 +153,19 @@ impl DebuggerCli {
         let mut cli = Self::new();
         let mut file_path = None;
 
+        // The config file is the starting point; every other flag overrides it
+        if let Some(config_path) = Self::config_file(&args)? {
+            let config = DebuggerConfig::from_toml(&config_path)?;
+            // A file leaving the width at its default still follows the terminal
+            let output_width = if config.output_width == DebuggerConfig::default().output_width {
+                cli.config.output_width
+            } else {
+                config.output_width
+            };
+            cli.config = DebuggerConfig { output_width, ..config };
+            cli.config_path = Some(config_path);
+        }
+
         // Simple argument parsing
         let mut i = 1;
         while i < args.len() {
@@ -51,9 +190,401 @@ impl DebuggerCli {
                         return Err(anyhow!("--max-snapshots requires a value"));
                     }
                 },
+                "--max-snapshot-memory" => {
+                    if i + 1 < args.len() {
+                        cli.config.max_snapshot_memory_mb = Some(args[i + 1].parse()
+                            .map_err(|_| anyhow!("Invalid max-snapshot-memory value"))?);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--max-snapshot-memory requires a value"));
+                    }
+                },
+                "--eviction-policy" => {
+                    if i + 1 < args.len() {
+                        cli.config.eviction_policy = match args[i + 1].as_str() {
+                            "oldest" => EvictionPolicy::OldestFirst,
+                            "lowest-depth" => EvictionPolicy::LowestDepth,
+                            "error" => EvictionPolicy::Error,
+                            other => return Err(anyhow!(
+                                "Unknown eviction policy: {} (expected oldest, lowest-depth or error)", other)),
+                        };
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--eviction-policy requires a value"));
+                    }
+                },
+                "--width" => {
+                    if i + 1 < args.len() {
+                        cli.config.output_width = args[i + 1].parse()
+                            .ok()
+                            .filter(|width| *width > 0)
+                            .ok_or_else(|| anyhow!("Invalid width value"))?;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--width requires a value"));
+                    }
+                },
+                "--alias" => {
+                    if i + 1 < args.len() {
+                        let (real_name, alias) = args[i + 1].split_once('=')
+                            .filter(|(real_name, alias)| !real_name.is_empty() && !alias.is_empty())
+                            .ok_or_else(|| anyhow!("Invalid alias (expected name=alias): {}", args[i + 1]))?;
+                        cli.config.variable_aliases.insert(real_name.to_string(), alias.to_string());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--alias requires a name=alias value"));
+                    }
+                },
+                "--max-memory-mb" => {
+                    if i + 1 < args.len() {
+                        cli.config.max_memory_mb = args[i + 1].parse()
+                            .ok()
+                            .filter(|mb| *mb > 0)
+                            .ok_or_else(|| anyhow!("Invalid max-memory-mb value"))?;
+                        cli.config.memory_pressure_mode = MemoryPressureMode::Adaptive;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--max-memory-mb requires a value"));
+                    }
+                },
+                "--trace-method" => {
+                    if i + 1 < args.len() {
+                        cli.config.prototype_method_trace.push(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--trace-method requires a method path (e.g. Array.prototype.push)"));
+                    }
+                },
+                "--capture-filter" => {
+                    if i + 1 < args.len() {
+                        cli.config.capture_function_filter = Some(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--capture-filter requires a regex"));
+                    }
+                },
+                "--snapshot-store" => {
+                    if i + 1 < args.len() {
+                        cli.config.snapshot_file = match args[i + 1].as_str() {
+                            "memory" => None,
+                            store => match store.strip_prefix("file:") {
+                                Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+                                _ => return Err(anyhow!(
+                                    "Invalid snapshot store: {} (expected memory or file:<path>)", store)),
+                            },
+                        };
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--snapshot-store requires memory or file:<path>"));
+                    }
+                },
+                "--write-batch-size" => {
+                    if i + 1 < args.len() {
+                        let size: usize = args[i + 1].parse()
+                            .map_err(|_| anyhow!("Invalid --write-batch-size value: {}", args[i + 1]))?;
+                        cli.config.write_batch_size = size;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--write-batch-size requires a number"));
+                    }
+                },
+                "--max-call-depth" => {
+                    if i + 1 < args.len() {
+                        let depth: usize = args[i + 1].parse()
+                            .map_err(|_| anyhow!("Invalid --max-call-depth value: {}", args[i + 1]))?;
+                        cli.config.max_call_depth = Some(depth);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--max-call-depth requires a number"));
+                    }
+                },
+                "--repeated-call-threshold" => {
+                    if i + 1 < args.len() {
+                        let threshold: u32 = args[i + 1].parse()
+                            .map_err(|_| anyhow!("Invalid --repeated-call-threshold value: {}", args[i + 1]))?;
+                        cli.config.repeated_call_threshold = Some(threshold);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--repeated-call-threshold requires a number"));
+                    }
+                },
+                "--warn-on-max-depth" => {
+                    cli.config.max_depth_warn_only = true;
+                },
+                "--crash-output" => {
+                    if i + 1 < args.len() {
+                        cli.config.crash_output = PathBuf::from(&args[i + 1]);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--crash-output requires a file path"));
+                    }
+                },
+                "--trace-id" => {
+                    if i + 1 < args.len() {
+                        cli.config.trace_id = Some(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--trace-id requires a value"));
+                    }
+                },
+                "--update-snapshots" => {
+                    cli.config.update_snapshots = true;
+                },
+                "--dedup-snapshots" => {
+                    cli.config.dedup_snapshots = true;
+                },
+                "--capture-on-entry" => {
+                    cli.config.capture_on_entry = true;
+                },
+                "--capture-on-exit" => {
+                    cli.config.capture_on_exit = true;
+                },
+                "--capture-global" => {
+                    cli.config.capture_global_on_exit = true;
+                },
                 "--no-capture" => {
                     cli.config.capture_enabled = false;
                 },
+                "--export" => {
+                    if i + 1 < args.len() {
+                        cli.export_path = Some(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--export requires a file path"));
+                    }
+                },
+                "--export-sequence-diagram" => {
+                    if i + 1 < args.len() {
+                        cli.sequence_diagram_path = Some(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--export-sequence-diagram requires a file path"));
+                    }
+                },
+                "--trace-output" => {
+                    if i + 1 < args.len() {
+                        cli.trace_output_path = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--trace-output requires a file path"));
+                    }
+                },
+                "--export-call-graph" => {
+                    if i + 1 < args.len() {
+                        cli.call_graph_path = Some(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--export-call-graph requires a file path"));
+                    }
+                },
+                "--module-graph" => {
+                    if i + 1 < args.len() {
+                        cli.module_graph_path = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--module-graph requires a file path"));
+                    }
+                },
+                "--flamegraph" => {
+                    if i + 1 < args.len() {
+                        cli.flamegraph_path = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--flamegraph requires a file path"));
+                    }
+                },
+                "--cpu-profile" => {
+                    if i + 1 < args.len() {
+                        cli.cpu_profile_path = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--cpu-profile requires a file path"));
+                    }
+                },
+                "--export-otlp" => {
+                    if i + 1 < args.len() {
+                        cli.otlp_path = Some(args[i + 1].clone());
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--export-otlp requires a file path"));
+                    }
+                },
+                "--export-report" => {
+                    if i + 1 < args.len() {
+                        cli.report_path = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--export-report requires a file path"));
+                    }
+                },
+                "--test-mode" => {
+                    cli.test_mode = true;
+                },
+                "--config" => {
+                    // Already loaded by `config_file`
+                    i += 1;
+                },
+                "--output-format" => {
+                    if i + 1 < args.len() {
+                        cli.config.output_format = OutputFormat::parse(&args[i + 1])?;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--output-format requires a value"));
+                    }
+                },
+                "--log-format" => {
+                    if i + 1 < args.len() {
+                        cli.log_format = LogFormat::parse(&args[i + 1])?;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--log-format requires a value"));
+                    }
+                },
+                "--format" => {
+                    if i + 1 < args.len() {
+                        cli.export_format = ExportFormat::parse(&args[i + 1])?;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--format requires a value"));
+                    }
+                },
+                "--coverage-report" => {
+                    if i + 1 < args.len() {
+                        cli.line_coverage_path = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--coverage-report requires a file path"));
+                    }
+                },
+                "--coverage-output" => {
+                    if i + 1 < args.len() {
+                        cli.coverage_output = Some(PathBuf::from(&args[i + 1]));
+                        cli.config.collect_coverage = true;
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--coverage-output requires a file path"));
+                    }
+                },
+                "--trace-gc" => {
+                    cli.config.trace_gc = true;
+                },
+                "--trace-event-loop" => {
+                    cli.config.trace_event_loop = true;
+                },
+                "--trace-promises" => {
+                    cli.config.trace_promises = true;
+                },
+                "--capture-heap-stats" => {
+                    cli.config.capture_heap_stats = true;
+                },
+                "--coupling-analysis" => {
+                    if i + 1 < args.len() {
+                        cli.coupling_top_n = Some(args[i + 1].parse()
+                            .map_err(|_| anyhow!("Invalid coupling-analysis value"))?);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--coupling-analysis requires a value"));
+                    }
+                },
+                "--detect-leaks" => {
+                    if i + 1 < args.len() {
+                        cli.leak_growth_rate = Some(args[i + 1].parse()
+                            .ok()
+                            .filter(|rate: &f64| rate.is_finite() && *rate >= 0.0)
+                            .ok_or_else(|| anyhow!("Invalid detect-leaks rate"))?);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--detect-leaks requires a rate in bytes per call"));
+                    }
+                },
+                "--repl" => {
+                    cli.repl = true;
+                },
+                "--dap" => {
+                    cli.dap = true;
+                },
+                "--load-trace" => {
+                    if i + 1 < args.len() {
+                        cli.load_trace = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--load-trace requires a .ttd file"));
+                    }
+                },
+                "--merge" => {
+                    while i + 1 < args.len() && !args[i + 1].starts_with('-') {
+                        cli.merge_paths.push(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    }
+                    if cli.merge_paths.len() < 2 {
+                        return Err(anyhow!("--merge requires at least two .ttd files"));
+                    }
+                },
+                "--cat" => {
+                    while i + 1 < args.len() && !args[i + 1].starts_with('-') {
+                        cli.cat_paths.push(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    }
+                    if cli.cat_paths.len() < 2 {
+                        return Err(anyhow!("--cat requires an output file and at least one snapshot log"));
+                    }
+                },
+                "--merge-output" => {
+                    if i + 1 < args.len() {
+                        cli.merge_output = PathBuf::from(&args[i + 1]);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--merge-output requires a file path"));
+                    }
+                },
+                "--replay" => {
+                    if i + 1 < args.len() {
+                        cli.replay_baseline = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--replay requires a baseline file"));
+                    }
+                },
+                "--record-baseline" => {
+                    if i + 1 < args.len() {
+                        cli.record_baseline = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--record-baseline requires a file path"));
+                    }
+                },
+                "--compare-baseline" => {
+                    if i + 1 < args.len() {
+                        cli.compare_baseline = Some(PathBuf::from(&args[i + 1]));
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--compare-baseline requires a baseline file"));
+                    }
+                },
+                "--size-analysis" => {
+                    cli.size_analysis = true;
+                },
+                "--analyze-purity" => {
+                    cli.analyze_purity = true;
+                },
+                "--cluster-snapshots" => {
+                    if i + 1 < args.len() {
+                        let threshold: f64 = args[i + 1].parse()
+                            .map_err(|_| anyhow!("Invalid cluster-snapshots threshold"))?;
+                        if !(0.0..=1.0).contains(&threshold) {
+                            return Err(anyhow!("--cluster-snapshots threshold must be between 0.0 and 1.0"));
+                        }
+                        cli.cluster_threshold = Some(threshold);
+                        i += 1;
+                    } else {
+                        return Err(anyhow!("--cluster-snapshots requires a threshold"));
+                    }
+                },
+                "--script-args" => {
+                    // Everything that follows belongs to the debugged script
+                    cli.config.script_args = args[i + 1..].to_vec();
+                    break;
+                },
                 arg if !arg.starts_with('-') => {
                     file_path = Some(arg.to_string());
                 },
@@ -64,6 +595,54 @@ impl DebuggerCli {
             i += 1;
         }
 
+        logging::init(cli.log_format, cli.config.verbose);
+
+        if cli.dap {
+            // The program arrives in the client's `launch` request
+            return DapServer::stdio(cli.config)?.run().await;
+        }
+
+        if let Some((output, inputs)) = cli.cat_paths.split_first() {
+            let inputs: Vec<&Path> = inputs.iter().map(PathBuf::as_path).collect();
+            let stats = FileSnapshotStore::cat(&inputs, output)?;
+            cli.output().status("cat", &format!("🔀 Concatenated {} snapshot logs into {} ({} snapshots, {} bytes)",
+                stats.input_files, output.display(), stats.total_snapshots, stats.merged_size_bytes), json!({
+                "inputs": stats.input_files,
+                "path": output,
+                "snapshots": stats.total_snapshots,
+                "bytes": stats.merged_size_bytes,
+            }));
+            return Ok(());
+        }
+
+        if !cli.merge_paths.is_empty() {
+            let state = TraceMerger::merge_files(&cli.merge_paths)?;
+            StateSerializer::save(&state, &cli.merge_output)?;
+            cli.output().status("merge", &format!("🔀 Merged {} traces into {} ({} calls, {} snapshots)", cli.merge_paths.len(),
+                cli.merge_output.display(), state.function_call_history.len(), state.variable_snapshots.len()), json!({
+                "traces": cli.merge_paths.len(),
+                "path": cli.merge_output,
+                "calls": state.function_call_history.len(),
+                "snapshots": state.variable_snapshots.len(),
+            }));
+            if cli.repl {
+                return DebuggerRepl::new(state).run();
+            }
+            return Ok(());
+        }
+
+        if let Some(trace_path) = &cli.load_trace {
+            // Post-mortem: inspect a saved trace (e.g. a --crash-output file) without running
+            let state = StateSerializer::load(trace_path)?;
+            cli.output().status("load", &format!("📂 Loaded {} ({} calls, {} snapshots)", trace_path.display(),
+                state.function_call_history.len(), state.variable_snapshots.len()), json!({
+                "path": trace_path,
+                "calls": state.function_call_history.len(),
+                "snapshots": state.variable_snapshots.len(),
+            }));
+            return DebuggerRepl::new(state).run();
+        }
+
         // Validate file path
         let file_path = file_path.ok_or_else(|| anyhow!("No JavaScript file specified"))?;
         
@@ -75,35 +654,234 @@ impl DebuggerCli {
         cli.execute_file(&file_path).await
     }
 
+    /// `--config PATH` if given, else `.time-travel.toml` when the working directory has one
+    fn config_file(args: &[String]) -> Result<Option<PathBuf>> {
+        // Arguments after --script-args belong to the script
+        if let Some(position) = args.iter().take_while(|arg| *arg != "--script-args").position(|arg| arg == "--config") {
+            return args.get(position + 1)
+                .map(|path| Some(PathBuf::from(path)))
+                .ok_or_else(|| anyhow!("--config requires a file path"));
+        }
+        Ok(Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()))
+    }
+
+    fn output(&self) -> Output {
+        Output::new(self.config.output_format)
+    }
+
     /// Execute a JavaScript file with the debugger
     async fn execute_file(&self, file_path: &str) -> Result<()> {
-        println!("🚀 Time Travel Debugger starting...");
+        let output = self.output();
+        output.status("start", "🚀 Time Travel Debugger starting...", json!({ "file": file_path }));
         
-        if self.config.verbose {
-            println!("🔧 Configuration: {:?}", self.config);
-        }
+        tracing::debug!(config = ?self.config, "configuration");
 
         let mut runtime = TimeDebuggerRuntime::new(self.config.clone())?;
+        output.status("runtime", "[LOG] Time Travel Debugger Runtime v2.1 - JavaScript Value Serialization Enabled",
+            json!({ "version": "2.1", "value_serialization": true }));
+        // Load the baseline first so a bad file fails before the script runs
+        let baseline = self.replay_baseline.as_deref()
+            .map(|path| StateSerializer::load(path).map(|state| (path, state)))
+            .transpose()?;
+        let regression_baseline = self.compare_baseline.as_deref()
+            .map(|path| StateSerializer::load(path).map(|state| (path, state)))
+            .transpose()?;
+        let expectations = if self.test_mode {
+            let config_path = self.config_path.as_deref()
+                .ok_or_else(|| anyhow!("--test-mode needs a config file with a [test] section"))?;
+            Some((config_path, TestExpectations::from_toml(config_path)?))
+        } else {
+            None
+        };
         
-        match runtime.execute_file(file_path).await {
+        let result = match &self.coverage_output {
+            Some(coverage_output) => runtime.run_with_coverage(file_path, coverage_output).await
+                .map(|report| tracing::debug!("coverage\n{}", report.get_summary())),
+            None => runtime.execute_file(file_path).await,
+        };
+
+        match result {
             Ok(()) => {
-                if self.config.verbose {
-                    let state = runtime.get_execution_state();
-                    let state_ref = state.borrow();
-                    println!("📊 Execution Statistics:");
-                    println!("   - Function calls: {}", state_ref.function_calls);
-                    println!("   - Total time: {:?}", state_ref.total_execution_time);
+                {
+                    let state = runtime.get_execution_state().borrow();
+                    tracing::debug!(function_calls = state.function_calls,
+                        total_time = ?state.total_execution_time, "execution statistics");
+                }
+                self.report_results(&runtime, file_path)?;
+                if let Some((baseline_path, baseline)) = &baseline {
+                    let report = TraceComparator::compare(baseline, &runtime.get_execution_state().borrow());
+                    output.report("replay", &report.summary());
+                    if !report.is_match() {
+                        return Err(anyhow!("Replay diverged from {}", baseline_path.display()));
+                    }
+                }
+                if let Some((baseline_path, baseline)) = &regression_baseline {
+                    let regressions = RegressionDetector::detect(baseline, &runtime.get_execution_state().borrow());
+                    let regression_list: Vec<String> = regressions.iter().map(ToString::to_string).collect();
+                    let text = if regressions.is_empty() {
+                        format!("✅ Snapshots match {}", baseline_path.display())
+                    } else {
+                        format!("❌ Snapshot regressions against {}:{}", baseline_path.display(),
+                            regression_list.iter().map(|regression| format!("\n  - {}", regression)).collect::<String>())
+                    };
+                    output.status("regressions", &text, json!({ "baseline": baseline_path, "regressions": regression_list }));
+                    if !regressions.is_empty() {
+                        return Err(anyhow!("{} snapshot regressions against {}", regressions.len(), baseline_path.display()));
+                    }
+                }
+                if let Some((config_path, expectations)) = &expectations {
+                    let mismatches = expectations.check(&runtime.get_execution_state().borrow());
+                    let mismatch_list: Vec<String> = mismatches.iter().map(ToString::to_string).collect();
+                    let text = if mismatches.is_empty() {
+                        "✅ All [test] expectations met".to_string()
+                    } else {
+                        format!("❌ Test expectations from {} not met:{}", config_path.display(),
+                            mismatch_list.iter().map(|mismatch| format!("\n  - {}", mismatch)).collect::<String>())
+                    };
+                    output.status("expectations", &text, json!({ "config": config_path, "mismatches": mismatch_list }));
+                    if !mismatches.is_empty() {
+                        return Err(anyhow!("{} of the [test] expectations failed", mismatches.len()));
+                    }
+                }
+                output.finish(file_path, &runtime.get_execution_state().borrow());
+                if self.repl {
+                    let state = std::mem::take(&mut *runtime.get_execution_state().borrow_mut());
+                    DebuggerRepl::new(state).run()?;
                 }
-                println!("✅ Execution completed successfully");
                 Ok(())
             },
             Err(e) => {
-                eprintln!("❌ Execution failed: {}", e);
+                output.failure(file_path, &e);
                 Err(e)
             }
         }
     }
 
+    /// Run the post-execution exports and analyses requested on the command line
+    fn report_results(&self, runtime: &TimeDebuggerRuntime, file_path: &str) -> Result<()> {
+        let output = self.output();
+        if let Some(export_path) = &self.export_path {
+            self.export_state(runtime, export_path)?;
+        }
+
+        if let Some(baseline_path) = &self.record_baseline {
+            StateSerializer::save(&runtime.get_execution_state().borrow(), baseline_path)?;
+            output.status("export", &format!("💾 Recorded baseline to {}", baseline_path.display()), json!({ "kind": "baseline", "path": baseline_path }));
+        }
+
+        let state = runtime.get_execution_state().borrow();
+        if let Some(diagram_path) = &self.sequence_diagram_path {
+            let markdown = format!("```mermaid\n{}```\n",
+                state.generate_mermaid_sequence_diagram(SEQUENCE_DIAGRAM_MAX_CALLS));
+            std::fs::write(diagram_path, markdown)?;
+            output.status("export", &format!("💾 Exported sequence diagram to {}", diagram_path), json!({ "kind": "sequence_diagram", "path": diagram_path }));
+        }
+
+        if let Some(trace_path) = &self.trace_output_path {
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(trace_path)?);
+            state.export_ndjson(&mut writer)?;
+            std::io::Write::flush(&mut writer)?;
+            output.status("export", &format!("💾 Exported NDJSON trace to {}", trace_path.display()), json!({ "kind": "ndjson_trace", "path": trace_path }));
+        }
+
+        if let Some(graph_path) = &self.call_graph_path {
+            std::fs::write(graph_path, state.build_call_tree().to_dot())?;
+            output.status("export", &format!("💾 Exported call graph to {}", graph_path), json!({ "kind": "call_graph", "path": graph_path }));
+        }
+
+        if let Some(module_graph_path) = &self.module_graph_path {
+            std::fs::write(module_graph_path, runtime.module_graph_dot())?;
+            output.status("export", &format!("💾 Exported module graph to {} ({} modules loaded)", module_graph_path.display(), state.module_loads.len()), json!({ "kind": "module_graph", "path": module_graph_path }));
+        }
+
+        if let Some(flamegraph_path) = &self.flamegraph_path {
+            std::fs::write(flamegraph_path, state.to_folded_stacks())?;
+            output.status("export", &format!("💾 Exported folded stacks to {} (pipe into flamegraph.pl or inferno-flamegraph)", flamegraph_path.display()), json!({ "kind": "flamegraph", "path": flamegraph_path }));
+        }
+
+        if let Some(coverage_path) = &self.line_coverage_path {
+            // Calls are located by the script URL V8 reports, not the path as given
+            let script_url = deno_core::resolve_path(file_path, &std::env::current_dir()?)?;
+            let report = line_coverage_json(file_path, &state.line_coverage(script_url.as_str()));
+            std::fs::write(coverage_path, serde_json::to_string_pretty(&report)?)?;
+            output.status("export", &format!("💾 Exported line coverage to {}", coverage_path.display()), json!({ "kind": "line_coverage", "path": coverage_path }));
+        }
+
+        if let Some(profile_path) = &self.cpu_profile_path {
+            std::fs::write(profile_path, serde_json::to_vec(&state.to_cpu_profile())?)?;
+            output.status("export", &format!("💾 Exported CPU profile to {} (load it in Chrome DevTools' Performance tab)", profile_path.display()), json!({ "kind": "cpu_profile", "path": profile_path }));
+        }
+
+        if let Some(otlp_path) = &self.otlp_path {
+            let bytes = state.export_opentelemetry_traces()?;
+            std::fs::write(otlp_path, &bytes)?;
+            output.status("export", &format!("💾 Exported OTLP traces to {} ({} bytes)", otlp_path, bytes.len()), json!({ "kind": "otlp", "path": otlp_path }));
+        }
+
+        if let Some(report_path) = &self.report_path {
+            let title = format!("Time Travel Debugger report: {}", file_path);
+            state.export_markdown_report(report_path, &title)?;
+            output.status("export", &format!("💾 Exported markdown report to {}", report_path.display()), json!({ "kind": "markdown_report", "path": report_path }));
+        }
+
+        output.run_summary(&state, HOT_FUNCTIONS_SHOWN);
+
+        if let Some(threshold) = self.cluster_threshold {
+            output.section("clusters", &state.get_cluster_summary(threshold));
+        }
+
+        if self.config.trace_gc {
+            output.section("gc", &state.get_gc_summary());
+        }
+        if self.config.trace_event_loop {
+            output.section("event_loop", &state.get_event_loop_summary());
+        }
+        if self.config.trace_promises {
+            let promises: HashSet<&str> = state.promise_events.iter().map(|event| event.promise_id.as_str()).collect();
+            output.status("promises", &format!("\n🔗 Recorded {} promise events across {} promises", state.promise_events.len(), promises.len()),
+                json!({ "events": state.promise_events.len(), "promises": promises.len() }));
+        }
+        if self.config.capture_heap_stats {
+            let timeline = state.heap_stats_timeline();
+            if let (Some((_, first)), Some((_, last))) = (timeline.first(), timeline.last()) {
+                output.status("heap", &format!("\n📈 Used heap: {} → {} bytes over {} snapshots", first.used_heap_size, last.used_heap_size, timeline.len()),
+                    json!({ "first_used_bytes": first.used_heap_size, "last_used_bytes": last.used_heap_size, "snapshots": timeline.len() }));
+            }
+        }
+
+        if let Some(top_n) = self.coupling_top_n {
+            output.section("coupling", &state.get_coupling_summary(top_n));
+        }
+
+        if let Some(rate) = self.leak_growth_rate {
+            output.section("leaks", &state.get_leak_summary(rate));
+        }
+
+        if self.size_analysis {
+            output.section("size_analysis", &state.get_size_analysis(SIZE_ANALYSIS_TOP_N));
+        }
+
+        if self.analyze_purity {
+            output.section("purity", &PurityAnalyzer::summary(&PurityAnalyzer::analyze(&state)));
+        }
+
+        Ok(())
+    }
+
+    /// Write the recorded execution state to disk in the selected format
+    fn export_state(&self, runtime: &TimeDebuggerRuntime, export_path: &str) -> Result<()> {
+        let state = runtime.get_execution_state().borrow();
+        let bytes = match self.export_format {
+            ExportFormat::Json => state.serialize_to_json()?,
+            ExportFormat::Protobuf => state.serialize_to_protobuf()?,
+        };
+
+        std::fs::write(export_path, &bytes)?;
+        self.output().status("export", &format!("💾 Exported execution state to {} ({} bytes, {:?})",
+            export_path, bytes.len(), self.export_format), json!({ "kind": "execution_state", "path": export_path, "bytes": bytes.len() }));
+        Ok(())
+    }
+
     /// Print help information
     fn print_help() {
         println!("Time Travel Debugger v0.1.0");
@@ -116,15 +894,73 @@ impl DebuggerCli {
         println!("    <file.js>    JavaScript file to execute and debug");
         println!();
         println!("OPTIONS:");
-        println!("    -v, --verbose            Enable verbose output");
+        println!("        --config PATH        Read settings from a TOML file (default: ./.time-travel.toml if present)");
+        println!("        --test-mode          Fail unless the run meets the config file's [test] expectations");
+        println!("    -v, --verbose            Log at debug level (calls, captures, configuration)");
+        println!("        --log-format FORMAT  Log record layout on stderr: text (default), json or compact");
+        println!("        --output-format F    Results on stdout: text (default), json (one object per line) or compact");
         println!("    -h, --help               Print help information");
         println!("        --version            Print version information");
+        println!("        --snapshot-store S   memory (default) or file:PATH to also log every snapshot to disk");
+        println!("        --write-batch-size N Snapshots written to the --snapshot-store file at a time (default: 100)");
+        println!("        --dedup-snapshots    Skip snapshots identical to the one captured just before");
+        println!("        --max-call-depth N   Throw in the script when calls nest deeper than N");
+        println!("        --warn-on-max-depth  Only warn when --max-call-depth is exceeded");
+        println!("        --repeated-call-threshold N  Warn on more than N identical calls in a row");
+        println!("        --crash-output PATH  Save the partial trace here on Ctrl-C/SIGTERM (default: partial_trace.ttd)");
+        println!("        --trace-id ID        Stamp ID on every call and snapshot (timeDebugger.traceId), to correlate processes");
+        println!("        --update-snapshots   Overwrite values saved by timeDebugger.assertSnapshot in .snapshots/");
         println!("        --max-snapshots N    Maximum number of snapshots to keep (default: 1000)");
+        println!("        --eviction-policy P  At max-snapshots: oldest (default), lowest-depth or error");
+        println!("        --max-snapshot-memory N  Evict the oldest snapshots beyond an estimated N MB");
+        println!("        --max-memory-mb N    Shrink max-snapshots adaptively when RSS nears N MB");
         println!("        --no-capture         Disable state capture (run in normal mode)");
+        println!("        --trace-method PATH  Trace calls to a built-in method, e.g. Map.prototype.set (repeatable)");
+        println!("        --capture-filter RE  Only record calls to functions whose name matches RE");
+        println!("        --capture-on-entry   Snapshot globals before any user code runs");
+        println!("        --capture-on-exit    Snapshot globals and main module exports after execution");
+        println!("        --capture-global     Snapshot all of globalThis (minus built-ins) after execution");
+        println!("        --width N            Output width for traces and values (default: terminal width)");
+        println!("        --alias NAME=ALIAS   Show variable NAME as ALIAS in snapshots (repeatable)");
+        println!("        --export PATH        Write the execution state to PATH after execution");
+        println!("        --format FORMAT      Export format: json (default) or protobuf");
+        println!("        --trace-output PATH  Write calls and snapshots as NDJSON (one record per line)");
+        println!("        --export-call-graph PATH  Write the call tree as a Graphviz DOT file");
+        println!("        --module-graph PATH  Write the module import graph, with load order, as a DOT file");
+        println!("        --flamegraph PATH    Write folded stacks for flamegraph.pl / inferno");
+        println!("        --cpu-profile PATH   Write a Chrome DevTools .cpuprofile of the recorded calls");
+        println!("        --export-otlp PATH   Write an OTLP/protobuf trace (Jaeger, Zipkin, collectors)");
+        println!("        --export-report PATH Write a markdown report for sharing in bug reports");
+        println!("        --export-sequence-diagram PATH  Write a Mermaid sequence diagram (markdown) to PATH");
+        println!("        --cluster-snapshots T  Group similar snapshots (similarity threshold 0.0-1.0)");
+        println!("        --coupling-analysis N  Show the N function pairs that most often run together");
+        println!("        --detect-leaks RATE  Flag variables growing faster than RATE bytes per call");
+        println!("        --replay FILE        Re-run and compare calls and snapshots with a saved .ttd baseline");
+        println!("        --record-baseline FILE   Save the run's state as a .ttd baseline for CI");
+        println!("        --compare-baseline FILE  Fail on the first snapshot that differs from a .ttd baseline");
+        println!("        --repl               Inspect snapshots interactively after execution");
+        println!("        --load-trace FILE    Open the REPL on a saved .ttd trace instead of running a script");
+        println!("        --merge FILE FILE... Combine saved .ttd traces (e.g. parallel test runs) instead of running a script");
+        println!("        --merge-output PATH  Where --merge writes the combined trace (default: merged.ttd)");
+        println!("        --cat OUT IN IN...   Merge --snapshot-store logs into OUT, ordered by timestamp");
+        println!("        --dap                Serve the Debug Adapter Protocol on stdin/stdout (program comes from launch)");
+        println!("        --size-analysis      Report the largest snapshots and a size histogram");
+        println!("        --analyze-purity     Report functions that returned the same value for the same arguments");
+        println!("        --script-args <args...>  Pass the remaining arguments to the script as Deno.args");
+        println!("        --trace-gc           Record V8 garbage collection pauses");
+        println!("        --trace-event-loop   Sample op dispatch/completion counters during execution");
+        println!("        --trace-promises     Record promise creation, chaining and settlement");
+        println!("        --capture-heap-stats Record V8 heap usage with every variable snapshot");
+        println!("        --coverage-output PATH  Collect V8 coverage and write an Istanbul JSON report");
+        println!("        --coverage-report PATH  Write per-line hit counts estimated from the recorded calls");
         println!();
         println!("EXAMPLES:");
         println!("    time_travel_debugger examples/basic.js");
         println!("    time_travel_debugger --verbose --max-snapshots 500 script.js");
         println!("    time_travel_debugger --no-capture fast_script.js");
+        println!("    time_travel_debugger --alias a=userBalance --alias b=accountId bundle.min.js");
+        println!("    time_travel_debugger --export trace.pb --format protobuf script.js");
+        println!("    time_travel_debugger src/examples/script_args.js --script-args production 3");
+        println!("    time_travel_debugger --merge shard1.ttd shard2.ttd --merge-output all.ttd --repl");
     }
 } 
\ No newline at end of file
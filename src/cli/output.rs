@@ -0,0 +1,143 @@
+use crate::runtime::{ExecutionState, OutputFormat};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+/// Writes the CLI's messages and reports to stdout in the selected `OutputFormat`
+pub struct Output {
+    format: OutputFormat,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+
+    /// A one-line message: `text` as-is in text mode, `fields` tagged with `"type": kind`
+    /// in JSON mode, nothing in compact mode
+    pub fn status(&self, kind: &str, text: &str, fields: Value) {
+        match self.format {
+            OutputFormat::Text => println!("{}", text),
+            OutputFormat::Json => println!("{}", tagged(kind, fields)),
+            OutputFormat::Compact => {},
+        }
+    }
+
+    /// A multi-line report from the runtime, whose first line is its emoji-prefixed title.
+    /// JSON mode splits it into `title` and `lines`; compact mode leaves it out.
+    pub fn section(&self, kind: &str, report: &str) {
+        match self.format {
+            OutputFormat::Text => println!("\n{}", report),
+            OutputFormat::Json => println!("{}", section_json(kind, report)),
+            OutputFormat::Compact => {},
+        }
+    }
+
+    /// Like `section`, but printed in text mode without the blank line before it
+    pub fn report(&self, kind: &str, report: &str) {
+        match self.format {
+            OutputFormat::Text => print!("{}", report),
+            OutputFormat::Json => println!("{}", section_json(kind, report)),
+            OutputFormat::Compact => {},
+        }
+    }
+
+    /// Execution stats, the most called functions and snapshot counts once a run finishes.
+    /// Text mode shows the hot functions section; JSON mode one object for each.
+    pub fn run_summary(&self, state: &ExecutionState, hot_functions_shown: usize) {
+        match self.format {
+            OutputFormat::Text => {
+                if !state.function_call_counts.is_empty() {
+                    self.section("hot_functions", &state.get_hot_functions_summary(hot_functions_shown));
+                }
+            },
+            OutputFormat::Json => {
+                let hot_functions: Vec<Value> = state.hot_functions(hot_functions_shown).into_iter()
+                    .map(|(name, count)| json!({ "name": name, "count": count }))
+                    .collect();
+                let mut by_type: BTreeMap<&str, usize> = BTreeMap::new();
+                for snapshot in state.variable_snapshots.iter() {
+                    *by_type.entry(&snapshot.snapshot_type).or_default() += 1;
+                }
+                println!("{}", tagged("execution_stats", execution_stats(state)));
+                println!("{}", tagged("function_calls", json!({
+                    "total": state.function_calls,
+                    "distinct": state.function_call_counts.len(),
+                    "hottest": hot_functions,
+                })));
+                println!("{}", tagged("snapshots", json!({
+                    "total": state.variable_snapshots.len(),
+                    "by_type": by_type,
+                    "evicted": state.eviction_count,
+                })));
+            },
+            OutputFormat::Compact => {},
+        }
+    }
+
+    /// The end of a run: `✅ ...` in text mode, a `result` object in JSON mode and the
+    /// single summary line in compact mode
+    pub fn finish(&self, file_path: &str, state: &ExecutionState) {
+        match self.format {
+            OutputFormat::Text => println!("✅ Execution completed successfully"),
+            OutputFormat::Json => println!("{}", tagged("result", json!({ "status": "ok", "file": file_path }))),
+            OutputFormat::Compact => println!("ok {} calls={} snapshots={} exceptions={} time={:.3}ms",
+                file_path, state.function_calls, state.variable_snapshots.len(), state.exceptions.len(),
+                state.total_execution_time.as_secs_f64() * 1000.0),
+        }
+    }
+
+    /// A run that failed. Text mode reports on stderr as before.
+    pub fn failure(&self, file_path: &str, error: &anyhow::Error) {
+        match self.format {
+            OutputFormat::Text => eprintln!("❌ Execution failed: {}", error),
+            OutputFormat::Json => println!("{}", tagged("result", json!({
+                "status": "error", "file": file_path, "error": error.to_string(),
+            }))),
+            OutputFormat::Compact => println!("error {} {}", file_path, error),
+        }
+    }
+}
+
+fn execution_stats(state: &ExecutionState) -> Value {
+    json!({
+        "function_calls": state.function_calls,
+        "total_time_ms": state.total_execution_time.as_secs_f64() * 1000.0,
+        "max_call_depth": state.max_depth_reached,
+        "snapshots": state.variable_snapshots.len(),
+        "exceptions": state.exceptions.len(),
+    })
+}
+
+/// `fields` (an object) with `"type": kind` added
+fn tagged(kind: &str, fields: Value) -> Value {
+    let mut object = serde_json::Map::new();
+    object.insert("type".to_string(), Value::from(kind));
+    if let Value::Object(fields) = fields {
+        object.extend(fields);
+    }
+    Value::Object(object)
+}
+
+fn section_json(kind: &str, report: &str) -> Value {
+    let mut lines = report.lines();
+    let title = lines.next().unwrap_or_default()
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .trim_end_matches(':');
+    let lines: Vec<&str> = lines.map(str::trim).filter(|line| !line.is_empty()).collect();
+    tagged(kind, json!({ "title": title, "lines": lines }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_json_drops_the_emoji() {
+        let section = section_json("gc", "🗑️  GARBAGE COLLECTION (2 pauses):\n  Minor: 1 pauses\n\n  Major: 1 pauses\n");
+        assert_eq!(section, json!({
+            "type": "gc",
+            "title": "GARBAGE COLLECTION (2 pauses)",
+            "lines": ["Minor: 1 pauses", "Major: 1 pauses"],
+        }));
+    }
+}
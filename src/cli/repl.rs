@@ -0,0 +1,158 @@
+use crate::runtime::ExecutionState;
+use anyhow::{Result, anyhow};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+/// Interactive prompt for inspecting the snapshots of a finished execution
+pub struct DebuggerRepl {
+    state: ExecutionState,
+}
+
+impl DebuggerRepl {
+    pub fn new(state: ExecutionState) -> Self {
+        Self { state }
+    }
+
+    /// Read commands until `quit` or end of input
+    pub fn run(&mut self) -> Result<()> {
+        let mut editor = DefaultEditor::new()?;
+        println!("🔎 {} snapshots recorded. Type `help` for commands.", self.state.variable_snapshots.len());
+
+        loop {
+            let line = match editor.readline("ttd> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            editor.add_history_entry(line)?;
+            if matches!(line, "quit" | "exit" | "q") {
+                break;
+            }
+
+            match self.execute(line) {
+                Ok(output) => print!("{}", output),
+                Err(e) => println!("❌ {}", e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run a single command and return its output
+    pub fn execute(&self, line: &str) -> Result<String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+
+        match (command, args.as_slice()) {
+            ("help", []) => Ok(Self::help()),
+            ("snapshots", []) => Ok(self.list_snapshots()),
+            ("inspect", [index]) => self.inspect(parse_index(index)?),
+            ("find", [name]) => Ok(self.find(name)),
+            ("diff", [from, to]) => self.diff(parse_index(from)?, parse_index(to)?),
+            _ => Err(anyhow!("Unknown command: {} (type `help` for commands)", line)),
+        }
+    }
+
+    fn help() -> String {
+        [
+            "  snapshots         List all snapshots",
+            "  inspect <i>       Show every variable in snapshot i",
+            "  find <name>       List snapshots that captured variable <name>",
+            "  diff <i> <j>      Show variables that changed between snapshots i and j",
+            "  quit              Leave the REPL",
+        ].iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    fn list_snapshots(&self) -> String {
+        if self.state.variable_snapshots.is_empty() {
+            return "  No snapshots recorded\n".to_string();
+        }
+        self.state.variable_snapshots.iter().enumerate()
+            .map(|(index, snapshot)| format!("  #{} {} [{}] - {} vars\n",
+                index, snapshot.function_name, snapshot.snapshot_type, snapshot.variables.len()))
+            .collect()
+    }
+
+    fn inspect(&self, index: usize) -> Result<String> {
+        let snapshot = self.state.reconstruct_snapshot(index)
+            .ok_or_else(|| anyhow!("No snapshot at index {}", index))?;
+
+        let mut output = format!("📸 #{} {} [{}]\n", index, snapshot.function_name, snapshot.snapshot_type);
+        let mut names: Vec<&String> = snapshot.variables.keys().collect();
+        names.sort();
+        for name in names {
+            output.push_str(&format!("  {} = {}\n",
+                self.state.display_name(name), snapshot.variables[name].to_display_string()));
+        }
+        Ok(output)
+    }
+
+    fn find(&self, name: &str) -> String {
+        let matches: Vec<String> = self.state.variable_snapshots.iter().enumerate()
+            .filter_map(|(index, snapshot)| snapshot.variables.get(name).map(|value| {
+                format!("  #{} {} [{}]: {}\n",
+                    index, snapshot.function_name, snapshot.snapshot_type, value.to_display_string())
+            }))
+            .collect();
+
+        if matches.is_empty() {
+            format!("  {} was never captured\n", name)
+        } else {
+            matches.concat()
+        }
+    }
+
+    fn diff(&self, from: usize, to: usize) -> Result<String> {
+        let before = self.state.reconstruct_snapshot(from)
+            .ok_or_else(|| anyhow!("No snapshot at index {}", from))?;
+        let after = self.state.reconstruct_snapshot(to)
+            .ok_or_else(|| anyhow!("No snapshot at index {}", to))?;
+
+        Ok(before.diff(&after).to_display_string())
+    }
+}
+
+fn parse_index(value: &str) -> Result<usize> {
+    value.parse().map_err(|_| anyhow!("Invalid snapshot index: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::VariableSnapshot;
+    use crate::runtime::serialization::JSValue;
+    use std::collections::HashMap;
+
+    fn snapshot(variables: &[(&str, f64)]) -> VariableSnapshot {
+        VariableSnapshot {
+            timestamp: 0.0,
+            function_name: "tick".to_string(),
+            call_depth: 1,
+            variables: variables.iter().map(|(name, value)| (name.to_string(), JSValue::Number(*value))).collect(),
+            snapshot_type: "custom".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        }
+    }
+
+    #[test]
+    fn test_repl_commands() {
+        let mut state = ExecutionState::default();
+        state.variable_snapshots.push(snapshot(&[("count", 1.0), ("old", 0.0)]));
+        state.variable_snapshots.push(snapshot(&[("count", 2.0), ("new", 5.0)]));
+        let repl = DebuggerRepl::new(state);
+
+        assert_eq!(repl.execute("snapshots").unwrap(), "  #0 tick [custom] - 2 vars\n  #1 tick [custom] - 2 vars\n");
+        assert!(repl.execute("inspect 1").unwrap().contains("  count = 2\n"));
+        assert_eq!(repl.execute("find old").unwrap(), "  #0 tick [custom]: 0\n");
+        assert_eq!(repl.execute("diff 0 1").unwrap(), "  ~ count: 1 → 2\n  + new: 5\n  - old: 0\n");
+        assert!(repl.execute("inspect 9").is_err());
+        assert!(repl.execute("bogus").is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use crate::runtime::{DebuggerConfig, ExecutionState, TimeDebuggerRuntime, VariableSnapshot};
+use crate::runtime::serialization::JSValue;
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+
+/// The debugged program is single-threaded, so every request targets this thread
+const THREAD_ID: i64 = 1;
+
+/// Debug Adapter Protocol server for editors such as VS Code.
+///
+/// The program runs to completion once the client has sent its breakpoints; the
+/// session then stops at each recorded call to a breakpointed function in turn, so
+/// `continue` steps forward through the recording rather than through live code.
+pub struct DapServer<R, W> {
+    reader: R,
+    writer: W,
+    seq: i64,
+    config: DebuggerConfig,
+    program: Option<String>,
+    configured: bool,
+    launched: bool,
+    disconnected: bool,
+    /// Function names from `setBreakpoints`, per source path
+    source_breakpoints: HashMap<String, Vec<String>>,
+    function_breakpoints: Vec<String>,
+    state: ExecutionState,
+    /// Indices into `function_call_history` the session stops at
+    stops: Vec<usize>,
+    position: usize,
+    /// Values behind each `variablesReference`; reference `n` is entry `n - 1`
+    variables: Vec<Vec<(String, JSValue)>>,
+}
+
+impl DapServer<std::io::BufReader<std::io::Stdin>, std::fs::File> {
+    /// Serve over stdin/stdout. The program's own output (and the debugger's) is moved
+    /// to stderr so it cannot corrupt the protocol stream.
+    pub fn stdio(config: DebuggerConfig) -> Result<Self> {
+        let writer = take_stdout()?;
+        Ok(Self::new(std::io::BufReader::new(std::io::stdin()), writer, config))
+    }
+}
+
+impl<R: BufRead, W: Write> DapServer<R, W> {
+    pub fn new(reader: R, writer: W, config: DebuggerConfig) -> Self {
+        Self {
+            reader,
+            writer,
+            seq: 0,
+            config,
+            program: None,
+            configured: false,
+            launched: false,
+            disconnected: false,
+            source_breakpoints: HashMap::new(),
+            function_breakpoints: Vec::new(),
+            state: ExecutionState::default(),
+            stops: Vec::new(),
+            position: 0,
+            variables: Vec::new(),
+        }
+    }
+
+    /// Handle requests until the client disconnects or closes the stream
+    pub async fn run(&mut self) -> Result<()> {
+        while let Some(message) = read_message(&mut self.reader)? {
+            self.handle(&message)?;
+            if self.disconnected {
+                break;
+            }
+
+            if self.configured && !self.launched && let Some(program) = self.program.clone() {
+                self.launched = true;
+                match self.record(&program).await {
+                    Ok(state) => self.load_recording(state)?,
+                    Err(e) => {
+                        self.send_event("output", json!({ "category": "stderr", "output": format!("❌ Execution failed: {}\n", e) }))?;
+                        self.send_event("terminated", json!({}))?;
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute `program` with the breakpointed functions as capture triggers
+    async fn record(&self, program: &str) -> Result<ExecutionState> {
+        let mut runtime = TimeDebuggerRuntime::new(self.config.clone())?;
+        runtime.get_execution_state().borrow_mut().breakpoints = self.breakpoint_names();
+        runtime.execute_file(program).await?;
+        Ok(std::mem::take(&mut *runtime.get_execution_state().borrow_mut()))
+    }
+
+    /// Replace the recording being inspected and stop at its first breakpoint hit
+    fn load_recording(&mut self, state: ExecutionState) -> Result<()> {
+        self.state = state;
+        self.state.breakpoints = self.breakpoint_names();
+        self.stops = self.state.breakpoint_hits();
+        self.position = 0;
+        self.send_event("output", json!({
+            "category": "console",
+            "output": format!("🔎 Recorded {} calls and {} snapshots; {} breakpoint hits\n",
+                self.state.function_call_history.len(), self.state.variable_snapshots.len(), self.stops.len()),
+        }))?;
+        self.stop_or_terminate()
+    }
+
+    fn stop_or_terminate(&mut self) -> Result<()> {
+        self.variables.clear();
+        if self.position < self.stops.len() {
+            self.send_event("stopped", json!({ "reason": "breakpoint", "threadId": THREAD_ID, "allThreadsStopped": true }))
+        } else {
+            self.send_event("terminated", json!({}))
+        }
+    }
+
+    /// Answer one request, sending any events it triggers
+    pub fn handle(&mut self, request: &Value) -> Result<()> {
+        let command = request["command"].as_str().unwrap_or_default().to_string();
+        let arguments = &request["arguments"];
+
+        let body = match command.as_str() {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsFunctionBreakpoints": true,
+            })),
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setFunctionBreakpoints" => Ok(self.set_function_breakpoints(arguments)),
+            "configurationDone" => {
+                self.configured = true;
+                Ok(json!({}))
+            },
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => self.scopes(arguments),
+            "variables" => self.variables(arguments),
+            "continue" => Ok(json!({ "allThreadsContinued": true })),
+            "disconnect" => {
+                self.disconnected = true;
+                Ok(json!({}))
+            },
+            _ => Err(anyhow!("Unsupported request: {}", command)),
+        };
+
+        self.send_response(request, &command, body)?;
+        match command.as_str() {
+            "initialize" => self.send_event("initialized", json!({})),
+            "continue" if self.position < self.stops.len() => {
+                self.position += 1;
+                self.stop_or_terminate()
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn launch(&mut self, arguments: &Value) -> Result<Value> {
+        let program = arguments["program"].as_str()
+            .ok_or_else(|| anyhow!("launch requires a `program` path"))?;
+        if !std::path::Path::new(program).exists() {
+            return Err(anyhow!("File not found: {}", program));
+        }
+        if let Some(args) = arguments["args"].as_array() {
+            self.config.script_args = args.iter().filter_map(|arg| arg.as_str().map(str::to_string)).collect();
+        }
+        self.program = Some(program.to_string());
+        Ok(json!({}))
+    }
+
+    /// A line breakpoint becomes a breakpoint on the function declared on that line
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value> {
+        let path = arguments["source"]["path"].as_str()
+            .ok_or_else(|| anyhow!("setBreakpoints requires a source path"))?;
+        let source = std::fs::read_to_string(path)?;
+        let lines: Vec<&str> = source.lines().collect();
+
+        let mut names = Vec::new();
+        let breakpoints: Vec<Value> = arguments["breakpoints"].as_array().into_iter().flatten()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_u64().unwrap_or_default();
+                let name = (line as usize).checked_sub(1)
+                    .and_then(|index| lines.get(index))
+                    .and_then(|text| declared_function(text));
+                match name {
+                    Some(name) => {
+                        names.push(name.clone());
+                        json!({ "verified": true, "line": line, "message": format!("Stops when {} is entered", name) })
+                    },
+                    None => json!({ "verified": false, "line": line, "message": "No function declared on this line" }),
+                }
+            })
+            .collect();
+
+        self.source_breakpoints.insert(path.to_string(), names);
+        self.state.breakpoints = self.breakpoint_names();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Value) -> Value {
+        self.function_breakpoints = arguments["breakpoints"].as_array().into_iter().flatten()
+            .filter_map(|breakpoint| breakpoint["name"].as_str().map(str::to_string))
+            .collect();
+        self.state.breakpoints = self.breakpoint_names();
+        let breakpoints: Vec<Value> = self.function_breakpoints.iter().map(|_| json!({ "verified": true })).collect();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn breakpoint_names(&self) -> HashSet<String> {
+        self.source_breakpoints.values().flatten()
+            .chain(&self.function_breakpoints)
+            .cloned()
+            .collect()
+    }
+
+    /// Frames of the current stop; each frame id is the call's index in the history
+    fn stack_trace(&self) -> Value {
+        let Some(&stop) = self.stops.get(self.position) else {
+            return json!({ "stackFrames": [], "totalFrames": 0 });
+        };
+
+        let frames: Vec<Value> = self.state.call_stack_at(stop).into_iter()
+            .map(|call| {
+                let index = self.state.function_call_history.iter()
+                    .position(|candidate| std::ptr::eq(candidate, call))
+                    .unwrap_or(stop);
+                let mut frame = json!({
+                    "id": index,
+                    "name": call.name,
+                    "line": call.line_number.unwrap_or(0),
+                    "column": 0,
+                });
+                if let Some(location) = &call.file_location {
+                    frame["source"] = json!({ "path": location.strip_prefix("file://").unwrap_or(location) });
+                }
+                frame
+            })
+            .collect();
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    /// `Arguments` from the call itself and `Locals` from its entry snapshot
+    fn scopes(&mut self, arguments: &Value) -> Result<Value> {
+        let frame_id = arguments["frameId"].as_u64().ok_or_else(|| anyhow!("scopes requires a frameId"))? as usize;
+        let call = self.state.function_call_history.get(frame_id)
+            .ok_or_else(|| anyhow!("Unknown frame: {}", frame_id))?;
+
+        let call_arguments: Vec<(String, JSValue)> = call.arg_values.iter().enumerate()
+            .map(|(index, value)| (format!("arg{}", index), value.clone()))
+            .collect();
+        let locals = self.entry_snapshot(frame_id).map(|snapshot| {
+            let mut locals: Vec<(String, JSValue)> = snapshot.variables.iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            locals.sort_by(|a, b| a.0.cmp(&b.0));
+            locals
+        }).unwrap_or_default();
+
+        let scopes = [("Arguments", call_arguments), ("Locals", locals)].into_iter()
+            .map(|(name, variables)| json!({
+                "name": name,
+                "variablesReference": self.reference(variables),
+                "expensive": false,
+            }))
+            .collect::<Vec<_>>();
+        Ok(json!({ "scopes": scopes }))
+    }
+
+    /// The `entry` snapshot captured by the call at `call_index`, if any
+    fn entry_snapshot(&self, call_index: usize) -> Option<&VariableSnapshot> {
+        let index = self.state.function_call_to_snapshot_index(call_index)?;
+        Some(&self.state.variable_snapshots[index]).filter(|snapshot| snapshot.snapshot_type == "entry")
+    }
+
+    fn variables(&mut self, arguments: &Value) -> Result<Value> {
+        let reference = arguments["variablesReference"].as_u64().unwrap_or_default() as usize;
+        let entries = reference.checked_sub(1)
+            .and_then(|index| self.variables.get(index))
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown variablesReference: {}", reference))?;
+
+        let variables: Vec<Value> = entries.into_iter()
+            .map(|(name, value)| json!({
+                "name": name,
+                "value": value.to_display_string(),
+                "type": value.type_name(),
+                "variablesReference": self.reference(children(&value)),
+            }))
+            .collect();
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Register `variables` for a later `variables` request; 0 (not expandable) when empty
+    fn reference(&mut self, variables: Vec<(String, JSValue)>) -> usize {
+        if variables.is_empty() {
+            return 0;
+        }
+        self.variables.push(variables);
+        self.variables.len()
+    }
+
+    fn send_response(&mut self, request: &Value, command: &str, body: Result<Value>) -> Result<()> {
+        self.seq += 1;
+        let mut response = json!({
+            "seq": self.seq,
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+        });
+        match body {
+            Ok(body) => {
+                response["success"] = json!(true);
+                response["body"] = body;
+            },
+            Err(e) => {
+                response["success"] = json!(false);
+                response["message"] = json!(e.to_string());
+            },
+        }
+        write_message(&mut self.writer, &response)
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.seq += 1;
+        write_message(&mut self.writer, &json!({ "seq": self.seq, "type": "event", "event": event, "body": body }))
+    }
+}
+
+/// Name of the function declared on a source line: `function foo(`, `foo = function`,
+/// `foo = (...) =>`, `foo = x =>` or a `foo(...) {` method
+fn declared_function(line: &str) -> Option<String> {
+    let patterns = [
+        r"\bfunction\s*\*?\s*([A-Za-z_$][\w$]*)\s*\(",
+        r"([A-Za-z_$][\w$]*)\s*[=:]\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*=>|[A-Za-z_$][\w$]*\s*=>)",
+        r"^\s*(?:static\s+)?(?:async\s+)?([A-Za-z_$][\w$]*)\s*\([^)]*\)\s*\{",
+    ];
+    patterns.iter().find_map(|pattern| {
+        Regex::new(pattern).ok()?
+            .captures(line)
+            .map(|captures| captures[1].to_string())
+            .filter(|name| !matches!(name.as_str(), "if" | "for" | "while" | "switch" | "catch" | "function"))
+    })
+}
+
+/// Expandable members of a value, in display order
+fn children(value: &JSValue) -> Vec<(String, JSValue)> {
+    match value {
+        JSValue::Object(properties) => {
+            let mut members: Vec<(String, JSValue)> = properties.iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+            members
+        },
+        JSValue::Array(elements) | JSValue::Set(elements) => elements.iter().enumerate()
+            .map(|(index, value)| (index.to_string(), value.clone()))
+            .collect(),
+        JSValue::Map(entries) => entries.iter()
+            .map(|(key, value)| (key.to_display_string(), value.clone()))
+            .collect(),
+        JSValue::TypedArray { elements, .. } => elements.iter().enumerate()
+            .map(|(index, value)| (index.to_string(), JSValue::Number(*value)))
+            .collect(),
+        JSValue::Promise { value: Some(value), .. } => vec![("[[PromiseResult]]".to_string(), (**value).clone())],
+        _ => Vec::new(),
+    }
+}
+
+/// Read one `Content-Length` framed message; `None` at end of input
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("Content-Length") {
+            content_length = Some(value.trim().parse::<usize>()
+                .map_err(|_| anyhow!("Invalid Content-Length: {}", value.trim()))?);
+        }
+    }
+
+    let length = content_length.ok_or_else(|| anyhow!("DAP message is missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Keep a private handle on stdout for the protocol and point file descriptor 1 at stderr
+#[cfg(unix)]
+fn take_stdout() -> Result<std::fs::File> {
+    use std::os::fd::{AsFd, AsRawFd};
+
+    let protocol = std::io::stdout().as_fd().try_clone_to_owned()?;
+    // SAFETY: both descriptors are open for the life of the process
+    if unsafe { libc::dup2(std::io::stderr().as_raw_fd(), std::io::stdout().as_raw_fd()) } < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(std::fs::File::from(protocol))
+}
+
+#[cfg(not(unix))]
+fn take_stdout() -> Result<std::fs::File> {
+    Err(anyhow!("--dap is only supported on Unix platforms"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::FunctionCall;
+    use std::io::Cursor;
+
+    fn call(name: &str, depth: usize, timestamp: f64) -> FunctionCall {
+        FunctionCall {
+            name: name.to_string(),
+            timestamp,
+            call_depth: depth,
+            arguments: vec!["2".to_string()],
+            arg_values: vec![JSValue::Number(2.0)],
+            file_location: Some("file:///app/main.js".to_string()),
+            line_number: Some(3),
+            duration_ms: None,
+            return_value: None,
+            call_count: 1,
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn test_session_steps_through_breakpoint_hits() {
+        let mut server = DapServer::new(Cursor::new(Vec::new()), Vec::new(), DebuggerConfig::default());
+        server.handle(&json!({ "seq": 1, "command": "setFunctionBreakpoints", "arguments": { "breakpoints": [{ "name": "square" }] } })).unwrap();
+
+        let mut state = ExecutionState {
+            function_call_history: vec![call("main", 1, 1.0), call("square", 2, 2.0), call("square", 2, 3.0)],
+            ..Default::default()
+        };
+        state.variable_snapshots.push(VariableSnapshot {
+            timestamp: 2.0,
+            function_name: "square".to_string(),
+            call_depth: 2,
+            variables: HashMap::from([("point".to_string(), JSValue::Object(HashMap::from([("x".to_string(), JSValue::Number(1.0))])))]),
+            snapshot_type: "entry".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        });
+        server.load_recording(state).unwrap();
+        assert_eq!(server.stops, [1, 2]);
+
+        let trace = server.stack_trace();
+        let names: Vec<&str> = trace["stackFrames"].as_array().unwrap().iter().map(|f| f["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["square", "main"]);
+        assert_eq!(trace["stackFrames"][0]["source"]["path"], "/app/main.js");
+
+        let scopes = server.scopes(&json!({ "frameId": 1 })).unwrap();
+        let locals = scopes["scopes"][1]["variablesReference"].as_u64().unwrap();
+        let variables = server.variables(&json!({ "variablesReference": locals })).unwrap();
+        assert_eq!(variables["variables"][0]["name"], "point");
+        let point = variables["variables"][0]["variablesReference"].as_u64().unwrap();
+        let members = server.variables(&json!({ "variablesReference": point })).unwrap();
+        assert_eq!(members["variables"][0]["value"], "1");
+
+        server.handle(&json!({ "seq": 2, "command": "continue" })).unwrap();
+        server.handle(&json!({ "seq": 3, "command": "continue" })).unwrap();
+        let mut output = Cursor::new(std::mem::take(&mut server.writer));
+        let mut events = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            if message["type"] == "event" {
+                events.push(message["event"].as_str().unwrap().to_string());
+            }
+        }
+        assert_eq!(events, ["output", "stopped", "stopped", "terminated"]);
+
+        assert_eq!(declared_function("  const add = (a, b) => a + b;").as_deref(), Some("add"));
+        assert_eq!(declared_function("async function load(url) {").as_deref(), Some("load"));
+        assert_eq!(declared_function("  if (x) {"), None);
+    }
+}
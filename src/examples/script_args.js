@@ -0,0 +1,16 @@
+// Script arguments test: run with --script-args <mode> [count]
+console.log("🚀 Script Arguments Test");
+
+const mode = Deno.args[0];
+const count = Number(Deno.args[1] ?? 1);
+
+if (mode === undefined) {
+    throw new Error("Expected a mode argument (pass --script-args <mode>)");
+}
+
+const greet = timeDebugger.captureFunction(function greet(name, times) {
+    return Array(times).fill(`hello from ${name}`).join(", ");
+}, "greet");
+
+console.log("Deno.args:", JSON.stringify(Deno.args));
+console.log("Result:", greet(mode, count));
@@ -0,0 +1,3 @@
+pub mod runtime;
+pub mod cli;
+pub mod dap;
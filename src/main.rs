@@ -1,10 +1,14 @@
-mod runtime;
-mod cli;
-
-use cli::DebuggerCli;
+use time_travel_debugger::cli::DebuggerCli;
+use time_travel_debugger::runtime::interrupt::Interrupted;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize the debugger CLI and run
-    DebuggerCli::run().await
+    let result = DebuggerCli::run().await;
+    // The runtime is gone by now; exit the way the signal would have
+    if let Err(e) = &result
+        && let Some(interrupted) = e.downcast_ref::<Interrupted>() {
+        std::process::exit(interrupted.signal.exit_code());
+    }
+    result
 }
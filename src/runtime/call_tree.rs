@@ -0,0 +1,295 @@
+use crate::runtime::engine::{ExecutionState, FunctionCall};
+use deno_core::serde_json::{self, json};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// A recorded call and the calls made while it was on the stack
+#[derive(Debug, Clone)]
+pub struct CallNode {
+    pub call: FunctionCall,
+    pub children: Vec<CallNode>,
+}
+
+impl CallNode {
+    fn new(call: FunctionCall) -> Self {
+        Self { call, children: Vec::new() }
+    }
+
+    /// Number of calls in this subtree, excluding this node
+    pub fn descendant_count(&self) -> usize {
+        self.children.iter().map(|child| 1 + child.descendant_count()).sum()
+    }
+
+    /// Graphviz `digraph` of the tree. Calls that never recorded an exit are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n    node [shape=box, fontname=\"monospace\"];\n");
+        let mut next_id = 0;
+        self.write_dot(&mut dot, &mut next_id);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Add a `a;b;c count` entry for every leaf below this node, `stack` being the path to it
+    fn fold_leaves(&self, stack: &mut Vec<String>, folded: &mut BTreeMap<String, u64>) {
+        // `;` separates frames in the folded format
+        stack.push(self.call.name.replace(';', ":"));
+        if self.children.is_empty() {
+            *folded.entry(stack.join(";")).or_insert(0) += u64::from(self.call.call_count);
+        }
+        for child in &self.children {
+            child.fold_leaves(stack, folded);
+        }
+        stack.pop();
+    }
+
+    /// End of this call in microseconds: entry plus duration, or for calls that never
+    /// exited, the latest end among their children
+    fn end_micros(&self) -> f64 {
+        let start = self.call.timestamp * 1_000_000.0;
+        match self.call.duration_ms {
+            Some(duration) => start + duration * 1000.0,
+            None => self.children.iter().map(CallNode::end_micros).fold(start, f64::max),
+        }
+    }
+
+    /// Append this call's profile node (merged with earlier calls on the same path) and the
+    /// `(time, node)` samples marking where it and its children were on top of the stack
+    fn write_profile(&self, parent: usize, profile: &mut CpuProfileBuilder) {
+        let id = profile.node(parent, &self.call);
+        profile.samples.push((self.call.timestamp * 1_000_000.0, id));
+        for child in &self.children {
+            child.write_profile(id, profile);
+            profile.samples.push((child.end_micros(), id));
+        }
+    }
+
+    fn write_dot(&self, dot: &mut String, next_id: &mut usize) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+
+        let mut label = format!("{}({})", self.call.name, self.call.arguments.join(", "));
+        if let Some(duration) = self.call.duration_ms {
+            let _ = write!(label, "\\n{:.3}ms", duration);
+        }
+        if self.call.call_count > 1 {
+            let _ = write!(label, " ×{}", self.call.call_count);
+        }
+        let style = if self.call.duration_ms.is_none() && self.call.call_depth > 0 { ", style=dashed" } else { "" };
+        let _ = writeln!(dot, "    n{} [label=\"{}\"{}];", id, label.replace('"', "\\\""), style);
+
+        for child in &self.children {
+            let child_id = child.write_dot(dot, next_id);
+            let _ = writeln!(dot, "    n{} -> n{};", id, child_id);
+        }
+        id
+    }
+}
+
+impl ExecutionState {
+    /// Rebuild the call hierarchy from `call_depth`: each call becomes a child of the most
+    /// recent shallower call, under a synthetic depth-0 `<program>` root. Skipped depths
+    /// attach to the nearest shallower call, and calls without an exit keep their subtree.
+    pub fn build_call_tree(&self) -> CallNode {
+        let root = CallNode::new(FunctionCall {
+            name: "<program>".to_string(),
+            timestamp: self.function_call_history.first().map_or(0.0, |call| call.timestamp),
+            call_depth: 0,
+            arguments: Vec::new(),
+            arg_values: Vec::new(),
+            file_location: None,
+            line_number: None,
+            duration_ms: Some(self.total_execution_time.as_secs_f64() * 1000.0),
+            return_value: None,
+            call_count: 1,
+            trace_id: None,
+        });
+
+        // Open path from the root to the most recent call
+        let mut stack = vec![root];
+        for call in &self.function_call_history {
+            let depth = call.call_depth.max(1);
+            while stack.len() > 1 && stack.last().is_some_and(|node| node.call.call_depth >= depth) {
+                close_top(&mut stack);
+            }
+            stack.push(CallNode::new(call.clone()));
+        }
+        while stack.len() > 1 {
+            close_top(&mut stack);
+        }
+
+        stack.pop().expect("root stays on the stack")
+    }
+
+    /// Leaf call paths in the folded-stack format read by `flamegraph.pl` and `inferno`:
+    /// `outer;inner;leaf count` per line, identical paths merged and sorted
+    pub fn to_folded_stacks(&self) -> String {
+        let mut folded = BTreeMap::new();
+        let mut stack = Vec::new();
+        // The synthetic `<program>` root is left out of every path
+        for child in &self.build_call_tree().children {
+            child.fold_leaves(&mut stack, &mut folded);
+        }
+
+        folded.into_iter().map(|(path, count)| format!("{} {}\n", path, count)).collect()
+    }
+
+    /// Chrome DevTools `.cpuprofile` (a CDP `Profiler.Profile`) synthesized from the call
+    /// history: calls on the same path share a node, and a sample is placed at every entry
+    /// and exit so the flame chart shows exactly when each call was on top of the stack.
+    /// Times are in microseconds; `hitCount` is the number of samples landing on a node.
+    pub fn to_cpu_profile(&self) -> serde_json::Value {
+        let tree = self.build_call_tree();
+        let mut profile = CpuProfileBuilder::new();
+        for call in &tree.children {
+            call.write_profile(1, &mut profile);
+            profile.samples.push((call.end_micros(), 1));
+        }
+
+        let start_time = profile.samples.first().map_or(0.0, |&(time, _)| time);
+        let end_time = profile.samples.iter().map(|&(time, _)| time).fold(start_time, f64::max);
+        let mut hit_counts = vec![0u64; profile.nodes.len()];
+        let mut time_deltas = Vec::with_capacity(profile.samples.len());
+        let mut previous = start_time;
+        for &(time, id) in &profile.samples {
+            hit_counts[id - 1] += 1;
+            // Overlapping records (e.g. clock adjustments) must not move time backwards
+            let time = time.max(previous);
+            time_deltas.push((time - previous).round() as i64);
+            previous = time;
+        }
+
+        let nodes: Vec<serde_json::Value> = profile.nodes.into_iter().zip(profile.children).zip(hit_counts)
+            .map(|((mut node, children), hit_count)| {
+                node["hitCount"] = json!(hit_count);
+                node["children"] = json!(children);
+                node
+            })
+            .collect();
+        json!({
+            "nodes": nodes,
+            "startTime": start_time.round() as i64,
+            "endTime": end_time.round() as i64,
+            "samples": profile.samples.iter().map(|&(_, id)| id).collect::<Vec<_>>(),
+            "timeDeltas": time_deltas,
+        })
+    }
+}
+
+/// Nodes and samples of a CPU profile under construction; node 1 is `(root)`
+struct CpuProfileBuilder {
+    nodes: Vec<serde_json::Value>,
+    children: Vec<Vec<usize>>,
+    /// `(parent, function, url, line)` to node id
+    node_ids: HashMap<(usize, String, String, Option<u32>), usize>,
+    script_ids: HashMap<String, usize>,
+    samples: Vec<(f64, usize)>,
+}
+
+impl CpuProfileBuilder {
+    fn new() -> Self {
+        let mut builder = Self {
+            nodes: Vec::new(),
+            children: Vec::new(),
+            node_ids: HashMap::new(),
+            script_ids: HashMap::new(),
+            samples: Vec::new(),
+        };
+        builder.push_node("(root)", "", None);
+        builder
+    }
+
+    fn node(&mut self, parent: usize, call: &FunctionCall) -> usize {
+        let url = call.file_location.clone().unwrap_or_default();
+        let key = (parent, call.name.clone(), url.clone(), call.line_number);
+        if let Some(&id) = self.node_ids.get(&key) {
+            return id;
+        }
+        let id = self.push_node(&call.name, &url, call.line_number);
+        self.children[parent - 1].push(id);
+        self.node_ids.insert(key, id);
+        id
+    }
+
+    fn push_node(&mut self, function_name: &str, url: &str, line_number: Option<u32>) -> usize {
+        let next_script_id = self.script_ids.len() + 1;
+        let script_id = if url.is_empty() { 0 } else { *self.script_ids.entry(url.to_string()).or_insert(next_script_id) };
+        self.nodes.push(json!({
+            "id": self.nodes.len() + 1,
+            "callFrame": {
+                "functionName": function_name,
+                "scriptId": script_id.to_string(),
+                "url": url,
+                // CDP positions are 0-based, -1 when unknown
+                "lineNumber": line_number.map_or(-1, |line| i64::from(line) - 1),
+                "columnNumber": -1,
+            },
+        }));
+        self.children.push(Vec::new());
+        self.nodes.len()
+    }
+}
+
+fn close_top(stack: &mut Vec<CallNode>) {
+    let node = stack.pop().expect("caller checks the stack has a parent");
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_tree_from_depths() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        state.log_function_entry("parse".to_string(), vec![], None, None);
+        state.log_function_exit("parse".to_string(), 1.0);
+        // Sibling at the same depth (e.g. a tail call)
+        state.log_function_entry("render".to_string(), vec![], None, None);
+        // Throws: no exits for render or main
+        state.log_function_entry("draw".to_string(), vec!["\"x\"".to_string()], None, None);
+
+        let tree = state.build_call_tree();
+        assert_eq!(tree.children.len(), 1);
+        let main = &tree.children[0];
+        assert_eq!(main.call.name, "main");
+        let names: Vec<&str> = main.children.iter().map(|c| c.call.name.as_str()).collect();
+        assert_eq!(names, ["parse", "render"]);
+        assert_eq!(main.children[1].children[0].call.name, "draw");
+        assert_eq!(tree.descendant_count(), 4);
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph calls {"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("draw(\\\"x\\\")\", style=dashed"));
+
+        state.log_function_exit("draw".to_string(), 1.0);
+        state.log_function_entry("draw".to_string(), vec![], None, None);
+        assert_eq!(state.to_folded_stacks(), "main;parse 1\nmain;render;draw 2\n");
+    }
+
+    #[test]
+    fn test_cpu_profile_from_calls() {
+        let mut state = ExecutionState::default();
+        for (name, depth, timestamp, duration) in [("main", 1, 1.0, 3000.0), ("work", 2, 1.5, 1000.0), ("work", 2, 3.0, 500.0)] {
+            state.log_function_entry(name.to_string(), vec![], Some("file:///app.js".to_string()), Some(4));
+            let call = state.function_call_history.last_mut().unwrap();
+            (call.call_depth, call.timestamp, call.duration_ms) = (depth, timestamp, Some(duration));
+        }
+
+        let profile = state.to_cpu_profile();
+        let names: Vec<&str> = profile["nodes"].as_array().unwrap().iter()
+            .map(|node| node["callFrame"]["functionName"].as_str().unwrap())
+            .collect();
+        // Both `work` calls share one node under `main`
+        assert_eq!(names, ["(root)", "main", "work"]);
+        assert_eq!(profile["nodes"][1]["children"], json!([3]));
+        assert_eq!(profile["nodes"][2]["callFrame"]["lineNumber"], 3);
+        assert_eq!(profile["samples"], json!([2, 3, 2, 3, 2, 1]));
+        assert_eq!(profile["timeDeltas"], json!([0, 500_000, 1_000_000, 500_000, 500_000, 500_000]));
+        assert_eq!(profile["nodes"][2]["hitCount"], 2);
+        assert_eq!(profile["endTime"].as_i64().unwrap() - profile["startTime"].as_i64().unwrap(), 3_000_000);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+
+/// Words that look like identifiers but never name a captured variable
+const RESERVED: &[&str] = &[
+    "arguments", "async", "await", "break", "case", "catch", "class", "const", "continue",
+    "debugger", "default", "delete", "do", "else", "export", "extends", "false", "finally",
+    "for", "function", "if", "import", "in", "instanceof", "let", "new", "null", "of",
+    "return", "static", "super", "switch", "this", "throw", "true", "try", "typeof",
+    "undefined", "var", "void", "while", "with", "yield",
+];
+
+/// Globals a closure "references" without capturing anything worth recording
+const BUILTINS: &[&str] = &[
+    "Array", "BigInt", "Boolean", "Date", "Deno", "Error", "Infinity", "JSON", "Map", "Math",
+    "NaN", "Number", "Object", "Promise", "Reflect", "RegExp", "Set", "String", "Symbol",
+    "WeakMap", "WeakSet", "clearTimeout", "console", "globalThis", "parseFloat", "parseInt",
+    "performance", "setTimeout", "timeDebugger",
+];
+
+/// Names a function's source reads without declaring them itself, in order of first use:
+/// the candidates for what it closes over. V8 doesn't expose a closure's captured scope
+/// to embedders, so this is a heuristic over `Function.prototype.toString()` output.
+/// Strings and comments are skipped and property accesses, object literal keys,
+/// parameters (including those of nested functions) and `let`/`const`/`var`/`function`/
+/// `class`/`catch` bindings are excluded. Destructured bindings are not recognized, and a
+/// name that is both declared inside and captured from outside is left out.
+pub fn free_variables(source: &str) -> Vec<String> {
+    let tokens = tokenize(&strip_literals(source));
+    let mut declared: HashSet<&str> = HashSet::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let next = tokens.get(index + 1).map(String::as_str);
+        match token.as_str() {
+            "let" | "const" | "var" | "function" | "class" => {
+                if let Some(name) = next.filter(|name| is_identifier(name)) {
+                    declared.insert(name);
+                }
+            },
+            "=>" => {
+                // `x => ...` or `(a, b) => ...`
+                if index > 0 && is_identifier(&tokens[index - 1]) {
+                    declared.insert(&tokens[index - 1]);
+                } else if index > 0 && tokens[index - 1] == ")"
+                    && let Some((_, params)) = parameter_group(&tokens, index - 1) {
+                    declared.extend(params);
+                }
+            },
+            "{" if index > 0 && tokens[index - 1] == ")" => {
+                // `function f(a) {`, `catch (e) {`, `method(a) {` -- but not `if (...) {`
+                if let Some((open, params)) = parameter_group(&tokens, index - 1) {
+                    let keyword = open.checked_sub(1).map(|i| tokens[i].as_str());
+                    if !matches!(keyword, Some("if" | "for" | "while" | "switch" | "with")) {
+                        declared.extend(params);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut free = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if !is_identifier(token) || declared.contains(token.as_str())
+            || RESERVED.contains(&token.as_str()) || BUILTINS.contains(&token.as_str()) {
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|i| tokens[i].as_str());
+        let next = tokens.get(index + 1).map(String::as_str);
+        let property_access = previous == Some(".");
+        let object_key = next == Some(":") && matches!(previous, Some("{" | ","));
+        if !property_access && !object_key && seen.insert(token.as_str()) {
+            free.push(token.clone());
+        }
+    }
+    free
+}
+
+/// Position of the `(` and the identifiers of a simple parameter list `( a , b )` whose
+/// `)` is at `close`; `None` if the group holds anything else (a call's arguments, most
+/// conditions)
+fn parameter_group(tokens: &[String], close: usize) -> Option<(usize, Vec<&str>)> {
+    let mut params = Vec::new();
+    let mut index = close;
+    while index > 0 {
+        index -= 1;
+        match tokens[index].as_str() {
+            "(" => return Some((index, params)),
+            "," => {},
+            name if is_identifier(name) => params.push(name),
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+}
+
+/// Split into identifiers, `=>`, `...` and single punctuation characters
+fn tokenize(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_alphanumeric() || c == '_' || c == '$' {
+            let start = index;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_' || chars[index] == '$') {
+                index += 1;
+            }
+            tokens.push(chars[start..index].iter().collect());
+            continue;
+        }
+        if c == '=' && chars.get(index + 1) == Some(&'>') {
+            tokens.push("=>".to_string());
+            index += 2;
+        } else if c == '.' && chars.get(index + 1) == Some(&'.') && chars.get(index + 2) == Some(&'.') {
+            tokens.push("...".to_string());
+            index += 3;
+        } else {
+            if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+            index += 1;
+        }
+    }
+    tokens
+}
+
+/// Blank out comments and the text of string and template literals, keeping the code
+/// inside template `${...}` substitutions
+fn strip_literals(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    // Brace depth at which each open template substitution resumes its template
+    let mut templates: Vec<usize> = Vec::new();
+    let mut depth = 0;
+    let mut index = 0;
+    let mut in_template = false;
+
+    while index < chars.len() {
+        let c = chars[index];
+        if in_template {
+            match c {
+                '\\' => index += 1,
+                '`' => in_template = false,
+                '$' if chars.get(index + 1) == Some(&'{') => {
+                    in_template = false;
+                    templates.push(depth);
+                    depth += 1;
+                    out.push('(');
+                    index += 1;
+                },
+                _ => {},
+            }
+            index += 1;
+            continue;
+        }
+        match c {
+            '/' if chars.get(index + 1) == Some(&'/') => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+                continue;
+            },
+            '/' if chars.get(index + 1) == Some(&'*') => {
+                index += 2;
+                while index + 1 < chars.len() && !(chars[index] == '*' && chars[index + 1] == '/') {
+                    index += 1;
+                }
+                index += 2;
+                out.push(' ');
+                continue;
+            },
+            '\'' | '"' => {
+                index += 1;
+                while index < chars.len() && chars[index] != c {
+                    if chars[index] == '\\' {
+                        index += 1;
+                    }
+                    index += 1;
+                }
+                out.push_str("\"\"");
+            },
+            '`' => {
+                in_template = true;
+                out.push_str("\"\"");
+            },
+            '{' => {
+                depth += 1;
+                out.push(c);
+            },
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if templates.last() == Some(&depth) {
+                    templates.pop();
+                    in_template = true;
+                    out.push(')');
+                } else {
+                    out.push(c);
+                }
+            },
+            _ => out.push(c),
+        }
+        index += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_variables_skips_locals_and_properties() {
+        let source = r#"(item, index) => {
+            // counter is mentioned in a comment only
+            const label = `${prefix}: ${item.name}`;
+            for (let i = 0; i < limit; i++) { total += i; }
+            if (handlers[index]) { return { label, value: scale * item.value }; }
+            return items.map(x => x * factor).join("separator");
+        }"#;
+        assert_eq!(free_variables(source), ["prefix", "limit", "total", "handlers", "scale", "items", "factor"]);
+        assert_eq!(free_variables("function tick(n) { console.log(n, count); }"), ["count"]);
+        assert_eq!(free_variables("() => { if (ready) { run(); } }"), ["ready", "run"]);
+    }
+}
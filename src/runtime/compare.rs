@@ -0,0 +1,253 @@
+use crate::runtime::engine::{ExecutionState, FunctionCall, VariableSnapshot};
+use crate::runtime::serialization::JSValue;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// One way a re-run strayed from its baseline
+#[derive(Debug, Clone, PartialEq)]
+pub enum DivergenceKind {
+    CallCount { baseline: usize, current: usize },
+    /// The first call whose name or depth differs; later calls are not compared
+    CallOrder { call_index: usize, baseline: String, current: String },
+    SnapshotCount { baseline: usize, current: usize },
+    /// The snapshot at this index was taken in a different function or of a different type
+    SnapshotOrigin { baseline: String, current: String },
+    /// Variables missing on either side or holding structurally different values
+    Variables { names: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Index into `variable_snapshots`, for snapshot divergences
+    pub snapshot_index: Option<usize>,
+    pub function_name: String,
+    pub kind: DivergenceKind,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(index) = self.snapshot_index {
+            write!(f, "snapshot #{} in {}: ", index, self.function_name)?;
+        }
+        match &self.kind {
+            DivergenceKind::CallCount { baseline, current } =>
+                write!(f, "{} calls recorded, baseline has {}", current, baseline),
+            DivergenceKind::CallOrder { call_index, baseline, current } =>
+                write!(f, "call #{} is {}, baseline has {}", call_index, current, baseline),
+            DivergenceKind::SnapshotCount { baseline, current } =>
+                write!(f, "{} snapshots recorded, baseline has {}", current, baseline),
+            DivergenceKind::SnapshotOrigin { baseline, current } =>
+                write!(f, "taken in {}, baseline was taken in {}", current, baseline),
+            DivergenceKind::Variables { names } =>
+                write!(f, "variables differ: {}", names.join(", ")),
+        }
+    }
+}
+
+/// Result of comparing a re-run with its baseline
+#[derive(Debug, Clone, Default)]
+pub struct TraceReport {
+    pub divergences: Vec<Divergence>,
+}
+
+impl TraceReport {
+    pub fn is_match(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.is_match() {
+            return "✅ Replay matches the baseline\n".to_string();
+        }
+        let mut summary = format!("❌ Replay diverged from the baseline ({} divergences):\n", self.divergences.len());
+        for divergence in &self.divergences {
+            summary.push_str(&format!("  - {}\n", divergence));
+        }
+        summary
+    }
+}
+
+/// Checks a re-run against a recorded baseline: call count, call order (name and depth at
+/// each position) and, snapshot by snapshot, where it was taken and the variable values.
+/// Timestamps and durations are ignored since they differ between runs.
+pub struct TraceComparator;
+
+impl TraceComparator {
+    pub fn compare(baseline: &ExecutionState, current: &ExecutionState) -> TraceReport {
+        let mut divergences = Vec::new();
+
+        let (baseline_calls, current_calls) = (&baseline.function_call_history, &current.function_call_history);
+        if baseline_calls.len() != current_calls.len() {
+            divergences.push(Divergence {
+                snapshot_index: None,
+                function_name: String::new(),
+                kind: DivergenceKind::CallCount { baseline: baseline_calls.len(), current: current_calls.len() },
+            });
+        }
+        if let Some((call_index, (expected, actual))) = baseline_calls.iter().zip(current_calls).enumerate()
+            .find(|(_, (expected, actual))| expected.name != actual.name || expected.call_depth != actual.call_depth) {
+            divergences.push(Divergence {
+                snapshot_index: None,
+                function_name: actual.name.clone(),
+                kind: DivergenceKind::CallOrder { call_index, baseline: describe_call(expected), current: describe_call(actual) },
+            });
+        }
+
+        let (baseline_count, current_count) = (baseline.variable_snapshots.len(), current.variable_snapshots.len());
+        if baseline_count != current_count {
+            divergences.push(Divergence {
+                snapshot_index: None,
+                function_name: String::new(),
+                kind: DivergenceKind::SnapshotCount { baseline: baseline_count, current: current_count },
+            });
+        }
+        for index in 0..baseline_count.min(current_count) {
+            // Diff snapshots are compared by the full variables they stand for
+            let (Some(expected), Some(actual)) = (baseline.reconstruct_snapshot(index), current.reconstruct_snapshot(index)) else {
+                continue;
+            };
+            if let Some(kind) = compare_snapshots(&expected, &actual) {
+                divergences.push(Divergence { snapshot_index: Some(index), function_name: actual.function_name.clone(), kind });
+            }
+        }
+
+        TraceReport { divergences }
+    }
+}
+
+fn describe_call(call: &FunctionCall) -> String {
+    format!("{} at depth {}", call.name, call.call_depth)
+}
+
+fn compare_snapshots(expected: &VariableSnapshot, actual: &VariableSnapshot) -> Option<DivergenceKind> {
+    if expected.function_name != actual.function_name || expected.snapshot_type != actual.snapshot_type {
+        return Some(DivergenceKind::SnapshotOrigin {
+            baseline: format!("{} [{}]", expected.function_name, expected.snapshot_type),
+            current: format!("{} [{}]", actual.function_name, actual.snapshot_type),
+        });
+    }
+
+    let names: BTreeSet<&String> = expected.variables.keys().chain(actual.variables.keys()).collect();
+    let differing: Vec<String> = names.into_iter()
+        .filter(|name| expected.variables.get(*name) != actual.variables.get(*name))
+        .cloned()
+        .collect();
+    (!differing.is_empty()).then_some(DivergenceKind::Variables { names: differing })
+}
+
+/// A variable whose value at the first diverging snapshot differs from the baseline.
+/// A `None` value means the variable (or the whole snapshot) is missing on that side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub snapshot_index: usize,
+    pub function_name: String,
+    pub variable_name: String,
+    pub baseline_value: Option<JSValue>,
+    pub current_value: Option<JSValue>,
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<JSValue>| value.as_ref().map_or("<missing>".to_string(), JSValue::to_display_string);
+        write!(f, "snapshot #{} in {}: {}\n      baseline: {}\n      current:  {}",
+            self.snapshot_index, self.function_name, self.variable_name,
+            show(&self.baseline_value), show(&self.current_value))
+    }
+}
+
+/// Finds where a run's snapshots first stop matching a baseline, for CI suites that treat
+/// snapshot stability as correctness
+pub struct RegressionDetector;
+
+impl RegressionDetector {
+    /// Every differing variable of the first snapshot that differs from the baseline;
+    /// empty when all snapshots match
+    pub fn detect(baseline: &ExecutionState, current: &ExecutionState) -> Vec<Regression> {
+        let count = baseline.variable_snapshots.len().max(current.variable_snapshots.len());
+        (0..count)
+            .map(|index| Self::regressions_at(index, baseline.reconstruct_snapshot(index), current.reconstruct_snapshot(index)))
+            .find(|regressions| !regressions.is_empty())
+            .unwrap_or_default()
+    }
+
+    fn regressions_at(index: usize, expected: Option<VariableSnapshot>, actual: Option<VariableSnapshot>) -> Vec<Regression> {
+        let function_name = actual.as_ref().or(expected.as_ref())
+            .map(|snapshot| snapshot.function_name.clone())
+            .unwrap_or_default();
+        let empty = HashMap::new();
+        let expected_vars = expected.as_ref().map_or(&empty, |snapshot| &snapshot.variables);
+        let actual_vars = actual.as_ref().map_or(&empty, |snapshot| &snapshot.variables);
+
+        let names: BTreeSet<&String> = expected_vars.keys().chain(actual_vars.keys()).collect();
+        names.into_iter()
+            .filter(|name| expected_vars.get(*name) != actual_vars.get(*name))
+            .map(|name| Regression {
+                snapshot_index: index,
+                function_name: function_name.clone(),
+                variable_name: name.clone(),
+                baseline_value: expected_vars.get(name).cloned(),
+                current_value: actual_vars.get(name).cloned(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(names: &[&str], total: f64) -> ExecutionState {
+        let mut state = ExecutionState::default();
+        for name in names {
+            state.log_function_entry(name.to_string(), vec![], None, None);
+            state.variable_snapshots.push(VariableSnapshot {
+                timestamp: 0.0,
+                function_name: name.to_string(),
+                call_depth: 1,
+                variables: HashMap::from([("total".to_string(), JSValue::Number(total))]),
+                snapshot_type: "entry".to_string(),
+                metadata: HashMap::new(),
+                trace_id: None,
+                heap_stats: None,
+            });
+            state.log_function_exit(name.to_string(), 1.0);
+        }
+        state
+    }
+
+    #[test]
+    fn test_compare_reports_divergences() {
+        assert!(TraceComparator::compare(&run(&["a", "b"], 1.0), &run(&["a", "b"], 1.0)).is_match());
+
+        let report = TraceComparator::compare(&run(&["a", "b"], 1.0), &run(&["a", "c", "d"], 2.0));
+        let kinds: Vec<&DivergenceKind> = report.divergences.iter().map(|d| &d.kind).collect();
+        assert_eq!(kinds[0], &DivergenceKind::CallCount { baseline: 2, current: 3 });
+        assert!(matches!(kinds[1], DivergenceKind::CallOrder { call_index: 1, .. }));
+        assert_eq!(kinds[2], &DivergenceKind::SnapshotCount { baseline: 2, current: 3 });
+        assert_eq!(kinds[3], &DivergenceKind::Variables { names: vec!["total".to_string()] });
+        assert_eq!(report.divergences[3].snapshot_index, Some(0));
+        assert!(matches!(kinds[4], DivergenceKind::SnapshotOrigin { .. }));
+        assert!(report.summary().contains("snapshot #0 in a: variables differ: total"));
+    }
+
+    #[test]
+    fn test_regressions_at_first_divergence() {
+        let baseline = run(&["a", "b"], 1.0);
+        assert!(RegressionDetector::detect(&baseline, &run(&["a", "b"], 1.0)).is_empty());
+
+        let mut current = run(&["a", "b", "c"], 1.0);
+        current.variable_snapshots[1].variables.insert("total".to_string(), JSValue::Number(5.0));
+        let regressions = RegressionDetector::detect(&baseline, &current);
+        assert_eq!(regressions, [Regression {
+            snapshot_index: 1,
+            function_name: "b".to_string(),
+            variable_name: "total".to_string(),
+            baseline_value: Some(JSValue::Number(1.0)),
+            current_value: Some(JSValue::Number(5.0)),
+        }]);
+
+        // An extra snapshot regresses every variable it holds
+        let regressions = RegressionDetector::detect(&baseline, &run(&["a", "b", "c"], 1.0));
+        assert_eq!((regressions[0].snapshot_index, regressions[0].baseline_value.is_none()), (2, true));
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::{Result, anyhow};
+use deno_core::serde_json::{self, json};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+/// Coverage for a single JS function, cross-referenced with the debugger's call tracing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCoverage {
+    pub name: String,
+    pub file: String,
+    pub line: u32,
+    /// Invocation count reported by V8
+    pub call_count: u64,
+    /// Invocation count recorded by `ExecutionState`, if the function was traced
+    pub traced_call_count: Option<u32>,
+}
+
+/// Line and function coverage gathered from V8 precise coverage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub covered_lines: HashMap<String, Vec<u32>>,
+    /// Number of non-blank lines per script
+    pub total_lines: HashMap<String, u32>,
+    /// The non-blank line numbers counted in `total_lines`
+    pub code_lines: HashMap<String, Vec<u32>>,
+    pub functions: Vec<FunctionCoverage>,
+}
+
+/// Subset of the `Profiler.takePreciseCoverage` response we rely on
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScriptCoverage {
+    url: String,
+    functions: Vec<V8FunctionCoverage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct V8FunctionCoverage {
+    function_name: String,
+    ranges: Vec<CoverageRange>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CoverageRange {
+    start_offset: usize,
+    end_offset: usize,
+    count: u64,
+}
+
+impl CoverageReport {
+    /// Build a report from a `Profiler.takePreciseCoverage` result. `load_source` maps a
+    /// script URL to its source text; scripts it can't load (e.g. `ext:` internals) are skipped.
+    pub fn from_v8_coverage(
+        coverage: &serde_json::Value,
+        load_source: impl Fn(&str) -> Option<String>,
+        traced_call_counts: &HashMap<String, u32>,
+    ) -> Result<Self> {
+        let scripts: Vec<ScriptCoverage> = serde_json::from_value(
+            coverage.get("result").cloned().ok_or_else(|| anyhow!("Coverage response has no result"))?,
+        )?;
+
+        let mut report = CoverageReport::default();
+
+        for script in scripts {
+            let Some(source) = load_source(&script.url) else { continue };
+            let line_starts: Vec<usize> = std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+                .collect();
+
+            // V8 reports nested ranges; the innermost range containing an offset decides its count
+            let mut ranges: Vec<&CoverageRange> = script.functions.iter().flat_map(|f| &f.ranges).collect();
+            ranges.sort_by_key(|r| r.end_offset - r.start_offset);
+
+            let mut covered = Vec::new();
+            let mut code_lines = Vec::new();
+            for (index, start) in line_starts.iter().enumerate() {
+                let line_end = line_starts.get(index + 1).copied().unwrap_or(source.len());
+                let line = &source[*start..line_end];
+                let Some(indent) = line.find(|c: char| !c.is_whitespace()) else { continue };
+                let offset = start + indent;
+                code_lines.push(index as u32 + 1);
+
+                let count = ranges.iter()
+                    .find(|r| r.start_offset <= offset && offset < r.end_offset)
+                    .map_or(0, |r| r.count);
+                if count > 0 {
+                    covered.push(index as u32 + 1);
+                }
+            }
+
+            for function in &script.functions {
+                // The anonymous top-level range is the module body, not a function
+                if function.function_name.is_empty() {
+                    continue;
+                }
+                let Some(range) = function.ranges.first() else { continue };
+                let line = line_starts.partition_point(|start| *start <= range.start_offset) as u32;
+                report.functions.push(FunctionCoverage {
+                    name: function.function_name.clone(),
+                    file: script.url.clone(),
+                    line,
+                    call_count: range.count,
+                    traced_call_count: traced_call_counts.get(&function.function_name).copied(),
+                });
+            }
+
+            report.total_lines.insert(script.url.clone(), code_lines.len() as u32);
+            report.code_lines.insert(script.url.clone(), code_lines);
+            report.covered_lines.insert(script.url, covered);
+        }
+
+        Ok(report)
+    }
+
+    /// Render as Istanbul's `coverage-final.json` format (one statement per covered-able line)
+    pub fn to_istanbul_json(&self) -> serde_json::Value {
+        let mut files = serde_json::Map::new();
+
+        for (url, code_lines) in &self.code_lines {
+            let covered = self.covered_lines.get(url).map(Vec::as_slice).unwrap_or(&[]);
+            let mut statement_map = serde_json::Map::new();
+            let mut statements = serde_json::Map::new();
+            for (id, &line) in code_lines.iter().enumerate() {
+                let id = id.to_string();
+                statement_map.insert(id.clone(), json!({
+                    "start": { "line": line, "column": 0 },
+                    "end": { "line": line, "column": null },
+                }));
+                statements.insert(id, json!(u32::from(covered.contains(&line))));
+            }
+
+            let mut fn_map = serde_json::Map::new();
+            let mut function_counts = serde_json::Map::new();
+            for (id, function) in self.functions.iter().filter(|f| &f.file == url).enumerate() {
+                let location = json!({
+                    "start": { "line": function.line, "column": 0 },
+                    "end": { "line": function.line, "column": null },
+                });
+                fn_map.insert(id.to_string(), json!({
+                    "name": function.name,
+                    "decl": location,
+                    "loc": location,
+                    "line": function.line,
+                }));
+                function_counts.insert(id.to_string(), json!(function.call_count));
+            }
+
+            let path = deno_core::url::Url::parse(url).ok()
+                .and_then(|u| u.to_file_path().ok())
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| url.clone());
+            files.insert(path.clone(), json!({
+                "path": path,
+                "statementMap": statement_map,
+                "fnMap": fn_map,
+                "branchMap": {},
+                "s": statements,
+                "f": function_counts,
+                "b": {},
+            }));
+        }
+
+        serde_json::Value::Object(files)
+    }
+
+    pub fn get_summary(&self) -> String {
+        let mut summary = String::new();
+        summary.push_str("🧪 COVERAGE SUMMARY:\n");
+        for (url, total) in &self.total_lines {
+            let covered = self.covered_lines.get(url).map_or(0, Vec::len);
+            summary.push_str(&format!("  {} → {}/{} lines\n", url, covered, total));
+        }
+        for function in self.functions.iter().filter(|f| f.call_count > 0) {
+            let traced = function.traced_call_count
+                .map(|count| format!("traced {} calls", count))
+                .unwrap_or_else(|| "not traced".to_string());
+            summary.push_str(&format!("  {}:{} {} → {} calls ({})\n",
+                function.file, function.line, function.name, function.call_count, traced));
+        }
+        summary
+    }
+}
+
+/// `{ "file": ..., "lines": { "<line>": hits } }` for `ExecutionState::line_coverage`,
+/// lines in ascending order
+pub fn line_coverage_json(file: &str, lines: &HashMap<u32, u32>) -> serde_json::Value {
+    let mut sorted: Vec<(&u32, &u32)> = lines.iter().collect();
+    sorted.sort();
+    let lines: serde_json::Map<String, serde_json::Value> = sorted.into_iter()
+        .map(|(line, hits)| (line.to_string(), json!(hits)))
+        .collect();
+    json!({ "file": file, "lines": lines })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_from_v8() {
+        let source = "function used() {\n  return 1;\n}\nfunction unused() {\n  return 2;\n}\nused();\n";
+        let coverage = json!({
+            "result": [{
+                "scriptId": "1",
+                "url": "file:///tmp/app.js",
+                "functions": [
+                    { "functionName": "", "isBlockCoverage": false,
+                      "ranges": [{ "startOffset": 0, "endOffset": source.len(), "count": 1 }] },
+                    { "functionName": "used", "isBlockCoverage": false,
+                      "ranges": [{ "startOffset": 0, "endOffset": 31, "count": 1 }] },
+                    { "functionName": "unused", "isBlockCoverage": false,
+                      "ranges": [{ "startOffset": 32, "endOffset": 65, "count": 0 }] },
+                ]
+            }]
+        });
+        let traced = HashMap::from([("used".to_string(), 1)]);
+
+        let report = CoverageReport::from_v8_coverage(&coverage, |_| Some(source.to_string()), &traced).unwrap();
+
+        assert_eq!(report.covered_lines["file:///tmp/app.js"], vec![1, 2, 3, 7]);
+        assert_eq!(report.total_lines["file:///tmp/app.js"], 7);
+        assert_eq!(report.functions.len(), 2);
+        assert_eq!(report.functions[0].traced_call_count, Some(1));
+        assert_eq!(report.functions[1].line, 4);
+        assert_eq!(report.functions[1].call_count, 0);
+
+        let istanbul = report.to_istanbul_json();
+        assert_eq!(istanbul["/tmp/app.js"]["f"]["1"], json!(0));
+    }
+
+    #[test]
+    fn test_line_coverage_from_call_history() {
+        let mut state = crate::runtime::engine::ExecutionState::default();
+        for (file, line) in [("app.js", 10), ("app.js", 10), ("lib.js", 3), ("app.js", 12)] {
+            state.log_function_entry("f".to_string(), vec![], Some(file.to_string()), Some(line));
+            state.log_function_exit("f".to_string(), 1.0);
+        }
+
+        let lines = state.line_coverage("app.js");
+        assert_eq!(lines, HashMap::from([(10, 2), (12, 1)]));
+        assert_eq!(line_coverage_json("app.js", &lines), json!({ "file": "app.js", "lines": { "10": 2, "12": 1 } }));
+    }
+}
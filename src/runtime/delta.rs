@@ -0,0 +1,146 @@
+use crate::runtime::engine::{ExecutionState, VariableSnapshot};
+use crate::runtime::serialization::JSValue;
+use std::collections::HashMap;
+
+/// Variables of one snapshot expressed against the snapshot before it
+#[derive(Debug, Clone)]
+pub struct SnapshotDelta {
+    pub unchanged_keys: Vec<String>,
+    pub changed: HashMap<String, JSValue>,
+    pub added: HashMap<String, JSValue>,
+    pub removed: Vec<String>,
+    /// The snapshot's own timestamp, function, type and metadata, with `variables` empty
+    pub target: VariableSnapshot,
+}
+
+impl VariableSnapshot {
+    /// Describe this snapshot's variables as changes to `base`'s, comparing values with
+    /// `structural_eq`
+    pub fn delta_from(&self, base: &VariableSnapshot) -> SnapshotDelta {
+        let mut delta = SnapshotDelta {
+            unchanged_keys: Vec::new(),
+            changed: HashMap::new(),
+            added: HashMap::new(),
+            removed: base.variables.keys()
+                .filter(|name| !self.variables.contains_key(*name))
+                .cloned()
+                .collect(),
+            target: VariableSnapshot { variables: HashMap::new(), ..self.clone() },
+        };
+
+        for (name, value) in &self.variables {
+            match base.variables.get(name) {
+                Some(old) if old.structural_eq(value) => delta.unchanged_keys.push(name.clone()),
+                Some(_) => { delta.changed.insert(name.clone(), value.clone()); },
+                None => { delta.added.insert(name.clone(), value.clone()); },
+            }
+        }
+        delta.unchanged_keys.sort();
+        delta.removed.sort();
+        delta
+    }
+}
+
+impl SnapshotDelta {
+    /// Rebuild the snapshot this delta was taken from, given the base it was taken against
+    pub fn apply_to(&self, base: &VariableSnapshot) -> VariableSnapshot {
+        let mut variables: HashMap<String, JSValue> = self.unchanged_keys.iter()
+            .filter_map(|name| base.variables.get(name).map(|value| (name.clone(), value.clone())))
+            .collect();
+        variables.extend(self.changed.iter().chain(&self.added).map(|(name, value)| (name.clone(), value.clone())));
+
+        VariableSnapshot { variables, ..self.target.clone() }
+    }
+
+    /// Replay a chain from `ExecutionState::compress_delta_chain` back into full snapshots
+    pub fn expand_chain(chain: &[SnapshotDelta]) -> Vec<VariableSnapshot> {
+        let mut snapshots: Vec<VariableSnapshot> = Vec::with_capacity(chain.len());
+        for delta in chain {
+            let snapshot = match snapshots.last() {
+                Some(base) => delta.apply_to(base),
+                None => delta.apply_to(&delta.target),
+            };
+            snapshots.push(snapshot);
+        }
+        snapshots
+    }
+
+    /// Estimated bytes held by the delta's keys and values
+    pub fn size_estimate(&self) -> usize {
+        let keys: usize = self.unchanged_keys.iter().chain(&self.removed).map(String::len).sum();
+        let values: usize = self.changed.iter().chain(&self.added)
+            .map(|(name, value)| name.len() + value.estimated_heap_bytes())
+            .sum();
+        keys + values
+    }
+}
+
+impl ExecutionState {
+    /// Move every snapshot into a delta chain: the first delta is taken against an empty
+    /// snapshot and each later one against its predecessor. Diff snapshots are expanded
+    /// first, so every link describes complete variables. `variable_snapshots` is left
+    /// empty; `SnapshotDelta::expand_chain` restores it.
+    pub fn compress_delta_chain(&mut self) -> Vec<SnapshotDelta> {
+        let snapshots: Vec<VariableSnapshot> = (0..self.variable_snapshots.len())
+            .filter_map(|index| self.reconstruct_snapshot(index))
+            .collect();
+        self.variable_snapshots.clear();
+        self.snapshot_bytes = 0;
+
+        let mut chain = Vec::with_capacity(snapshots.len());
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            let delta = match index.checked_sub(1) {
+                Some(previous) => snapshot.delta_from(&snapshots[previous]),
+                None => snapshot.delta_from(&VariableSnapshot { variables: HashMap::new(), ..snapshot.clone() }),
+            };
+            chain.push(delta);
+        }
+        chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_chain_roundtrip() {
+        let mut state = ExecutionState::default();
+        for (i, extra) in [(0, None), (1, None), (1, Some("done")), (2, None)] {
+            let mut variables = HashMap::from([
+                ("i".to_string(), JSValue::Number(i as f64)),
+                ("config".to_string(), JSValue::Object(HashMap::from([("n".to_string(), JSValue::Number(3.0))]))),
+            ]);
+            if let Some(name) = extra {
+                variables.insert(name.to_string(), JSValue::Boolean(true));
+            }
+            state.variable_snapshots.push(VariableSnapshot {
+                timestamp: i as f64,
+                function_name: "loop".to_string(),
+                call_depth: 1,
+                variables,
+                snapshot_type: "custom".to_string(),
+                metadata: HashMap::new(),
+                trace_id: None,
+                heap_stats: None,
+            });
+        }
+        let original = state.variable_snapshots.to_vec();
+
+        let chain = state.compress_delta_chain();
+        assert!(state.variable_snapshots.is_empty());
+        assert_eq!(chain[0].added.len(), 2);
+        assert_eq!(chain[1].unchanged_keys, ["config"]);
+        assert!(chain[1].changed.contains_key("i"));
+        assert_eq!(chain[2].added.keys().collect::<Vec<_>>(), ["done"]);
+        assert_eq!(chain[3].removed, ["done"]);
+
+        let restored = SnapshotDelta::expand_chain(&chain);
+        assert_eq!(restored.len(), original.len());
+        for (restored, original) in restored.iter().zip(&original) {
+            assert_eq!(restored.timestamp, original.timestamp);
+            assert_eq!(restored.variables.len(), original.variables.len());
+            assert!(restored.variables.iter().all(|(name, value)| original.variables[name].structural_eq(value)));
+        }
+    }
+}
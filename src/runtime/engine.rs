@@ -1,18 +1,144 @@
-use deno_core::{extension, op2, JsRuntime, RuntimeOptions, OpState, v8};
+use deno_core::{extension, op2, JsRuntime, ModuleId, ModuleSpecifier, RuntimeOptions, OpState, OpMetricsSummary, OpMetricsSummaryTracker, v8};
 use std::rc::Rc;
 use std::cell::RefCell;
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use regex::Regex;
+use tracing::{debug, error, info, trace, warn};
 use std::time::{SystemTime, UNIX_EPOCH, Instant};
-use crate::runtime::serialization::{JSValue, SerializationContext, SerializationConfig};
+use serde::{Serialize, Deserialize};
+use crate::runtime::coverage::CoverageReport;
+use crate::runtime::replay::ReplaySession;
+use crate::runtime::query::SnapshotQuery;
+use crate::runtime::gc::{GcEvent, GcKind, GcTracer};
+use crate::runtime::modules::{ImportRecord, ModuleLoad, TracingModuleLoader, module_graph_to_dot};
+use crate::runtime::memory::{MemoryPressureMode, MemoryPressureMonitor, SnapshotLimit};
+use crate::runtime::source_map::SourceMapRegistry;
+use crate::runtime::snapshot_store::{FileSnapshotStore, SnapshotStore, VecSnapshotStore};
+use crate::runtime::interrupt::{InterruptSignal, InterruptWatcher, Interrupted, PanicTraceConfig, DEFAULT_CRASH_OUTPUT, install_panic_hook};
+use crate::runtime::persistence::{SNAPSHOT_EXTENSION, SnapshotSerializer, StateSerializer, resolve_within};
+use crate::runtime::inline_snapshot::{SnapshotAssertions, SnapshotOutcome, SNAPSHOT_DIR};
+use crate::runtime::closure::free_variables;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use deno_core::futures::FutureExt;
+use std::path::{Path, PathBuf};
+use std::hash::{Hash, Hasher};
+use twox_hash::XxHash64;
+use crate::runtime::serialization::{JSValue, PrettyPrintConfig, SerializationContext, SerializationConfig};
 
-/// Configuration for the time travel debugger
-#[derive(Debug, Clone)]
+/// What happens to a new snapshot once `max_snapshots` are held
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvictionPolicy {
+    /// Drop the oldest snapshot
+    #[default]
+    #[serde(rename = "oldest")]
+    OldestFirst,
+    /// Drop the shallowest snapshot (oldest first among ties), keeping detail from deep calls
+    LowestDepth,
+    /// Keep existing snapshots and reject the new capture
+    Error,
+}
+
+/// How the CLI reports a run on stdout
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Emoji-prefixed messages and report sections, for terminals
+    #[default]
+    Text,
+    /// One JSON object per line, for scripting
+    Json,
+    /// A single summary line at the end of the run
+    Compact,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "compact" => Ok(OutputFormat::Compact),
+            _ => Err(anyhow::anyhow!("Unknown output format: {} (expected text, json or compact)", value)),
+        }
+    }
+}
+
+/// Configuration for the time travel debugger. Also read from TOML by `from_toml`, with
+/// the same field names; fields left out keep their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct DebuggerConfig {
     pub capture_enabled: bool,
     pub max_snapshots: usize,
+    pub eviction_policy: EvictionPolicy,
     pub verbose: bool,
     pub trace_function_calls: bool,
+    /// Column budget for human-readable output (trace tables, pretty-printed values)
+    pub output_width: usize,
+    /// How the CLI reports the run; the runtime's own banner only appears in `Text`
+    pub output_format: OutputFormat,
+    /// Readable names for (typically minified) variables, keyed by the real name
+    pub variable_aliases: HashMap<String, String>,
+    /// Start the V8 inspector so `run_with_coverage` can collect precise coverage
+    pub collect_coverage: bool,
+    /// Serialization depth for function arguments ("entry" scope captures)
+    pub max_argument_depth: usize,
+    /// Serialization depth for function return values
+    pub max_return_value_depth: usize,
+    /// Record op dispatch/completion counters and sample them while the event loop runs
+    pub trace_event_loop: bool,
+    pub memory_pressure_mode: MemoryPressureMode,
+    /// Memory budget used by `MemoryPressureMode::Adaptive`
+    pub max_memory_mb: u64,
+    /// Sliding window size (in calls) for function coupling analysis
+    pub coupling_window: usize,
+    /// Record V8 garbage collection pauses
+    pub trace_gc: bool,
+    /// Arguments exposed to the debugged script as `Deno.args`
+    pub script_args: Vec<String>,
+    /// Built-in methods to trace without modifying the script, e.g. `Array.prototype.push`
+    pub prototype_method_trace: Vec<String>,
+    /// Merge consecutive duplicate calls in the history whenever the snapshot limit is hit
+    pub compress_call_history: bool,
+    /// Snapshot the script-visible globals before any user code runs
+    pub capture_on_entry: bool,
+    /// Snapshot globals and the main module's exports once execution finishes
+    pub capture_on_exit: bool,
+    /// Snapshot everything on `globalThis`, hidden properties included, once execution finishes
+    pub capture_global_on_exit: bool,
+    /// Regex a function name must match for its entry/exit to be recorded
+    pub capture_function_filter: Option<String>,
+    /// Also append every snapshot to this file as it is captured (see `FileSnapshotStore`)
+    pub snapshot_file: Option<PathBuf>,
+    /// Snapshots buffered before each write to `snapshot_file`; 1 writes every one as captured
+    pub write_batch_size: usize,
+    /// Budget for the estimated size of held snapshots; the oldest are evicted beyond it
+    pub max_snapshot_memory_mb: Option<usize>,
+    /// Skip a snapshot whose variables and type match the one captured just before it
+    pub dedup_snapshots: bool,
+    /// Overwrite `timeDebugger.assertSnapshot` values instead of comparing against them
+    pub update_snapshots: bool,
+    /// Deepest call nesting allowed; a call beyond it throws in the script
+    pub max_call_depth: Option<usize>,
+    /// Only warn when `max_call_depth` is exceeded instead of throwing
+    pub max_depth_warn_only: bool,
+    /// Warn as soon as a function is called with the same arguments more than this many
+    /// times in a row, a likely infinite loop or recursion
+    pub repeated_call_threshold: Option<u32>,
+    /// Where the trace recorded so far is saved when Ctrl-C or SIGTERM interrupts a run
+    pub crash_output: PathBuf,
+    /// Saving the trace when the debugger itself panics
+    pub panic_trace: PanicTraceConfig,
+    /// Record promise creation, chaining and settlement through V8 promise hooks
+    pub trace_promises: bool,
+    /// Record V8 heap statistics with every `capture_variables` snapshot. Off by
+    /// default: collecting them costs a heap walk per capture.
+    pub capture_heap_stats: bool,
+    /// Trace ID stamped on every call and snapshot outside an async trace, for
+    /// correlating recordings from several processes
+    pub trace_id: Option<String>,
 }
 
 impl Default for DebuggerConfig {
@@ -20,36 +146,445 @@ impl Default for DebuggerConfig {
         Self {
             capture_enabled: true,
             max_snapshots: 1000,
+            eviction_policy: EvictionPolicy::OldestFirst,
             verbose: false,
             trace_function_calls: true,
+            output_width: 120,
+            output_format: OutputFormat::Text,
+            variable_aliases: HashMap::new(),
+            collect_coverage: false,
+            max_argument_depth: 5,
+            max_return_value_depth: 5,
+            trace_event_loop: false,
+            memory_pressure_mode: MemoryPressureMode::Static,
+            max_memory_mb: 1024,
+            coupling_window: 5,
+            trace_gc: false,
+            script_args: Vec::new(),
+            prototype_method_trace: Vec::new(),
+            compress_call_history: false,
+            capture_on_entry: false,
+            capture_on_exit: false,
+            capture_global_on_exit: false,
+            capture_function_filter: None,
+            snapshot_file: None,
+            write_batch_size: DEFAULT_WRITE_BATCH_SIZE,
+            max_snapshot_memory_mb: None,
+            dedup_snapshots: false,
+            update_snapshots: false,
+            max_call_depth: None,
+            max_depth_warn_only: false,
+            repeated_call_threshold: None,
+            crash_output: PathBuf::from(DEFAULT_CRASH_OUTPUT),
+            panic_trace: PanicTraceConfig::default(),
+            trace_promises: false,
+            capture_heap_stats: false,
+            trace_id: None,
         }
     }
 }
 
+impl DebuggerConfig {
+    /// Read a config file; see `.time-travel.example.toml` for the schema
+    pub fn from_toml(path: &Path) -> Result<DebuggerConfig> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read config {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| anyhow::anyhow!("Invalid config {}: {}", path.display(), e))
+    }
+}
+
+/// Command-line arguments for the debugged script, kept in op state for `op_get_script_args`
+pub struct ScriptArgs(pub Vec<String>);
+
+/// Compiled `DebuggerConfig::capture_function_filter`, kept in op state for the entry/exit ops
+pub struct CaptureFilter(pub Regex);
+
 /// Function call information for execution monitoring
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub name: String,
     pub timestamp: f64,
     pub call_depth: usize,
+    /// Display form of each argument (`arg_values` rendered with `to_display_string` when present)
     pub arguments: Vec<String>,
+    /// Serialized argument values, when logged through `op_function_entry_with_args`
+    #[serde(default)]
+    pub arg_values: Vec<JSValue>,
     pub file_location: Option<String>,
     pub line_number: Option<u32>,
+    /// Filled in when the matching exit is logged
+    #[serde(default)]
+    pub duration_ms: Option<f64>,
+    /// Serialized return value, when captured (`undefined` returns are not recorded)
+    #[serde(default)]
+    pub return_value: Option<JSValue>,
+    /// Number of consecutive identical calls merged into this entry by `compress_call_history`
+    #[serde(default = "default_call_count")]
+    pub call_count: u32,
+    /// Trace active when the call was made: an async trace (see `op_begin_async_trace`) or the process trace ID
+    #[serde(default)]
+    pub trace_id: Option<String>,
+}
+
+fn default_call_count() -> u32 {
+    1
+}
+
+/// V8 heap usage at the moment a snapshot was captured, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeapStats {
+    pub total_heap_size: usize,
+    pub used_heap_size: usize,
+    pub heap_size_limit: usize,
+    pub external_memory: usize,
+}
+
+impl HeapStats {
+    pub fn from_isolate(isolate: &mut v8::Isolate) -> Self {
+        let mut stats = v8::HeapStatistics::default();
+        isolate.get_heap_statistics(&mut stats);
+        Self {
+            total_heap_size: stats.total_heap_size(),
+            used_heap_size: stats.used_heap_size(),
+            heap_size_limit: stats.heap_size_limit(),
+            external_memory: stats.external_memory(),
+        }
+    }
 }
 
 /// Variable capture snapshot for a specific execution point
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VariableSnapshot {
     pub timestamp: f64,
     pub function_name: String,
     pub call_depth: usize,
     pub variables: HashMap<String, JSValue>,
     pub snapshot_type: String, // "entry", "exit", "custom"
+    /// Extra facts about the capture, e.g. `is_diff` / `baseline_index` for differential snapshots
+    #[serde(default)]
+    pub metadata: HashMap<String, JSValue>,
+    /// Trace active when the snapshot was taken (async or process-wide)
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    /// Heap usage at capture time, recorded when `DebuggerConfig::capture_heap_stats` is set
+    #[serde(default)]
+    pub heap_stats: Option<HeapStats>,
+}
+
+impl VariableSnapshot {
+    /// Snapshot this one is a diff against, if it was captured with `capture_variables_diff`
+    pub fn baseline_index(&self) -> Option<usize> {
+        match (self.metadata.get("is_diff"), self.metadata.get("baseline_index")) {
+            (Some(JSValue::Boolean(true)), Some(JSValue::Number(index))) => Some(*index as usize),
+            _ => None,
+        }
+    }
+
+    /// xxHash64 of the function name, snapshot type and variables (not the timestamp or
+    /// metadata), equal for snapshots whose variables are structurally equal
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = XxHash64::with_seed(0);
+        self.function_name.hash(&mut hasher);
+        self.snapshot_type.hash(&mut hasher);
+        let mut names: Vec<&String> = self.variables.keys().collect();
+        names.sort();
+        for name in names {
+            name.hash(&mut hasher);
+            self.variables[name].content_hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Estimated bytes held by this snapshot's variables
+    pub fn estimated_bytes(&self) -> usize {
+        self.variables.iter().map(|(name, value)| name.len() + value.estimated_heap_bytes()).sum()
+    }
+
+    /// Similarity in [0.0, 1.0]: key overlap weighted by how many shared values are equal
+    pub fn similarity_score(&self, other: &VariableSnapshot) -> f64 {
+        let max_len = self.variables.len().max(other.variables.len());
+        if max_len == 0 {
+            return 1.0;
+        }
+
+        let mut shared_keys = 0;
+        let mut matched_values = 0;
+        for (name, value) in &self.variables {
+            if let Some(other_value) = other.variables.get(name) {
+                shared_keys += 1;
+                if value.structural_eq(other_value) {
+                    matched_values += 1;
+                }
+            }
+        }
+
+        if shared_keys == 0 {
+            return 0.0;
+        }
+
+        (shared_keys as f64 / max_len as f64) * (matched_values as f64 / shared_keys as f64)
+    }
+
+    /// Field-level changes from `self` to `other`. Objects present in both are compared
+    /// property by property, so nested changes are keyed by dotted path (`user.name`).
+    pub fn diff(&self, other: &VariableSnapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        diff_properties(&self.variables, &other.variables, "", &mut diff);
+        diff
+    }
+}
+
+/// Changes between two snapshots, keyed by variable name or dotted property path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub added: HashMap<String, JSValue>,
+    pub removed: HashMap<String, JSValue>,
+    /// `(old, new)` values
+    pub changed: HashMap<String, (JSValue, JSValue)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Changes from `old` to `new`, with paths rooted at `name`
+    pub fn between_values(name: &str, old: &JSValue, new: &JSValue) -> Self {
+        let mut diff = Self::default();
+        diff_properties(&HashMap::from([(name.to_string(), old.clone())]),
+            &HashMap::from([(name.to_string(), new.clone())]), "", &mut diff);
+        diff
+    }
+
+    /// One line per change (`+` added, `-` removed, `~` changed), sorted by path
+    pub fn to_display_string(&self) -> String {
+        if self.is_empty() {
+            return "  No changes\n".to_string();
+        }
+
+        let mut lines: Vec<(&String, String)> = Vec::new();
+        for (path, (old, new)) in &self.changed {
+            lines.push((path, format!("  ~ {}: {} → {}\n", path, old.to_display_string(), new.to_display_string())));
+        }
+        for (path, value) in &self.added {
+            lines.push((path, format!("  + {}: {}\n", path, value.to_display_string())));
+        }
+        for (path, value) in &self.removed {
+            lines.push((path, format!("  - {}: {}\n", path, value.to_display_string())));
+        }
+        lines.sort_by(|a, b| a.0.cmp(b.0));
+        lines.into_iter().map(|(_, line)| line).collect()
+    }
+
+    pub fn to_json_value(&self) -> serde_json::Value {
+        let values = |map: &HashMap<String, JSValue>| map.iter()
+            .map(|(path, value)| (path.clone(), value.to_json_value()))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::json!({
+            "added": values(&self.added),
+            "removed": values(&self.removed),
+            "changed": self.changed.iter()
+                .map(|(path, (old, new))| (path.clone(), serde_json::json!([old.to_json_value(), new.to_json_value()])))
+                .collect::<serde_json::Map<_, _>>(),
+        })
+    }
+}
+
+fn diff_properties(old: &HashMap<String, JSValue>, new: &HashMap<String, JSValue>, prefix: &str, diff: &mut SnapshotDiff) {
+    for (name, old_value) in old {
+        let path = format!("{}{}", prefix, name);
+        match (old_value, new.get(name)) {
+            (_, None) => {
+                diff.removed.insert(path, old_value.clone());
+            },
+            (JSValue::Object(old_fields), Some(JSValue::Object(new_fields))) => {
+                diff_properties(old_fields, new_fields, &format!("{}.", path), diff);
+            },
+            (_, Some(new_value)) if !old_value.structural_eq(new_value) => {
+                diff.changed.insert(path, (old_value.clone(), new_value.clone()));
+            },
+            _ => {},
+        }
+    }
+    for (name, new_value) in new {
+        if !old.contains_key(name) {
+            diff.added.insert(format!("{}{}", prefix, name), new_value.clone());
+        }
+    }
+}
+
+/// Op counters observed through deno_core's op metrics hooks
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct EventLoopStats {
+    /// Async ops dispatched but not yet completed
+    pub pending_ops: u32,
+    pub dispatched_ops: u64,
+    pub completed_ops: u64,
+    /// deno_core keeps its dynamic import queue private, so this only reflects imports
+    /// the debugger can observe; with the synchronous `FsModuleLoader` it stays 0
+    pub pending_dynamic_imports: u32,
+}
+
+impl EventLoopStats {
+    fn from_summary(summary: &OpMetricsSummary) -> Self {
+        Self {
+            pending_ops: summary.ops_dispatched_async.saturating_sub(summary.ops_completed_async) as u32,
+            dispatched_ops: summary.ops_dispatched_sync + summary.ops_dispatched_async,
+            // Sync ops complete within their dispatch
+            completed_ops: summary.ops_dispatched_sync + summary.ops_completed_async,
+            pending_dynamic_imports: 0,
+        }
+    }
+}
+
+/// A section of JS explicitly marked as performance-critical via `timeDebugger.hot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotPathRecord {
+    pub label: String,
+    pub start_ms: f64,
+    pub end_ms: f64,
+    pub call_count_in_path: u64,
+    pub summary_snapshot_index: Option<usize>,
+}
+
+/// A variable whose captured size keeps growing from call to call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryLeakCandidate {
+    pub variable_name: String,
+    pub function_name: String,
+    pub size_at_start: usize,
+    pub size_at_end: usize,
+    /// Least-squares slope of the size estimate, in bytes per call
+    pub growth_rate: f64,
+}
+
+/// Fewest captures of a variable before its growth trend is trusted
+const LEAK_MIN_SAMPLES: usize = 10;
+
+/// Length of the call sequences counted by `ExecutionState::hot_call_paths`
+const HOT_CALL_PATH_LENGTH: usize = 3;
+
+/// Snapshots buffered per write to a snapshot file unless `write_batch_size` says otherwise
+const DEFAULT_WRITE_BATCH_SIZE: usize = 100;
+
+/// A transition reported through `timeDebugger.transition`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateMachineEvent {
+    pub machine_name: String,
+    pub from_state: String,
+    pub to_state: String,
+    pub event: String,
+    pub timestamp_ms: f64,
+    /// Function that was executing when the transition happened
+    pub function_name: Option<String>,
+}
+
+/// One evaluation of a branch condition reported through `timeDebugger.trackBranch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchRecord {
+    pub function_name: String,
+    /// Caller-chosen id distinguishing the branches within a function
+    pub branch_id: u32,
+    pub taken: bool,
+    pub timestamp: f64,
+}
+
+/// A write to a property watched with `timeDebugger.watchProperty`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyMutation {
+    /// Identity of the written object, stable for its lifetime (`object#<identity hash>`)
+    pub object_ref: String,
+    pub property_name: String,
+    pub old_value: JSValue,
+    pub new_value: JSValue,
+    pub timestamp: f64,
+    pub call_depth: usize,
+}
+
+/// What happened to a promise in a `PromiseEvent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromiseEventType {
+    Created,
+    Resolved,
+    Rejected,
+    /// The promise was derived from another, e.g. by `then`; the event's value is the parent's id
+    Chained,
 }
 
-/// Enhanced execution state tracking with function monitoring and variable capture
+/// A promise lifecycle event recorded by the promise hooks installed with `trace_promises`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromiseEvent {
+    /// Identity of the promise, stable for its lifetime (`promise#<identity hash>`)
+    pub promise_id: String,
+    pub event_type: PromiseEventType,
+    /// The settled value for `Resolved`/`Rejected`, the parent promise's id for `Chained`
+    pub value: Option<JSValue>,
+    pub timestamp: f64,
+}
+
+/// A run of consecutive calls to one function with identical arguments
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepeatedCallWarning {
+    pub function_name: String,
+    /// The arguments' display forms, comma separated
+    pub argument_signature: String,
+    pub count: u32,
+}
+
+/// One pass through a loop body reported through `timeDebugger.trackLoop`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoopRecord {
+    /// Caller-chosen id distinguishing the instrumented loops
+    pub loop_id: u32,
+    pub iteration: u32,
+    pub timestamp: f64,
+    pub function_name: String,
+}
+
+/// Per-loop summary from `ExecutionState::loop_statistics`. Durations are the gaps, in
+/// milliseconds, between consecutive iterations; they are 0 when fewer than two ran.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoopStats {
+    pub total_iterations: u64,
+    pub min_duration_ms: f64,
+    pub max_duration_ms: f64,
+    pub avg_duration_ms: f64,
+}
+
+/// A `performance.mark` entry (W3C User Timing). Times are milliseconds since
+/// `ExecutionState::perf_time_origin_ms`, as `performance.now()` reports them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerfMark {
+    pub name: String,
+    pub start_time: f64,
+}
+
+/// A `performance.measure` entry spanning two marks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerfMeasure {
+    pub name: String,
+    pub start_time: f64,
+    pub duration: f64,
+    /// `None` when the measure starts at the time origin
+    pub start_mark: Option<String>,
+    /// `None` when the measure ends at the time it was taken
+    pub end_mark: Option<String>,
+}
+
+/// Hot path that has been started but not yet ended
+#[derive(Debug)]
+pub(crate) struct ActiveHotPath {
+    label: String,
+    start_ms: f64,
+    function_calls_at_start: u64,
+    call_counts_at_start: HashMap<String, u32>,
+}
+
+/// Enhanced execution state tracking with function monitoring and variable capture.
+/// Snapshots live in `S`; the runtime's capture pipeline (eviction, diffs, analyses)
+/// uses the default `VecSnapshotStore`.
 #[derive(Debug)]
-pub struct ExecutionState {
+pub struct ExecutionState<S: SnapshotStore = VecSnapshotStore> {
     pub function_calls: u64,
     pub total_execution_time: std::time::Duration,
     pub current_function: Option<String>,
@@ -57,12 +592,111 @@ pub struct ExecutionState {
     pub function_call_history: Vec<FunctionCall>,
     pub function_call_counts: HashMap<String, u32>,
     pub execution_start_time: Option<Instant>,
-    pub variable_snapshots: Vec<VariableSnapshot>,
+    pub variable_snapshots: S,
     pub serialization_context: SerializationContext,
+    pub output_width: usize,
+    pub hot_paths: Vec<HotPathRecord>,
+    pub variable_aliases: HashMap<String, String>,
+    pub max_argument_depth: usize,
+    pub max_return_value_depth: usize,
+    /// `(timestamp_ms, stats)` samples taken while the event loop runs
+    pub event_loop_samples: Vec<(f64, EventLoopStats)>,
+    /// Maximum number of snapshots kept; may be lowered by the memory pressure monitor
+    pub snapshot_limit: SnapshotLimit,
+    pub eviction_policy: EvictionPolicy,
+    /// Snapshots dropped to stay within `snapshot_limit`
+    pub eviction_count: u64,
+    pub coupling_window: usize,
+    /// Snapshot handed to the script through `op_replay_inject` when running a fork
+    pub replay_injection: Option<VariableSnapshot>,
+    /// Garbage collection pauses observed while running with `trace_gc`
+    pub gc_events: Vec<GcEvent>,
+    /// Modules in the order they were loaded (see `TracingModuleLoader`)
+    pub module_loads: Vec<ModuleLoad>,
+    /// Every `import()` call, in the order they were made
+    pub dynamic_imports: Vec<ImportRecord>,
+    pub state_machine_events: Vec<StateMachineEvent>,
+    pub branch_log: Vec<BranchRecord>,
+    pub loop_iterations: Vec<LoopRecord>,
+    pub property_mutations: Vec<PropertyMutation>,
+    pub promise_events: Vec<PromiseEvent>,
+    /// Wall-clock time, in epoch milliseconds, that `performance.now()` counts from
+    pub perf_time_origin_ms: f64,
+    pub perf_marks: Vec<PerfMark>,
+    pub perf_measures: Vec<PerfMeasure>,
+    /// Apply `compress_call_history(true)` when the snapshot limit overflows
+    pub compress_call_history: bool,
+    /// Watched variable names and their last captured value (`None` until first seen)
+    pub watchpoints: HashMap<String, Option<JSValue>>,
+    /// Async trace stamped on new calls and snapshots, so work resumed after an
+    /// `await` can be tied back to the operation that started it
+    pub current_trace_id: Option<String>,
+    /// Trace ID for the whole run (`--trace-id`), stamped whenever no async trace is active
+    pub process_trace_id: Option<String>,
+    /// Every `exception` snapshot, unaffected by the snapshot limit
+    pub exceptions: Vec<VariableSnapshot>,
+    /// Maps used to point `FunctionCall` locations at original sources
+    pub source_maps: SourceMapRegistry,
+    /// Function names a DAP client stops at when they are entered. Breakpointed
+    /// functions are always recorded, even when the capture filter excludes them.
+    pub breakpoints: HashSet<String>,
+    /// On-disk log receiving every captured snapshot, including those later evicted
+    pub snapshot_file: Option<FileSnapshotStore>,
+    /// Snapshots are written to `snapshot_file` this many at a time
+    pub write_batch_size: usize,
+    /// Captured snapshots not yet written to `snapshot_file`
+    pub(crate) pending_writes: Vec<VariableSnapshot>,
+    /// Evict the oldest snapshots once their `VariableSnapshot::estimated_bytes` total exceeds this
+    pub max_snapshot_bytes: Option<usize>,
+    /// Running `estimated_bytes` total of `variable_snapshots`, kept while `max_snapshot_bytes` is set
+    pub(crate) snapshot_bytes: usize,
+    /// Drop snapshots identical (by `VariableSnapshot::content_hash`) to the previous one
+    pub dedup_snapshots: bool,
+    /// Attach `HeapStats` to snapshots taken by `capture_variables`
+    pub capture_heap_stats: bool,
+    /// Snapshots skipped by `dedup_snapshots`
+    pub dedup_skipped: u64,
+    pub(crate) last_snapshot_hash: Option<u64>,
+    /// Deepest call nesting allowed; see `DebuggerConfig::max_call_depth`
+    pub max_call_depth: Option<usize>,
+    pub max_depth_warn_only: bool,
+    /// See `DebuggerConfig::repeated_call_threshold`
+    pub repeated_call_threshold: Option<u32>,
+    /// Deepest call nesting seen so far
+    pub max_depth_reached: usize,
+    /// Set when `log_function_entry` refuses a call beyond `max_call_depth`; the entry
+    /// ops take it and throw it into the script
+    pub(crate) depth_limit_error: Option<String>,
+    pub(crate) active_hot_paths: Vec<ActiveHotPath>,
+    /// Set by `stop_capture` once a run is shutting down
+    pub(crate) capture_stopped: bool,
+}
+
+/// Take the latest call at each depth from `depth` down to 1 out of `calls`, which
+/// must run backwards through the history
+fn stack_frames<'a>(calls: impl Iterator<Item = &'a FunctionCall>, mut depth: usize) -> Vec<&'a FunctionCall> {
+    let mut frames = Vec::with_capacity(depth);
+    for call in calls {
+        if depth == 0 {
+            break;
+        }
+        if call.call_depth == depth {
+            frames.push(call);
+            depth -= 1;
+        }
+    }
+    frames
 }
 
 impl Default for ExecutionState {
     fn default() -> Self {
+        Self::with_store(VecSnapshotStore::new())
+    }
+}
+
+impl<S: SnapshotStore> ExecutionState<S> {
+    /// An empty state keeping its snapshots in `store`
+    pub fn with_store(store: S) -> Self {
         Self {
             function_calls: 0,
             total_execution_time: std::time::Duration::default(),
@@ -71,18 +705,76 @@ impl Default for ExecutionState {
             function_call_history: Vec::new(),
             function_call_counts: HashMap::new(),
             execution_start_time: None,
-            variable_snapshots: Vec::new(),
+            variable_snapshots: store,
             serialization_context: SerializationContext::new(SerializationConfig::default()),
+            output_width: 120,
+            hot_paths: Vec::new(),
+            variable_aliases: HashMap::new(),
+            max_argument_depth: 5,
+            max_return_value_depth: 5,
+            event_loop_samples: Vec::new(),
+            snapshot_limit: Arc::new(AtomicUsize::new(DebuggerConfig::default().max_snapshots)),
+            eviction_policy: EvictionPolicy::default(),
+            eviction_count: 0,
+            coupling_window: DebuggerConfig::default().coupling_window,
+            replay_injection: None,
+            gc_events: Vec::new(),
+            module_loads: Vec::new(),
+            dynamic_imports: Vec::new(),
+            state_machine_events: Vec::new(),
+            branch_log: Vec::new(),
+            loop_iterations: Vec::new(),
+            property_mutations: Vec::new(),
+            promise_events: Vec::new(),
+            perf_time_origin_ms: current_time_ms(),
+            perf_marks: Vec::new(),
+            perf_measures: Vec::new(),
+            compress_call_history: false,
+            watchpoints: HashMap::new(),
+            current_trace_id: None,
+            process_trace_id: None,
+            exceptions: Vec::new(),
+            source_maps: SourceMapRegistry::default(),
+            breakpoints: HashSet::new(),
+            snapshot_file: None,
+            write_batch_size: DEFAULT_WRITE_BATCH_SIZE,
+            pending_writes: Vec::new(),
+            max_snapshot_bytes: None,
+            snapshot_bytes: 0,
+            dedup_snapshots: false,
+            dedup_skipped: 0,
+            last_snapshot_hash: None,
+            capture_heap_stats: false,
+            max_call_depth: None,
+            max_depth_warn_only: false,
+            repeated_call_threshold: None,
+            max_depth_reached: 0,
+            depth_limit_error: None,
+            active_hot_paths: Vec::new(),
+            capture_stopped: false,
         }
     }
-}
 
-impl ExecutionState {
     pub fn start_execution(&mut self) {
         self.execution_start_time = Some(Instant::now());
     }
 
+    /// Record a call. A call beyond `max_call_depth` is not recorded unless
+    /// `max_depth_warn_only` is set; `depth_limit_error` explains why instead.
     pub fn log_function_entry(&mut self, name: String, args: Vec<String>, location: Option<String>, line: Option<u32>) {
+        if let Some(limit) = self.max_call_depth
+            && self.call_stack_depth >= limit {
+            if !self.max_depth_warn_only {
+                warn!(function = %name, limit, "maximum call depth exceeded; call refused");
+                self.depth_limit_error = Some(format!("Maximum call depth of {} exceeded entering {}", limit, name));
+                return;
+            }
+            // Warn once per descent past the limit, not on every deeper call
+            if self.call_stack_depth == limit {
+                warn!(function = %name, limit, "maximum call depth exceeded");
+            }
+        }
+
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -90,11 +782,22 @@ impl ExecutionState {
 
         self.function_calls += 1;
         self.call_stack_depth += 1;
+        self.max_depth_reached = self.max_depth_reached.max(self.call_stack_depth);
         self.current_function = Some(name.clone());
 
         // Update function call counts
         let count = self.function_call_counts.entry(name.clone()).or_insert(0);
         *count += 1;
+        let count = *count;
+
+        // Point bundled locations at the original source when a map is registered
+        let (location, line) = match (&location, line) {
+            (Some(file), Some(line_number)) => match self.source_maps.resolve(file, line_number, 1) {
+                Some(position) => (Some(position.original_file), Some(position.original_line)),
+                None => (location, Some(line_number)),
+            },
+            _ => (location, line),
+        };
 
         // Store function call details
         let call = FunctionCall {
@@ -102,25 +805,91 @@ impl ExecutionState {
             timestamp,
             call_depth: self.call_stack_depth,
             arguments: args,
+            arg_values: Vec::new(),
             file_location: location,
             line_number: line,
+            duration_ms: None,
+            return_value: None,
+            call_count: 1,
+            trace_id: self.current_trace_id.clone(),
         };
 
         self.function_call_history.push(call);
 
-        // Print execution trace
-        let indent = "  ".repeat(self.call_stack_depth.saturating_sub(1));
-        println!("📞 {}→ {} (depth: {}, count: {})", 
-                indent, name, self.call_stack_depth, count);
+        // Hot paths run in profiler-only mode: calls are counted but not traced
+        if self.in_hot_path() {
+            return;
+        }
+
+        debug!(function = %name, depth = self.call_stack_depth, count, "function entered");
+    }
+
+    /// `log_function_entry` keeping the serialized arguments alongside their display strings
+    pub fn log_function_entry_with_values(&mut self, name: String, arg_values: Vec<JSValue>, location: Option<String>, line: Option<u32>) {
+        let args = arg_values.iter().map(JSValue::to_display_string).collect();
+        self.log_function_entry(name, args, location, line);
+        if self.depth_limit_error.is_some() {
+            return;
+        }
+        if let Some(call) = self.function_call_history.last_mut() {
+            call.arg_values = arg_values;
+        }
+    }
+
+    /// Calls currently on the stack, innermost first: walking back through the history,
+    /// the latest call at each depth from `call_stack_depth` down to 1
+    pub fn current_call_stack(&self) -> Vec<&FunctionCall> {
+        stack_frames(self.function_call_history.iter().rev(), self.call_stack_depth)
+    }
+
+    /// The stack as it was when `function_call_history[index]` was entered, innermost first
+    pub fn call_stack_at(&self, index: usize) -> Vec<&FunctionCall> {
+        let Some(call) = self.function_call_history.get(index) else {
+            return Vec::new();
+        };
+        stack_frames(self.function_call_history[..=index].iter().rev(), call.call_depth)
+    }
+
+    pub fn in_hot_path(&self) -> bool {
+        !self.active_hot_paths.is_empty()
+    }
+
+    /// Indices into `function_call_history` of every call to a breakpointed function
+    pub fn breakpoint_hits(&self) -> Vec<usize> {
+        self.function_call_history.iter().enumerate()
+            .filter(|(_, call)| self.breakpoints.contains(&call.name))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Attach `value` to the most recent call of `function_name` that has no return value yet.
+    /// Returns false when there is no such call.
+    pub fn set_return_value(&mut self, function_name: &str, value: JSValue) -> bool {
+        match self.function_call_history.iter_mut().rev()
+            .find(|call| call.name == function_name && call.return_value.is_none()) {
+            Some(call) => {
+                call.return_value = Some(value);
+                true
+            },
+            None => false,
+        }
     }
 
     pub fn log_function_exit(&mut self, name: String, duration_ms: f64) {
+        // Pair the exit with the innermost open call of the same function at this depth
+        let exiting_depth = self.call_stack_depth;
+        if let Some(call) = self.function_call_history.iter_mut().rev()
+            .find(|call| call.call_depth == exiting_depth && call.name == name && call.duration_ms.is_none()) {
+            call.duration_ms = Some(duration_ms);
+        }
+
         if self.call_stack_depth > 0 {
             self.call_stack_depth -= 1;
         }
 
-        let indent = "  ".repeat(self.call_stack_depth);
-        println!("📤 {}← {} ({}ms)", indent, name, duration_ms);
+        if !self.in_hot_path() {
+            debug!(function = %name, depth = self.call_stack_depth, duration_ms, "function exited");
+        }
 
         // Update current function to the parent if we have call history
         if let Some(parent_call) = self.function_call_history
@@ -133,6 +902,67 @@ impl ExecutionState {
         }
     }
 
+    pub fn begin_async_trace(&mut self, trace_id: String) {
+        self.current_trace_id = Some(trace_id);
+    }
+
+    /// Stop stamping `trace_id`, returning to the process trace ID; a different active
+    /// trace is left alone
+    pub fn end_async_trace(&mut self, trace_id: &str) {
+        if self.current_trace_id.as_deref() == Some(trace_id) {
+            self.current_trace_id = self.process_trace_id.clone();
+        }
+    }
+
+    /// Replace the process trace ID, applying it right away unless an async trace is active
+    pub fn set_process_trace_id(&mut self, trace_id: Option<String>) {
+        if self.current_trace_id == self.process_trace_id {
+            self.current_trace_id = trace_id.clone();
+        }
+        self.process_trace_id = trace_id;
+    }
+
+    /// Snapshots recorded by `timeDebugger.checkpoint(label)`, in order
+    pub fn checkpoints(&self) -> Vec<&VariableSnapshot> {
+        self.variable_snapshots.iter()
+            .filter(|snapshot| snapshot.snapshot_type == "checkpoint")
+            .collect()
+    }
+
+    /// Every snapshot taken while `trace_id` was active, in capture order
+    pub fn snapshots_for_trace(&self, trace_id: &str) -> Vec<&VariableSnapshot> {
+        self.variable_snapshots.iter()
+            .filter(|snapshot| snapshot.trace_id.as_deref() == Some(trace_id))
+            .collect()
+    }
+
+    /// `(timestamp, value)` for every snapshot that captured `name`, oldest first
+    pub fn variable_timeline(&self, name: &str) -> Vec<(f64, &JSValue)> {
+        let mut timeline: Vec<(f64, &JSValue)> = self.variable_snapshots.iter()
+            .filter_map(|snapshot| snapshot.variables.get(name).map(|value| (snapshot.timestamp, value)))
+            .collect();
+        // Capture order is chronological except where eviction or replay reordered it
+        timeline.sort_by(|a, b| a.0.total_cmp(&b.0));
+        timeline
+    }
+
+    /// Hits per line in `file_path` (a script URL, as recorded from V8's stack frames),
+    /// estimated from the recorded calls' `line_number`s. Merged duplicate calls count once
+    /// per call they stand for; lines with no hits are absent.
+    pub fn line_coverage(&self, file_path: &str) -> HashMap<u32, u32> {
+        let mut lines = HashMap::new();
+        for call in &self.function_call_history {
+            if let (Some(location), Some(line)) = (&call.file_location, call.line_number)
+                && location == file_path {
+                *lines.entry(line).or_insert(0) += call.call_count;
+            }
+        }
+        lines
+    }
+}
+
+/// Capture, eviction and the snapshot analyses index and reorder the snapshot `Vec`
+impl ExecutionState {
     pub fn capture_variables(
         &mut self,
         scope: &mut v8::HandleScope,
@@ -140,287 +970,2885 @@ impl ExecutionState {
         snapshot_type: String,
         variables: HashMap<String, v8::Local<v8::Value>>,
     ) -> Result<()> {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs_f64();
-
-        let mut captured_vars = HashMap::new();
-        
-        // Serialize each variable using our serialization context
-        for (name, value) in variables {
-            match self.serialization_context.serialize_value(scope, value) {
-                Ok(serialized_value) => {
-                    captured_vars.insert(name, serialized_value);
-                },
-                Err(e) => {
-                    // If serialization fails, store an error representation
-                    captured_vars.insert(name, JSValue::Error(format!("Serialization failed: {}", e)));
-                }
-            }
+        // Fine-grained capture is disabled inside hot paths
+        if self.in_hot_path() {
+            return Ok(());
+        }
+        if self.at_rejecting_limit() {
+            return Err(anyhow::anyhow!("Snapshot limit of {} reached; capture of {} rejected",
+                self.snapshot_limit.load(Ordering::Relaxed), function_name));
         }
 
-        let snapshot = VariableSnapshot {
-            timestamp,
-            function_name: function_name.clone(),
-            call_depth: self.call_stack_depth,
-            variables: captured_vars,
-            snapshot_type: snapshot_type.clone(),
-        };
-
-        self.variable_snapshots.push(snapshot);
-
-        // Print capture info if verbose
-        let indent = "  ".repeat(self.call_stack_depth);
-        println!("📸 {}CAPTURE: {} ({} - {} variables)", 
-            indent, function_name, snapshot_type, 
-            self.variable_snapshots.last().unwrap().variables.len());
+        let captured_vars = self.serialize_variables(scope, &snapshot_type, variables);
+        let changed = self.check_watchpoints(&captured_vars);
+        let mut snapshot = self.new_snapshot(function_name.clone(), snapshot_type, captured_vars, HashMap::new());
+        if self.capture_heap_stats {
+            snapshot.heap_stats = Some(HeapStats::from_isolate(scope));
+        }
+        self.push_snapshot(snapshot);
+        for (name, previous, current) in changed {
+            self.record_watchpoint_hit(function_name.clone(), name, previous, current);
+        }
 
         Ok(())
     }
 
-    pub fn get_execution_trace(&self) -> String {
-        let mut trace = String::new();
-        trace.push_str("🔍 EXECUTION TRACE:\n");
-        trace.push_str(&format!("Total function calls: {}\n", self.function_calls));
-        trace.push_str(&format!("Variable snapshots: {}\n", self.variable_snapshots.len()));
-        trace.push_str(&format!("Max call depth reached: {}\n", 
-            self.function_call_history.iter().map(|c| c.call_depth).max().unwrap_or(0)));
-        
-        trace.push_str("\n📊 FUNCTION CALL STATISTICS:\n");
-        let mut sorted_counts: Vec<_> = self.function_call_counts.iter().collect();
-        sorted_counts.sort_by_key(|(_, count)| *count);
-        sorted_counts.reverse();
+    /// Snapshot `variable_name` again whenever a capture shows it with a different value
+    pub fn add_watchpoint(&mut self, variable_name: String) {
+        self.watchpoints.entry(variable_name).or_insert(None);
+    }
 
-        for (func_name, count) in sorted_counts.iter().take(10) {
-            trace.push_str(&format!("  {} → {} calls\n", func_name, count));
+    /// Update the last known values of watched variables, returning
+    /// `(name, previous, current)` for those whose value changed structurally
+    fn check_watchpoints(&mut self, captured_vars: &HashMap<String, JSValue>) -> Vec<(String, JSValue, JSValue)> {
+        let mut changed = Vec::new();
+        for (name, last_value) in self.watchpoints.iter_mut() {
+            let Some(current) = captured_vars.get(name) else { continue };
+            if let Some(previous) = last_value.as_ref().filter(|previous| !previous.structural_eq(current)) {
+                changed.push((name.clone(), previous.clone(), current.clone()));
+            }
+            *last_value = Some(current.clone());
         }
+        changed.sort_by(|a, b| a.0.cmp(&b.0));
+        changed
+    }
 
-        trace.push_str("\n🕐 FUNCTION CALL TIMELINE:\n");
-        for (i, call) in self.function_call_history.iter().enumerate().take(20) {
-            let indent = "  ".repeat(call.call_depth.saturating_sub(1));
-            trace.push_str(&format!("  {}: {}{}({})\n", 
-                i + 1, indent, call.name, call.arguments.join(", ")));
+    fn record_watchpoint_hit(&mut self, function_name: String, name: String, previous: JSValue, current: JSValue) {
+        info!(variable = %name, function = %function_name, previous = %previous.to_display_string(),
+            current = %current.to_display_string(), "watched variable changed");
+        let metadata = HashMap::from([
+            ("variable".to_string(), JSValue::String(name.clone())),
+            ("previous".to_string(), previous),
+        ]);
+        self.record_snapshot(function_name, "watchpoint".to_string(), HashMap::from([(name, current)]), metadata);
+    }
+
+    /// `capture_variables` guarded by a condition evaluated on the JS side; when it is
+    /// false nothing is serialized. Returns whether a snapshot was taken.
+    pub fn capture_variables_if(
+        &mut self,
+        condition: bool,
+        scope: &mut v8::HandleScope,
+        function_name: String,
+        snapshot_type: String,
+        variables: HashMap<String, v8::Local<v8::Value>>,
+    ) -> Result<bool> {
+        if !condition || self.in_hot_path() {
+            return Ok(false);
         }
+        self.capture_variables(scope, function_name, snapshot_type, variables)?;
+        Ok(true)
+    }
 
-        if self.function_call_history.len() > 20 {
-            trace.push_str(&format!("  ... and {} more calls\n", 
-                self.function_call_history.len() - 20));
+    /// Capture only the variables that differ from the (reconstructed) baseline snapshot.
+    /// Returns the number of changed variables stored.
+    pub fn capture_variables_diff(
+        &mut self,
+        scope: &mut v8::HandleScope,
+        function_name: String,
+        variables: HashMap<String, v8::Local<v8::Value>>,
+        baseline_index: usize,
+    ) -> Result<usize> {
+        if self.in_hot_path() {
+            return Ok(0);
         }
 
-        // Add variable snapshot summary
-        if !self.variable_snapshots.is_empty() {
-            trace.push_str("\n📸 VARIABLE SNAPSHOTS:\n");
-            for (i, snapshot) in self.variable_snapshots.iter().enumerate().take(10) {
-                let indent = "  ".repeat(snapshot.call_depth);
-                trace.push_str(&format!("  {}: {}{} [{}] - {} vars\n", 
-                    i + 1, indent, snapshot.function_name, 
-                    snapshot.snapshot_type, snapshot.variables.len()));
-            }
-            if self.variable_snapshots.len() > 10 {
-                trace.push_str(&format!("  ... and {} more snapshots\n", 
-                    self.variable_snapshots.len() - 10));
-            }
+        let baseline = self.reconstruct_snapshot(baseline_index)
+            .ok_or_else(|| anyhow::anyhow!("No snapshot at baseline index {}", baseline_index))?;
+
+        let current = self.serialize_variables(scope, "diff", variables);
+        let removed: Vec<JSValue> = baseline.variables.keys()
+            .filter(|name| !current.contains_key(*name))
+            .map(|name| JSValue::String(name.clone()))
+            .collect();
+        let changed: HashMap<String, JSValue> = current.into_iter()
+            .filter(|(name, value)| !baseline.variables.get(name).is_some_and(|old| old.structural_eq(value)))
+            .collect();
+        let changed_count = changed.len();
+
+        let mut metadata = HashMap::from([
+            ("is_diff".to_string(), JSValue::Boolean(true)),
+            ("baseline_index".to_string(), JSValue::Number(baseline_index as f64)),
+        ]);
+        if !removed.is_empty() {
+            metadata.insert("removed_variables".to_string(), JSValue::Array(removed));
         }
 
-        trace
+        self.record_snapshot(function_name, "diff".to_string(), changed, metadata);
+        Ok(changed_count)
     }
-}
 
-/// Main time travel debugger runtime
-pub struct TimeDebuggerRuntime {
-    js_runtime: JsRuntime,
-    execution_state: Rc<RefCell<ExecutionState>>,
-    config: DebuggerConfig,
-}
+    /// History up to and including snapshot `snapshot_index`, with that snapshot's full
+    /// variable set queued for injection into the forked execution
+    pub fn fork_at(&self, snapshot_index: usize) -> Result<ExecutionState> {
+        let snapshot = self.reconstruct_snapshot(snapshot_index)
+            .ok_or_else(|| anyhow::anyhow!("No snapshot at index {}", snapshot_index))?;
 
-impl TimeDebuggerRuntime {
-    /// Create a new time travel debugger runtime
-    pub fn new(config: DebuggerConfig) -> Result<Self> {
-        let execution_state = Rc::new(RefCell::new(ExecutionState::default()));
+        let function_call_history: Vec<FunctionCall> = self.function_call_history.iter()
+            .filter(|call| call.timestamp <= snapshot.timestamp)
+            .cloned()
+            .collect();
+        let mut function_call_counts = HashMap::new();
+        for call in &function_call_history {
+            *function_call_counts.entry(call.name.clone()).or_insert(0) += call.call_count;
+        }
+
+        Ok(ExecutionState {
+            function_calls: function_call_history.iter().map(|call| call.call_count as u64).sum(),
+            current_function: Some(snapshot.function_name.clone()),
+            function_call_history,
+            function_call_counts,
+            variable_snapshots: self.variable_snapshots[..=snapshot_index].to_vec().into(),
+            output_width: self.output_width,
+            variable_aliases: self.variable_aliases.clone(),
+            max_argument_depth: self.max_argument_depth,
+            max_return_value_depth: self.max_return_value_depth,
+            snapshot_limit: Arc::new(AtomicUsize::new(self.snapshot_limit.load(Ordering::Relaxed))),
+            coupling_window: self.coupling_window,
+            compress_call_history: self.compress_call_history,
+            replay_injection: Some(snapshot),
+            ..Default::default()
+        })
+    }
+
+    /// Materialize the full variable set of a snapshot by applying its diff chain
+    pub fn reconstruct_snapshot(&self, index: usize) -> Option<VariableSnapshot> {
+        let snapshot = self.variable_snapshots.get(index)?;
+        let Some(baseline_index) = snapshot.baseline_index() else {
+            return Some(snapshot.clone());
+        };
+        // Baselines always precede their diffs, so the chain terminates
+        if baseline_index >= index {
+            return None;
+        }
+
+        let mut variables = self.reconstruct_snapshot(baseline_index)?.variables;
+        if let Some(JSValue::Array(removed)) = snapshot.metadata.get("removed_variables") {
+            for name in removed {
+                if let JSValue::String(name) = name {
+                    variables.remove(name);
+                }
+            }
+        }
+        variables.extend(snapshot.variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        Some(VariableSnapshot {
+            variables,
+            metadata: HashMap::new(),
+            ..snapshot.clone()
+        })
+    }
+
+    /// Combine two recordings, e.g. from processes sharing a trace ID or parallel test
+    /// runs. Calls and branch records are ordered by timestamp; snapshots are grouped by trace ID (groups in order of their first
+    /// snapshot, untraced ones forming a group of their own) and ordered by timestamp
+    /// within each group. Diff snapshots are expanded, since their indices change.
+    pub fn merge(self, other: ExecutionState) -> ExecutionState {
+        let mut snapshots: Vec<VariableSnapshot> = [&self, &other].into_iter()
+            .flat_map(|state| (0..state.variable_snapshots.len()).filter_map(|index| state.reconstruct_snapshot(index)))
+            .collect();
+        snapshots.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        let mut groups: Vec<Option<String>> = Vec::new();
+        for snapshot in &snapshots {
+            if !groups.contains(&snapshot.trace_id) {
+                groups.push(snapshot.trace_id.clone());
+            }
+        }
+        snapshots.sort_by_key(|snapshot| groups.iter().position(|group| *group == snapshot.trace_id));
+
+        let mut function_call_history = self.function_call_history;
+        function_call_history.extend(other.function_call_history);
+        function_call_history.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        let mut function_call_counts = self.function_call_counts;
+        for (name, count) in other.function_call_counts {
+            *function_call_counts.entry(name).or_insert(0) += count;
+        }
+        let mut branch_log = self.branch_log;
+        branch_log.extend(other.branch_log);
+        branch_log.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        let mut variable_aliases = other.variable_aliases;
+        variable_aliases.extend(self.variable_aliases);
+
+        ExecutionState {
+            function_calls: self.function_calls + other.function_calls,
+            total_execution_time: self.total_execution_time + other.total_execution_time,
+            function_call_history,
+            function_call_counts,
+            variable_snapshots: snapshots.into(),
+            branch_log,
+            variable_aliases,
+            process_trace_id: self.process_trace_id.or(other.process_trace_id),
+            ..Default::default()
+        }
+    }
+
+    fn serialize_variables(
+        &mut self,
+        scope: &mut v8::HandleScope,
+        snapshot_type: &str,
+        variables: HashMap<String, v8::Local<v8::Value>>,
+    ) -> HashMap<String, JSValue> {
+        let mut captured_vars = HashMap::new();
+
+        // Entry snapshots hold function arguments, which only need a shallow look
+        let max_depth = if snapshot_type == "entry" {
+            Some(self.max_argument_depth)
+        } else {
+            None
+        };
+        
+        // Serialize each variable using our serialization context
+        for (name, value) in variables {
+            let name = self.display_name(&name).to_string();
+            let serialized = match max_depth {
+                Some(depth) => self.serialization_context.serialize_value_with_depth(scope, value, depth),
+                None => self.serialization_context.serialize_value(scope, value),
+            };
+            match serialized {
+                Ok(serialized_value) => {
+                    captured_vars.insert(name, serialized_value);
+                },
+                Err(e) => {
+                    // If serialization fails, store an error representation
+                    captured_vars.insert(name, JSValue::Error(format!("Serialization failed: {}", e)));
+                }
+            }
+        }
+
+        captured_vars
+    }
+
+    pub(crate) fn record_snapshot(
+        &mut self,
+        function_name: String,
+        snapshot_type: String,
+        variables: HashMap<String, JSValue>,
+        metadata: HashMap<String, JSValue>,
+    ) {
+        let snapshot = self.new_snapshot(function_name, snapshot_type, variables, metadata);
+        self.push_snapshot(snapshot);
+    }
+
+    /// A snapshot taken now, at the current call depth and trace
+    fn new_snapshot(
+        &self,
+        function_name: String,
+        snapshot_type: String,
+        variables: HashMap<String, JSValue>,
+        metadata: HashMap<String, JSValue>,
+    ) -> VariableSnapshot {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+
+        VariableSnapshot {
+            timestamp,
+            function_name,
+            call_depth: self.call_stack_depth,
+            variables,
+            snapshot_type,
+            metadata,
+            trace_id: self.current_trace_id.clone(),
+            heap_stats: None,
+        }
+    }
+
+    /// Store a finished snapshot, applying the snapshot limit and eviction policy. Returns
+    /// false if the snapshot was turned away (capture stopped, limit reached or a duplicate).
+    fn push_snapshot(&mut self, snapshot: VariableSnapshot) -> bool {
+        if self.capture_stopped || self.at_rejecting_limit() {
+            return false;
+        }
+        if self.dedup_snapshots {
+            let hash = snapshot.content_hash();
+            if self.last_snapshot_hash.replace(hash) == Some(hash) {
+                self.dedup_skipped += 1;
+                return false;
+            }
+        }
+
+        debug!(function = %snapshot.function_name, snapshot_type = %snapshot.snapshot_type,
+            variables = snapshot.variables.len(), depth = self.call_stack_depth, "snapshot captured");
+
+        if self.snapshot_file.is_some() {
+            self.pending_writes.push(snapshot.clone());
+            if self.pending_writes.len() >= self.write_batch_size {
+                self.flush_snapshot_file();
+            }
+        }
+        self.snapshot_bytes += self.tracked_bytes(&snapshot);
+        self.variable_snapshots.push(snapshot);
+
+        let limit = self.snapshot_limit.load(Ordering::Relaxed);
+        if self.variable_snapshots.len() > limit {
+            self.evict_snapshots(limit);
+            if self.compress_call_history {
+                self.compress_call_history(true);
+            }
+        }
+        if let Some(max_bytes) = self.max_snapshot_bytes {
+            self.evict_to_byte_budget(max_bytes);
+        }
+        true
+    }
+
+    /// Snapshot a thrown value as an `exception` in the current function, keeping a
+    /// copy in `exceptions` even if the snapshot itself is evicted later
+    pub fn record_exception(&mut self, error: JSValue) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+
+        let snapshot = VariableSnapshot {
+            timestamp,
+            function_name: self.current_function.clone().unwrap_or_else(|| "<global>".to_string()),
+            call_depth: self.call_stack_depth,
+            variables: HashMap::from([("error".to_string(), error)]),
+            snapshot_type: "exception".to_string(),
+            metadata: HashMap::new(),
+            trace_id: self.current_trace_id.clone(),
+            heap_stats: None,
+        };
+        self.exceptions.push(snapshot.clone());
+        self.push_snapshot(snapshot);
+    }
+
+    pub fn last_exception(&self) -> Option<&VariableSnapshot> {
+        self.exceptions.last()
+    }
+
+    /// Merge runs of consecutive calls with the same name into one entry that keeps the
+    /// first call's arguments and counts the calls. Runs are calls at the same depth, whose
+    /// durations are summed, and recursion chains each one level deeper than the last, which
+    /// keep the outermost call's duration. A call that is still running is never merged.
+    /// Returns how many entries were removed.
+    pub fn compress_call_history(&mut self, merge_consecutive_duplicates: bool) -> usize {
+        if !merge_consecutive_duplicates {
+            return 0;
+        }
+
+        let before = self.function_call_history.len();
+        let mut compressed: Vec<FunctionCall> = Vec::with_capacity(before);
+        // Depth of the last call folded into `compressed.last()`
+        let mut chain_depth = 0;
+        for call in self.function_call_history.drain(..) {
+            match compressed.last_mut() {
+                Some(previous) if previous.name == call.name
+                    && (call.call_depth == chain_depth || call.call_depth == chain_depth + 1)
+                    && previous.duration_ms.is_some()
+                    && call.duration_ms.is_some() => {
+                    previous.call_count += call.call_count;
+                    if call.call_depth == chain_depth {
+                        previous.duration_ms = previous.duration_ms.zip(call.duration_ms).map(|(a, b)| a + b);
+                    }
+                    chain_depth = call.call_depth;
+                },
+                _ => {
+                    chain_depth = call.call_depth;
+                    compressed.push(call);
+                },
+            }
+        }
+        self.function_call_history = compressed;
+        before - self.function_call_history.len()
+    }
+
+    /// Index of the first snapshot taken while `function_call_history[call_index]` was
+    /// running. An entry merged by `compress_call_history` maps to its first call's snapshot.
+    pub fn function_call_to_snapshot_index(&self, call_index: usize) -> Option<usize> {
+        let call = self.function_call_history.get(call_index)?;
+        self.variable_snapshots.iter().position(|snapshot| snapshot.function_name == call.name
+            && snapshot.call_depth >= call.call_depth
+            && snapshot.timestamp >= call.timestamp)
+    }
+
+    /// The last `n` snapshots, oldest first
+    pub fn tail_snapshot_window(&self, n: usize) -> impl DoubleEndedIterator<Item = &VariableSnapshot> {
+        let len = self.variable_snapshots.len();
+        self.variable_snapshots[len.saturating_sub(n)..].iter()
+    }
+
+    /// The last `n` recorded calls, oldest first
+    pub fn tail_call_window(&self, n: usize) -> impl DoubleEndedIterator<Item = &FunctionCall> {
+        let len = self.function_call_history.len();
+        self.function_call_history[len.saturating_sub(n)..].iter()
+    }
+
+    /// Name a variable should be shown and stored under, honoring `variable_aliases`
+    pub fn display_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.variable_aliases.get(name).map(String::as_str).unwrap_or(name)
+    }
+
+    /// Refuse any further snapshot and make those already in `snapshot_file` durable,
+    /// ahead of saving a partial trace
+    pub fn stop_capture(&mut self) {
+        self.capture_stopped = true;
+        self.flush_snapshot_file();
+        if let Some(file) = &self.snapshot_file
+            && let Err(e) = file.sync() {
+            warn!(path = %file.path().display(), error = %e, "failed to flush snapshot file");
+        }
+    }
+
+    /// Write the snapshots still waiting for a full `write_batch_size` batch to `snapshot_file`
+    pub fn flush_snapshot_file(&mut self) {
+        if let Some(file) = &mut self.snapshot_file
+            && !self.pending_writes.is_empty() {
+            if let Err(e) = file.append_batch(&self.pending_writes) {
+                warn!(path = %file.path().display(), error = %e, "failed to write snapshot batch");
+            }
+            self.pending_writes.clear();
+        }
+    }
+
+    /// Whether `EvictionPolicy::Error` should turn away the next snapshot
+    fn at_rejecting_limit(&self) -> bool {
+        self.eviction_policy == EvictionPolicy::Error
+            && self.variable_snapshots.len() >= self.snapshot_limit.load(Ordering::Relaxed)
+    }
+
+    /// Apply the eviction policy until at most `keep` snapshots remain
+    pub(crate) fn evict_snapshots(&mut self, keep: usize) {
+        let removed = match self.eviction_policy {
+            EvictionPolicy::OldestFirst => self.clear_old_snapshots(keep),
+            EvictionPolicy::LowestDepth => {
+                let mut removed = 0;
+                while self.variable_snapshots.len() > keep {
+                    let Some(index) = self.variable_snapshots.iter().enumerate()
+                        .min_by_key(|(_, snapshot)| snapshot.call_depth)
+                        .map(|(index, _)| index) else { break };
+                    self.remove_snapshot(index);
+                    removed += 1;
+                }
+                removed
+            },
+            // Over the limit only if the limit shrank; existing snapshots are kept
+            EvictionPolicy::Error => 0,
+        };
+        self.eviction_count += removed as u64;
+    }
+
+    /// Drop the oldest snapshots until the rest fit in `max_bytes`, always keeping the newest
+    fn evict_to_byte_budget(&mut self, max_bytes: usize) {
+        let mut excess = self.snapshot_bytes.saturating_sub(max_bytes);
+        let len = self.variable_snapshots.len();
+        let evicted = self.variable_snapshots[..len.saturating_sub(1)].iter()
+            .take_while(|snapshot| {
+                let over = excess > 0;
+                excess = excess.saturating_sub(snapshot.estimated_bytes());
+                over
+            })
+            .count();
+        if evicted > 0 {
+            self.eviction_count += self.clear_old_snapshots(len - evicted) as u64;
+        }
+    }
+
+    /// `snapshot.estimated_bytes()` while a byte budget is set; 0 skips the walk otherwise
+    fn tracked_bytes(&self, snapshot: &VariableSnapshot) -> usize {
+        if self.max_snapshot_bytes.is_some() { snapshot.estimated_bytes() } else { 0 }
+    }
+
+    /// Remove one snapshot, keeping diff baselines and hot path summaries pointing at the same data
+    fn remove_snapshot(&mut self, index: usize) {
+        let dependents: Vec<(usize, VariableSnapshot)> = (0..self.variable_snapshots.len())
+            .filter(|i| self.variable_snapshots[*i].baseline_index() == Some(index))
+            .filter_map(|i| Some((i, self.reconstruct_snapshot(i)?)))
+            .collect();
+        for (i, snapshot) in dependents {
+            self.snapshot_bytes += self.tracked_bytes(&snapshot);
+            let replaced = std::mem::replace(&mut self.variable_snapshots[i], snapshot);
+            self.snapshot_bytes = self.snapshot_bytes.saturating_sub(self.tracked_bytes(&replaced));
+        }
+
+        let removed = self.variable_snapshots.remove(index);
+        self.snapshot_bytes = self.snapshot_bytes.saturating_sub(self.tracked_bytes(&removed));
+        for snapshot in &mut self.variable_snapshots {
+            if let Some(base) = snapshot.baseline_index().filter(|base| *base > index) {
+                snapshot.metadata.insert("baseline_index".to_string(), JSValue::Number((base - 1) as f64));
+            }
+        }
+        for path in &mut self.hot_paths {
+            path.summary_snapshot_index = match path.summary_snapshot_index {
+                Some(i) if i == index => None,
+                Some(i) if i > index => Some(i - 1),
+                other => other,
+            };
+        }
+    }
+
+    /// Drop the oldest snapshots so at most `keep` remain. Returns how many were removed.
+    pub fn clear_old_snapshots(&mut self, keep: usize) -> usize {
+        let removed = self.variable_snapshots.len().saturating_sub(keep);
+        if removed == 0 {
+            return 0;
+        }
+        // Diffs whose baseline is about to disappear become full snapshots
+        let materialized: Vec<(usize, VariableSnapshot)> = (removed..self.variable_snapshots.len())
+            .filter(|index| self.variable_snapshots[*index].baseline_index().is_some_and(|base| base < removed))
+            .filter_map(|index| Some((index, self.reconstruct_snapshot(index)?)))
+            .collect();
+        for (index, snapshot) in materialized {
+            self.snapshot_bytes += self.tracked_bytes(&snapshot);
+            let replaced = std::mem::replace(&mut self.variable_snapshots[index], snapshot);
+            self.snapshot_bytes = self.snapshot_bytes.saturating_sub(self.tracked_bytes(&replaced));
+        }
+
+        let freed: usize = self.variable_snapshots[..removed].iter().map(|snapshot| self.tracked_bytes(snapshot)).sum();
+        self.snapshot_bytes = self.snapshot_bytes.saturating_sub(freed);
+        self.variable_snapshots.drain(..removed);
+        for snapshot in &mut self.variable_snapshots {
+            if let Some(base) = snapshot.baseline_index() {
+                snapshot.metadata.insert("baseline_index".to_string(), JSValue::Number((base - removed) as f64));
+            }
+        }
+
+        // Keep hot path summaries pointing at the same snapshot
+        for path in &mut self.hot_paths {
+            path.summary_snapshot_index = path.summary_snapshot_index
+                .and_then(|index| index.checked_sub(removed));
+        }
+
+        removed
+    }
+
+    pub fn start_hot_path(&mut self, label: String) {
+        info!(label = %label, "hot path started");
+        self.active_hot_paths.push(ActiveHotPath {
+            label,
+            start_ms: current_time_ms(),
+            function_calls_at_start: self.function_calls,
+            call_counts_at_start: self.function_call_counts.clone(),
+        });
+    }
+
+    /// Close the innermost hot path with `label`, recording a single summary snapshot
+    /// of the call counts that changed inside it. Returns the elapsed time in ms.
+    pub fn end_hot_path(&mut self, label: &str) -> Result<f64> {
+        let position = self.active_hot_paths.iter()
+            .rposition(|path| path.label == label)
+            .ok_or_else(|| anyhow::anyhow!("No active hot path named {}", label))?;
+        let path = self.active_hot_paths.remove(position);
+
+        let end_ms = current_time_ms();
+        let duration_ms = end_ms - path.start_ms;
+        let call_count_in_path = self.function_calls - path.function_calls_at_start;
+
+        let changed_counts: HashMap<String, JSValue> = self.function_call_counts.iter()
+            .filter_map(|(name, count)| {
+                let before = path.call_counts_at_start.get(name).copied().unwrap_or(0);
+                (*count != before).then(|| (name.clone(), JSValue::Number((count - before) as f64)))
+            })
+            .collect();
+
+        info!(label = %path.label, calls = call_count_in_path, duration_ms, "hot path ended");
+
+        // Nested paths are summarized by their outermost enclosing path
+        let summarize = !self.in_hot_path();
+        let label = path.label.clone();
+        // Recorded before the summary is pushed, so eviction keeps the index in step
+        self.hot_paths.push(HotPathRecord {
+            label: path.label,
+            start_ms: path.start_ms,
+            end_ms,
+            call_count_in_path,
+            summary_snapshot_index: summarize.then_some(self.variable_snapshots.len()),
+        });
+
+        if summarize {
+            let accepted = self.push_snapshot(VariableSnapshot {
+                timestamp: end_ms / 1000.0,
+                function_name: label,
+                call_depth: self.call_stack_depth,
+                variables: HashMap::from([
+                    ("call_count".to_string(), JSValue::Number(call_count_in_path as f64)),
+                    ("duration_ms".to_string(), JSValue::Number(duration_ms)),
+                    ("calls".to_string(), JSValue::Object(changed_counts)),
+                ]),
+                snapshot_type: "hot_path".to_string(),
+                metadata: HashMap::new(),
+                trace_id: self.current_trace_id.clone(),
+                heap_stats: None,
+            });
+            if !accepted && let Some(record) = self.hot_paths.last_mut() {
+                record.summary_snapshot_index = None;
+            }
+        }
+
+        Ok(duration_ms)
+    }
+
+    /// `avg(pending_ops) / avg(completed_ops + 1)` over the recorded samples
+    pub fn event_loop_saturation(&self) -> f64 {
+        if self.event_loop_samples.is_empty() {
+            return 0.0;
+        }
+        let samples = self.event_loop_samples.len() as f64;
+        let avg_pending = self.event_loop_samples.iter()
+            .map(|(_, stats)| stats.pending_ops as f64)
+            .sum::<f64>() / samples;
+        let avg_completed = self.event_loop_samples.iter()
+            .map(|(_, stats)| stats.completed_ops as f64 + 1.0)
+            .sum::<f64>() / samples;
+        avg_pending / avg_completed
+    }
+
+    pub fn get_event_loop_summary(&self) -> String {
+        let mut summary = String::new();
+        summary.push_str(&format!("⏱️  EVENT LOOP ({} samples):\n", self.event_loop_samples.len()));
+        if let Some((_, last)) = self.event_loop_samples.last() {
+            let peak_pending = self.event_loop_samples.iter().map(|(_, s)| s.pending_ops).max().unwrap_or(0);
+            summary.push_str(&format!("  Dispatched ops: {}\n", last.dispatched_ops));
+            summary.push_str(&format!("  Completed ops: {}\n", last.completed_ops));
+            summary.push_str(&format!("  Peak pending ops: {}\n", peak_pending));
+        }
+        summary.push_str(&format!("  Saturation: {:.4}\n", self.event_loop_saturation()));
+        summary
+    }
+
+    pub fn total_gc_time_ms(&self) -> f64 {
+        self.gc_events.iter().map(|event| event.duration_ms).sum()
+    }
+
+    /// Share of the total execution time spent in GC pauses, as a percentage
+    pub fn gc_overhead_pct(&self) -> f64 {
+        let total_ms = self.total_execution_time.as_secs_f64() * 1000.0;
+        if total_ms == 0.0 {
+            return 0.0;
+        }
+        self.total_gc_time_ms() / total_ms * 100.0
+    }
+
+    pub fn get_gc_summary(&self) -> String {
+        let mut summary = String::new();
+        summary.push_str(&format!("🗑️  GARBAGE COLLECTION ({} pauses):\n", self.gc_events.len()));
+        for kind in [GcKind::Minor, GcKind::Major] {
+            let events: Vec<&GcEvent> = self.gc_events.iter().filter(|e| e.kind == kind).collect();
+            if events.is_empty() {
+                continue;
+            }
+            let duration: f64 = events.iter().map(|e| e.duration_ms).sum();
+            let freed: usize = events.iter().map(|e| e.freed_bytes()).sum();
+            summary.push_str(&format!("  {:?}: {} pauses, {:.2}ms, {} freed\n",
+                kind, events.len(), duration, format_bytes(freed)));
+        }
+        summary.push_str(&format!("  Total GC time: {:.2}ms ({:.2}% of execution)\n",
+            self.total_gc_time_ms(), self.gc_overhead_pct()));
+        summary
+    }
+
+    /// Serialize the recorded history as a pretty-printed JSON document
+    pub fn serialize_to_json(&self) -> Result<Vec<u8>> {
+        let document = serde_json::json!({
+            "function_calls": self.function_calls,
+            "total_execution_time_ms": self.total_execution_time.as_secs_f64() * 1000.0,
+            "current_function": self.current_function,
+            "call_stack_depth": self.call_stack_depth,
+            "function_call_history": self.function_call_history,
+            "function_call_counts": self.function_call_counts,
+            "variable_snapshots": self.variable_snapshots,
+            "hot_paths": self.hot_paths,
+            "event_loop_samples": self.event_loop_samples,
+            "event_loop_saturation": self.event_loop_saturation(),
+            "gc_events": self.gc_events,
+            "state_machine_events": self.state_machine_events,
+            "branch_log": self.branch_log,
+            "loop_iterations": self.loop_iterations,
+            "property_mutations": self.property_mutations,
+            "promise_events": self.promise_events,
+            "perf_timeline": self.perf_timeline_json(),
+            "total_gc_time_ms": self.total_gc_time_ms(),
+        });
+
+        Ok(serde_json::to_vec_pretty(&document)?)
+    }
+
+    /// Write the history as NDJSON: a `header` line, then one `call` line per function call
+    /// and one `snapshot` line per snapshot. Values use the `JSValue::to_json_value` form.
+    pub fn export_ndjson(&self, writer: &mut impl std::io::Write) -> Result<()> {
+        let header = serde_json::json!({
+            "type": "header",
+            "format_version": 1,
+            "debugger_version": env!("CARGO_PKG_VERSION"),
+            "exported_at": chrono::Utc::now().to_rfc3339(),
+            "function_calls": self.function_calls,
+            "total_execution_time_ms": self.total_execution_time.as_secs_f64() * 1000.0,
+            "first_timestamp": self.function_call_history.first().map(|call| call.timestamp),
+            "last_timestamp": self.function_call_history.last().map(|call| call.timestamp),
+            "config": {
+                "max_snapshots": self.snapshot_limit.load(Ordering::Relaxed),
+                "max_argument_depth": self.max_argument_depth,
+                "max_return_value_depth": self.max_return_value_depth,
+                "variable_aliases": self.variable_aliases,
+            },
+        });
+        serde_json::to_writer(&mut *writer, &header)?;
+        writeln!(writer)?;
+
+        for call in &self.function_call_history {
+            let record = serde_json::json!({
+                "type": "call",
+                "name": call.name,
+                "timestamp": call.timestamp,
+                "call_depth": call.call_depth,
+                "arguments": call.arguments,
+                "file_location": call.file_location,
+                "line_number": call.line_number,
+                "duration_ms": call.duration_ms,
+                "call_count": call.call_count,
+            });
+            serde_json::to_writer(&mut *writer, &record)?;
+            writeln!(writer)?;
+        }
+
+        for (index, snapshot) in self.variable_snapshots.iter().enumerate() {
+            let values = |map: &HashMap<String, JSValue>| map.iter()
+                .map(|(name, value)| (name.clone(), value.to_json_value()))
+                .collect::<serde_json::Map<_, _>>();
+            let record = serde_json::json!({
+                "type": "snapshot",
+                "index": index,
+                "timestamp": snapshot.timestamp,
+                "function_name": snapshot.function_name,
+                "call_depth": snapshot.call_depth,
+                "snapshot_type": snapshot.snapshot_type,
+                "variables": values(&snapshot.variables),
+                "metadata": values(&snapshot.metadata),
+            });
+            serde_json::to_writer(&mut *writer, &record)?;
+            writeln!(writer)?;
+        }
+
+        for import in self.import_timeline() {
+            let record = serde_json::json!({
+                "type": "dynamic_import",
+                "specifier": import.specifier,
+                "resolved_url": import.resolved_url,
+                "timestamp": import.timestamp,
+                "from_function": import.from_function,
+            });
+            serde_json::to_writer(&mut *writer, &record)?;
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_execution_trace(&self) -> String {
+        let mut trace = String::new();
+        trace.push_str("🔍 EXECUTION TRACE:\n");
+        trace.push_str(&format!("Total function calls: {}\n", self.function_calls));
+        trace.push_str(&format!("Variable snapshots: {}\n", self.variable_snapshots.len()));
+        if self.dedup_snapshots {
+            trace.push_str(&format!("Duplicate snapshots skipped: {}\n", self.dedup_skipped));
+        }
+        trace.push_str(&format!("Max call depth reached: {}\n",
+            self.function_call_history.iter().map(|c| c.call_depth).max().unwrap_or(0).max(self.max_depth_reached)));
+        
+        trace.push_str("\n📊 FUNCTION CALL STATISTICS:\n");
+        let mut sorted_counts: Vec<_> = self.function_call_counts.iter().collect();
+        sorted_counts.sort_by_key(|(_, count)| *count);
+        sorted_counts.reverse();
+
+        for (func_name, count) in sorted_counts.iter().take(10) {
+            let suffix = format!(" → {} calls", count);
+            trace.push_str(&fit_line("  ", func_name, &suffix, self.output_width));
+        }
+
+        trace.push_str("\n🕐 FUNCTION CALL TIMELINE:\n");
+        for (i, call) in self.function_call_history.iter().enumerate().take(20) {
+            let prefix = format!("  {}: {}", i + 1, "  ".repeat(call.call_depth.saturating_sub(1)));
+            let mut suffix = format!("({})", call.arguments.join(", "));
+            if let Some(value) = &call.return_value {
+                suffix.push_str(&format!(" → {}", value.to_display_string()));
+            }
+            trace.push_str(&fit_line(&prefix, &call.name, &suffix, self.output_width));
+        }
+
+        if self.function_call_history.len() > 20 {
+            trace.push_str(&format!("  ... and {} more calls\n", 
+                self.function_call_history.len() - 20));
+        }
+
+        if !self.hot_paths.is_empty() {
+            trace.push_str("\n🔥 HOT PATHS:\n");
+            for path in &self.hot_paths {
+                let suffix = format!(" → {} calls in {:.3}ms", path.call_count_in_path, path.end_ms - path.start_ms);
+                trace.push_str(&fit_line("  ", &path.label, &suffix, self.output_width));
+            }
+        }
+
+        // Add variable snapshot summary
+        if !self.variable_snapshots.is_empty() {
+            trace.push_str("\n📸 VARIABLE SNAPSHOTS:\n");
+            for (i, snapshot) in self.variable_snapshots.iter().enumerate().take(10) {
+                let prefix = format!("  {}: {}", i + 1, "  ".repeat(snapshot.call_depth));
+                let suffix = format!(" [{}] - {} vars", snapshot.snapshot_type, snapshot.variables.len());
+                trace.push_str(&fit_line(&prefix, &snapshot.function_name, &suffix, self.output_width));
+            }
+            if self.variable_snapshots.len() > 10 {
+                trace.push_str(&format!("  ... and {} more snapshots\n", 
+                    self.variable_snapshots.len() - 10));
+            }
+        }
+
+        if !self.gc_events.is_empty() {
+            let minor = self.gc_events.iter().filter(|e| e.kind == GcKind::Minor).count();
+            let freed: usize = self.gc_events.iter().map(GcEvent::freed_bytes).sum();
+            trace.push_str(&format!("\n🗑️  GC: {} pauses ({} minor, {} major), {:.2}ms, {} freed\n",
+                self.gc_events.len(), minor, self.gc_events.len() - minor, self.total_gc_time_ms(), format_bytes(freed)));
+        }
+
+        trace
+    }
+
+    /// Render the first `max_calls` recorded calls as a Mermaid `sequenceDiagram`.
+    /// The caller of each call is the most recent call one level shallower.
+    pub fn generate_mermaid_sequence_diagram(&self, max_calls: usize) -> String {
+        let mut diagram = String::from("sequenceDiagram\n");
+        let mut active_at_depth: Vec<&str> = Vec::new();
+
+        for call in self.function_call_history.iter().take(max_calls) {
+            let depth = call.call_depth.max(1);
+            active_at_depth.truncate(depth - 1);
+            let caller = active_at_depth.last().copied().unwrap_or("Program");
+
+            diagram.push_str(&format!("    {}->>{}: {}({})\n",
+                mermaid_participant(caller), mermaid_participant(&call.name),
+                call.name, call.arguments.join(", ")));
+
+            // Skipped depths (e.g. untraced frames) fall back to the nearest known caller
+            while active_at_depth.len() < depth - 1 {
+                active_at_depth.push(caller);
+            }
+            active_at_depth.push(&call.name);
+        }
+
+        diagram
+    }
+
+    /// The `n` largest snapshots as `(snapshot_index, estimated_bytes)`, largest first
+    pub fn top_n_snapshots_by_size(&self, n: usize) -> Vec<(usize, usize)> {
+        let mut sizes: Vec<(usize, usize)> = self.variable_snapshots.iter()
+            .map(VariableSnapshot::estimated_bytes)
+            .enumerate()
+            .collect();
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        sizes.truncate(n);
+        sizes
+    }
+
+    /// Histogram of snapshot sizes as `(bucket_lower_bound_bytes, count)` over 10 buckets:
+    /// 0–1KB, 1–10KB, 10–100KB, ... with the last bucket open-ended
+    pub fn size_distribution(&self) -> Vec<(usize, usize)> {
+        let mut buckets: Vec<(usize, usize)> = (0..SIZE_BUCKETS)
+            .map(|i| (if i == 0 { 0 } else { 1024 * 10usize.pow(i as u32 - 1) }, 0))
+            .collect();
+
+        for snapshot in &self.variable_snapshots {
+            let size = snapshot.estimated_bytes();
+            let bucket = buckets.iter().rposition(|(lower, _)| size >= *lower).unwrap_or(0);
+            buckets[bucket].1 += 1;
+        }
+
+        buckets
+    }
+
+    pub fn get_size_analysis(&self, top_n: usize) -> String {
+        let mut analysis = String::new();
+        analysis.push_str(&format!("📏 SNAPSHOT SIZE ANALYSIS ({} snapshots):\n", self.variable_snapshots.len()));
+
+        analysis.push_str(&format!("\n  Largest {} snapshots:\n", top_n));
+        for (index, size) in self.top_n_snapshots_by_size(top_n) {
+            let snapshot = &self.variable_snapshots[index];
+            let prefix = format!("    #{} ", index);
+            let suffix = format!(" [{}] - {}", snapshot.snapshot_type, format_bytes(size));
+            analysis.push_str(&fit_line(&prefix, &snapshot.function_name, &suffix, self.output_width));
+        }
+
+        analysis.push_str("\n  Size distribution:\n");
+        let distribution = self.size_distribution();
+        for (i, (lower, count)) in distribution.iter().enumerate() {
+            let range = match distribution.get(i + 1) {
+                Some((upper, _)) => format!("{}–{}", format_bytes(*lower), format_bytes(*upper)),
+                None => format!("≥{}", format_bytes(*lower)),
+            };
+            analysis.push_str(&format!("    {:>14}: {}\n", range, count));
+        }
+
+        analysis
+    }
+
+    /// Variables captured in at least 10 snapshots of the same function whose size grows
+    /// faster than `min_growth_rate` bytes per call, fastest growing first
+    pub fn detect_memory_leaks(&self, min_growth_rate: f64) -> Vec<MemoryLeakCandidate> {
+        let mut sizes: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+        for snapshot in &self.variable_snapshots {
+            for (name, value) in &snapshot.variables {
+                sizes.entry((snapshot.function_name.as_str(), name.as_str()))
+                    .or_default()
+                    .push(value.estimated_heap_bytes());
+            }
+        }
+
+        let mut candidates: Vec<MemoryLeakCandidate> = sizes.into_iter()
+            .filter(|(_, samples)| samples.len() >= LEAK_MIN_SAMPLES)
+            .filter_map(|((function_name, variable_name), samples)| {
+                let growth_rate = regression_slope(&samples);
+                (growth_rate > min_growth_rate).then(|| MemoryLeakCandidate {
+                    variable_name: variable_name.to_string(),
+                    function_name: function_name.to_string(),
+                    size_at_start: samples[0],
+                    size_at_end: samples[samples.len() - 1],
+                    growth_rate,
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.growth_rate.total_cmp(&a.growth_rate)
+            .then_with(|| a.variable_name.cmp(&b.variable_name)));
+        candidates
+    }
+
+    pub fn get_leak_summary(&self, min_growth_rate: f64) -> String {
+        let mut summary = String::new();
+        let candidates = self.detect_memory_leaks(min_growth_rate);
+        summary.push_str(&format!("🚰 MEMORY LEAK CANDIDATES (> {} bytes/call):\n", min_growth_rate));
+        if candidates.is_empty() {
+            summary.push_str("  No variables grow faster than the threshold\n");
+        }
+        for candidate in candidates {
+            let suffix = format!(" → {:.1} bytes/call ({} → {})", candidate.growth_rate,
+                format_bytes(candidate.size_at_start), format_bytes(candidate.size_at_end));
+            let name = format!("{}.{}", candidate.function_name, self.display_name(&candidate.variable_name));
+            summary.push_str(&fit_line("  ", &name, &suffix, self.output_width));
+        }
+        summary
+    }
+
+    /// For every pair of distinct functions (names in sorted order), the number of
+    /// `coupling_window`-call sliding windows over the call history containing both
+    pub fn function_coupling_matrix(&self) -> HashMap<(String, String), u32> {
+        let mut matrix = HashMap::new();
+        let calls = &self.function_call_history;
+        let window = self.coupling_window.max(1).min(calls.len());
+        if window == 0 {
+            return matrix;
+        }
+
+        for start in 0..=calls.len() - window {
+            let mut names: Vec<&str> = calls[start..start + window].iter().map(|c| c.name.as_str()).collect();
+            names.sort_unstable();
+            names.dedup();
+
+            for (i, a) in names.iter().enumerate() {
+                for b in &names[i + 1..] {
+                    *matrix.entry((a.to_string(), b.to_string())).or_insert(0) += 1;
+                }
+            }
+        }
+
+        matrix
+    }
+
+    /// Coupled pairs seen together in at least `min_count` windows, most coupled first
+    pub fn strongly_coupled_pairs(&self, min_count: u32) -> Vec<((String, String), u32)> {
+        let mut pairs: Vec<_> = self.function_coupling_matrix().into_iter()
+            .filter(|(_, count)| *count >= min_count)
+            .collect();
+        pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        pairs
+    }
+
+    pub fn get_coupling_summary(&self, top_n: usize) -> String {
+        let mut summary = String::new();
+        summary.push_str(&format!("🔗 FUNCTION COUPLING (window of {} calls):\n", self.coupling_window));
+        let pairs = self.strongly_coupled_pairs(1);
+        if pairs.is_empty() {
+            summary.push_str("  No function pairs co-occur\n");
+        }
+        for ((a, b), count) in pairs.into_iter().take(top_n) {
+            let suffix = format!(" → {} windows", count);
+            summary.push_str(&fit_line("  ", &format!("{} ↔ {}", a, b), &suffix, self.output_width));
+        }
+        summary
+    }
+
+    /// Runs of more than `threshold` consecutive calls with the same name and arguments,
+    /// in history order. Calls merged by `compress_call_history` count individually.
+    pub fn detect_repeated_calls(&self, threshold: u32) -> Vec<RepeatedCallWarning> {
+        let mut warnings = Vec::new();
+        for run in self.function_call_history.chunk_by(|a, b| a.name == b.name && a.arguments == b.arguments) {
+            let count: u32 = run.iter().map(|call| call.call_count).sum();
+            if count > threshold {
+                warnings.push(RepeatedCallWarning {
+                    function_name: run[0].name.clone(),
+                    argument_signature: run[0].arguments.join(", "),
+                    count,
+                });
+            }
+        }
+        warnings
+    }
+
+    /// The run of identical calls ending with the latest call, if that call just took
+    /// it past `threshold`
+    fn repeated_call_crossing(&self, threshold: u32) -> Option<RepeatedCallWarning> {
+        let (last, earlier) = self.function_call_history.split_last()?;
+        let before: u32 = earlier.iter().rev()
+            .take_while(|call| call.name == last.name && call.arguments == last.arguments)
+            .map(|call| call.call_count)
+            .sum();
+        let count = before + last.call_count;
+        (before <= threshold && count > threshold).then(|| RepeatedCallWarning {
+            function_name: last.name.clone(),
+            argument_signature: last.arguments.join(", "),
+            count,
+        })
+    }
+
+    /// The `n` most called functions, most calls first (ties by name)
+    pub fn hot_functions(&self, n: usize) -> Vec<(&str, u32)> {
+        let mut functions: Vec<(&str, u32)> = self.function_call_counts.iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        functions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        functions.truncate(n);
+        functions
+    }
+
+    /// The `n` most frequent runs of `HOT_CALL_PATH_LENGTH` consecutive calls in the
+    /// history, counted over a sliding window, most frequent first
+    pub fn hot_call_paths(&self, n: usize) -> Vec<(Vec<String>, u32)> {
+        let mut counts: HashMap<Vec<&str>, u32> = HashMap::new();
+        for window in self.function_call_history.windows(HOT_CALL_PATH_LENGTH) {
+            *counts.entry(window.iter().map(|call| call.name.as_str()).collect()).or_insert(0) += 1;
+        }
+        let mut paths: Vec<(Vec<&str>, u32)> = counts.into_iter().collect();
+        paths.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        paths.into_iter()
+            .take(n)
+            .map(|(path, count)| (path.into_iter().map(String::from).collect(), count))
+            .collect()
+    }
+
+    pub fn get_hot_functions_summary(&self, top_n: usize) -> String {
+        let mut summary = format!("🔥 TOP {} HOTTEST FUNCTIONS:\n", top_n);
+        for (name, count) in self.hot_functions(top_n) {
+            let suffix = format!(" → {} calls", count);
+            summary.push_str(&fit_line("  ", name, &suffix, self.output_width));
+        }
+        summary
+    }
+
+    pub fn log_state_machine_transition(&mut self, machine_name: String, from_state: String, to_state: String, event: String) {
+        self.state_machine_events.push(StateMachineEvent {
+            machine_name,
+            from_state,
+            to_state,
+            event,
+            timestamp_ms: current_time_ms(),
+            function_name: self.current_function.clone(),
+        });
+    }
+
+    /// Mermaid `stateDiagram-v2` of the transitions observed for `machine_name`.
+    /// Repeated transitions are drawn once, labelled with their events and count.
+    pub fn state_machine_diagram(&self, machine_name: &str) -> String {
+        let mut edges: Vec<((&str, &str), Vec<&str>, usize)> = Vec::new();
+        for event in self.state_machine_events.iter().filter(|e| e.machine_name == machine_name) {
+            let key = (event.from_state.as_str(), event.to_state.as_str());
+            match edges.iter_mut().find(|(edge, _, _)| *edge == key) {
+                Some((_, labels, count)) => {
+                    if !labels.contains(&event.event.as_str()) {
+                        labels.push(&event.event);
+                    }
+                    *count += 1;
+                },
+                None => edges.push((key, vec![&event.event], 1)),
+            }
+        }
+
+        let mut diagram = String::from("stateDiagram-v2\n");
+        if let Some(((initial, _), _, _)) = edges.first() {
+            diagram.push_str(&format!("    [*] --> {}\n", mermaid_participant(initial)));
+        }
+        for ((from, to), labels, count) in &edges {
+            let times = if *count > 1 { format!(" (×{})", count) } else { String::new() };
+            diagram.push_str(&format!("    {} --> {}: {}{}\n",
+                mermaid_participant(from), mermaid_participant(to), labels.join(", "), times));
+        }
+        diagram
+    }
+
+    /// Observed transitions of `machine_name` whose `(from, to)` pair is not in `valid_transitions`
+    pub fn invalid_transitions(&self, machine_name: &str, valid_transitions: &[(String, String)]) -> Vec<StateMachineEvent> {
+        self.state_machine_events.iter()
+            .filter(|e| e.machine_name == machine_name)
+            .filter(|e| !valid_transitions.iter().any(|(from, to)| *from == e.from_state && *to == e.to_state))
+            .cloned()
+            .collect()
+    }
+
+    /// Milliseconds since the performance time origin
+    pub fn perf_now(&self) -> f64 {
+        current_time_ms() - self.perf_time_origin_ms
+    }
+
+    pub fn performance_mark(&mut self, name: String, timestamp: f64) -> Result<&PerfMark> {
+        if !timestamp.is_finite() || timestamp < 0.0 {
+            return Err(anyhow::anyhow!("Mark {} has invalid start time {}", name, timestamp));
+        }
+        self.perf_marks.push(PerfMark { name, start_time: timestamp });
+        Ok(self.perf_marks.last().expect("mark was just pushed"))
+    }
+
+    /// Measure from `start_mark` to `end_mark`, each the latest mark of that name. An empty
+    /// start means the time origin and an empty end means now, as in `performance.measure`.
+    pub fn performance_measure(&mut self, name: String, start_mark: String, end_mark: String) -> Result<&PerfMeasure> {
+        let resolve = |mark: &str| self.perf_marks.iter().rev()
+            .find(|m| m.name == mark)
+            .map(|m| m.start_time)
+            .ok_or_else(|| anyhow::anyhow!("No mark named {} for measure {}", mark, name));
+        let start_time = if start_mark.is_empty() { 0.0 } else { resolve(&start_mark)? };
+        let end_time = if end_mark.is_empty() { self.perf_now() } else { resolve(&end_mark)? };
+
+        self.perf_measures.push(PerfMeasure {
+            name,
+            start_time,
+            duration: end_time - start_time,
+            start_mark: (!start_mark.is_empty()).then_some(start_mark),
+            end_mark: (!end_mark.is_empty()).then_some(end_mark),
+        });
+        Ok(self.perf_measures.last().expect("measure was just pushed"))
+    }
+
+    /// Marks and measures as `PerformanceEntry` objects ordered by start time, the shape
+    /// `performance.getEntries()` returns
+    pub fn perf_timeline_json(&self) -> serde_json::Value {
+        let mut entries: Vec<(f64, serde_json::Value)> = self.perf_marks.iter()
+            .map(|mark| (mark.start_time, serde_json::json!({
+                "name": mark.name,
+                "entryType": "mark",
+                "startTime": mark.start_time,
+                "duration": 0.0,
+            })))
+            .chain(self.perf_measures.iter().map(|measure| (measure.start_time, serde_json::json!({
+                "name": measure.name,
+                "entryType": "measure",
+                "startTime": measure.start_time,
+                "duration": measure.duration,
+                "detail": { "startMark": measure.start_mark, "endMark": measure.end_mark },
+            }))))
+            .collect();
+        // Stable, so entries at the same time keep marks before measures
+        entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+        serde_json::json!({
+            "timeOrigin": self.perf_time_origin_ms,
+            "entries": entries.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn log_branch(&mut self, function_name: String, branch_id: u32, taken: bool) {
+        self.branch_log.push(BranchRecord { function_name, branch_id, taken, timestamp: current_time_ms() });
+    }
+
+    /// `(times_true, times_false)` for each `(function_name, branch_id)` in the branch log
+    pub fn branch_coverage_summary(&self) -> HashMap<(String, u32), (u32, u32)> {
+        let mut summary: HashMap<(String, u32), (u32, u32)> = HashMap::new();
+        for record in &self.branch_log {
+            let counts = summary.entry((record.function_name.clone(), record.branch_id)).or_default();
+            if record.taken {
+                counts.0 += 1;
+            } else {
+                counts.1 += 1;
+            }
+        }
+        summary
+    }
+
+    pub fn log_property_mutation(&mut self, object_ref: String, property_name: String, old_value: JSValue, new_value: JSValue) {
+        self.property_mutations.push(PropertyMutation {
+            object_ref,
+            property_name,
+            old_value,
+            new_value,
+            timestamp: current_time_ms(),
+            call_depth: self.call_stack_depth,
+        });
+    }
+
+    /// Writes to watched properties named `property_name`, on any object, oldest first
+    pub fn property_history(&self, property_name: &str) -> Vec<&PropertyMutation> {
+        self.property_mutations.iter()
+            .filter(|mutation| mutation.property_name == property_name)
+            .collect()
+    }
+
+    /// Heap usage over time, from the snapshots that recorded it, as `(timestamp, stats)`
+    pub fn heap_stats_timeline(&self) -> Vec<(f64, HeapStats)> {
+        self.variable_snapshots.iter()
+            .filter_map(|snapshot| snapshot.heap_stats.map(|stats| (snapshot.timestamp, stats)))
+            .collect()
+    }
+
+    pub fn log_promise_event(&mut self, promise_id: String, event_type: PromiseEventType, value: Option<JSValue>) {
+        self.promise_events.push(PromiseEvent { promise_id, event_type, value, timestamp: current_time_ms() });
+    }
+
+    /// `import()` calls in the order they were made
+    pub fn import_timeline(&self) -> Vec<&ImportRecord> {
+        let mut timeline: Vec<&ImportRecord> = self.dynamic_imports.iter().collect();
+        timeline.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        timeline
+    }
+
+    /// Events of `promise_id` and of every promise chained from it, directly or through
+    /// other chained promises, in the order they happened
+    pub fn promise_chain(&self, promise_id: &str) -> Vec<&PromiseEvent> {
+        let mut members: HashSet<&str> = HashSet::from([promise_id]);
+        loop {
+            let before = members.len();
+            for event in &self.promise_events {
+                if event.event_type == PromiseEventType::Chained
+                    && let Some(JSValue::String(parent)) = &event.value
+                    && members.contains(parent.as_str()) {
+                    members.insert(&event.promise_id);
+                }
+            }
+            if members.len() == before {
+                break;
+            }
+        }
+        self.promise_events.iter()
+            .filter(|event| members.contains(event.promise_id.as_str()))
+            .collect()
+    }
+
+    pub fn log_loop_iteration(&mut self, loop_id: u32, iteration: u32) {
+        let function_name = self.current_function.clone().unwrap_or_else(|| "<global>".to_string());
+        self.loop_iterations.push(LoopRecord { loop_id, iteration, timestamp: current_time_ms(), function_name });
+    }
+
+    /// Iteration count and iteration durations for each loop id. A gap only counts as a
+    /// duration when the iteration number goes up, so the time between two separate runs
+    /// of a loop (where it starts over) is left out.
+    pub fn loop_statistics(&self) -> HashMap<u32, LoopStats> {
+        let mut totals: HashMap<u32, u64> = HashMap::new();
+        let mut durations: HashMap<u32, Vec<f64>> = HashMap::new();
+        let mut previous: HashMap<u32, &LoopRecord> = HashMap::new();
+        for record in &self.loop_iterations {
+            *totals.entry(record.loop_id).or_default() += 1;
+            if let Some(last) = previous.insert(record.loop_id, record)
+                && record.iteration > last.iteration {
+                durations.entry(record.loop_id).or_default().push(record.timestamp - last.timestamp);
+            }
+        }
+
+        totals.into_iter()
+            .map(|(loop_id, total_iterations)| {
+                let durations = durations.remove(&loop_id).unwrap_or_default();
+                let stats = if durations.is_empty() {
+                    LoopStats { total_iterations, min_duration_ms: 0.0, max_duration_ms: 0.0, avg_duration_ms: 0.0 }
+                } else {
+                    LoopStats {
+                        total_iterations,
+                        min_duration_ms: durations.iter().copied().fold(f64::INFINITY, f64::min),
+                        max_duration_ms: durations.iter().copied().fold(0.0, f64::max),
+                        avg_duration_ms: durations.iter().sum::<f64>() / durations.len() as f64,
+                    }
+                };
+                (loop_id, stats)
+            })
+            .collect()
+    }
+
+    /// Group snapshot indices so every pair within a group has similarity >= threshold.
+    /// Uses complete-linkage agglomerative clustering.
+    pub fn cluster_snapshots(&self, threshold: f64) -> Vec<Vec<usize>> {
+        let snapshots = &self.variable_snapshots;
+        let mut clusters: Vec<Vec<usize>> = (0..snapshots.len()).map(|i| vec![i]).collect();
+
+        // Linkage between clusters is the minimum pairwise similarity of their members
+        let mut linkage: Vec<Vec<f64>> = (0..snapshots.len())
+            .map(|i| (0..snapshots.len())
+                .map(|j| snapshots[i].similarity_score(&snapshots[j]))
+                .collect())
+            .collect();
+
+        loop {
+            let mut best: Option<(usize, usize, f64)> = None;
+            for (i, row) in linkage.iter().enumerate() {
+                for (j, &score) in row.iter().enumerate().skip(i + 1) {
+                    if score >= threshold && best.is_none_or(|(_, _, best_score)| score > best_score) {
+                        best = Some((i, j, score));
+                    }
+                }
+            }
+
+            let Some((i, j, _)) = best else { break };
+
+            // Merge j into i (i < j) and update linkage with the complete-linkage rule
+            let row_j = linkage[j].clone();
+            for (k, score_j) in row_j.into_iter().enumerate() {
+                if k != i && k != j {
+                    let score = linkage[i][k].min(score_j);
+                    linkage[i][k] = score;
+                    linkage[k][i] = score;
+                }
+            }
+            linkage.remove(j);
+            for row in &mut linkage {
+                row.remove(j);
+            }
+            let merged = clusters.remove(j);
+            clusters[i].extend(merged);
+        }
+
+        for cluster in &mut clusters {
+            cluster.sort_unstable();
+        }
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.len()));
+        clusters
+    }
+
+    /// Human-readable summary of `cluster_snapshots` for the CLI
+    pub fn get_cluster_summary(&self, threshold: f64) -> String {
+        let clusters = self.cluster_snapshots(threshold);
+        let mut summary = String::new();
+        summary.push_str(&format!("🧩 SNAPSHOT CLUSTERS (threshold {:.2}): {} clusters from {} snapshots\n",
+            threshold, clusters.len(), self.variable_snapshots.len()));
+
+        for (i, cluster) in clusters.iter().enumerate() {
+            let mut functions: Vec<&str> = cluster.iter()
+                .map(|&idx| self.variable_snapshots[idx].function_name.as_str())
+                .collect();
+            functions.sort_unstable();
+            functions.dedup();
+
+            let first = &self.variable_snapshots[cluster[0]];
+            let mut common: Vec<&String> = first.variables.keys()
+                .filter(|name| cluster.iter().all(|&idx| self.variable_snapshots[idx].variables.contains_key(*name)))
+                .collect();
+            common.sort();
+
+            // Representative is the member most similar to the rest of its cluster
+            let representative = cluster.iter().copied().max_by(|&a, &b| {
+                let score = |idx: usize| -> f64 {
+                    cluster.iter().map(|&other| self.variable_snapshots[idx].similarity_score(&self.variable_snapshots[other])).sum()
+                };
+                score(a).total_cmp(&score(b))
+            }).unwrap_or(cluster[0]);
+
+            summary.push_str(&format!("  #{}: {} snapshots, representative #{}\n", i + 1, cluster.len(), representative));
+            summary.push_str(&format!("      functions: {}\n", functions.join(", ")));
+            summary.push_str(&format!("      common variables: {}\n",
+                if common.is_empty() { "(none)".to_string() } else { common.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ") }));
+        }
+
+        summary
+    }
+}
+
+/// How often `sample_event_loop` records `EventLoopStats`
+const EVENT_LOOP_SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+async fn sample_event_loop(tracker: Rc<OpMetricsSummaryTracker>, execution_state: Rc<RefCell<ExecutionState>>) {
+    let mut interval = tokio::time::interval(EVENT_LOOP_SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let stats = EventLoopStats::from_summary(&tracker.aggregate());
+        execution_state.borrow_mut().event_loop_samples.push((current_time_ms(), stats));
+    }
+}
+
+/// Number of buckets in `ExecutionState::size_distribution`
+const SIZE_BUCKETS: usize = 10;
+
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+/// Least-squares slope of `samples` against their index
+fn regression_slope(samples: &[usize]) -> f64 {
+    let n = samples.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = samples.iter().sum::<usize>() as f64 / n;
+    let (covariance, variance) = samples.iter().enumerate()
+        .map(|(x, &y)| (x as f64 - mean_x, y as f64 - mean_y))
+        .fold((0.0, 0.0), |(cov, var), (dx, dy)| (cov + dx * dy, var + dx * dx));
+    if variance == 0.0 { 0.0 } else { covariance / variance }
+}
+
+/// Mermaid participant ids can't contain spaces, dots or other punctuation
+fn mermaid_participant(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn current_time_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64() * 1000.0
+}
+
+/// Format `prefix + name + suffix` as one trace line, shortening the name (then the
+/// suffix) with an ellipsis so the line fits in `width` columns
+fn fit_line(prefix: &str, name: &str, suffix: &str, width: usize) -> String {
+    let fixed = prefix.chars().count() + suffix.chars().count();
+    let name = truncate_with_ellipsis(name, width.saturating_sub(fixed).max(1));
+    let line = format!("{}{}{}", prefix, name, suffix);
+    format!("{}\n", truncate_with_ellipsis(&line, width))
+}
+
+fn truncate_with_ellipsis(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Globals installed by the debugger runtime itself, left out of entry/exit captures
+const RUNTIME_GLOBALS: [&str; 4] = ["console", "timeDebugger", "Deno", "performance"];
+
+/// Built-ins `op_capture_global_state` leaves out: the ECMAScript standard library and
+/// the host functions V8 and deno_core put on every global object
+const STANDARD_GLOBALS: &[&str] = &[
+    "globalThis", "undefined", "NaN", "Infinity",
+    "eval", "isFinite", "isNaN", "parseFloat", "parseInt",
+    "decodeURI", "decodeURIComponent", "encodeURI", "encodeURIComponent", "escape", "unescape",
+    "Object", "Function", "Boolean", "Symbol", "Number", "BigInt", "Math", "Date", "String", "RegExp",
+    "Error", "AggregateError", "EvalError", "RangeError", "ReferenceError", "SyntaxError", "TypeError", "URIError",
+    "Array", "Int8Array", "Uint8Array", "Uint8ClampedArray", "Int16Array", "Uint16Array", "Int32Array",
+    "Uint32Array", "Float16Array", "Float32Array", "Float64Array", "BigInt64Array", "BigUint64Array",
+    "Map", "Set", "WeakMap", "WeakSet", "WeakRef", "FinalizationRegistry",
+    "ArrayBuffer", "SharedArrayBuffer", "DataView", "Atomics", "JSON", "Reflect", "Proxy",
+    "Promise", "Iterator", "Intl", "WebAssembly",
+    "queueMicrotask", "SuppressedError", "DisposableStack", "AsyncDisposableStack",
+];
+
+/// Main time travel debugger runtime
+pub struct TimeDebuggerRuntime {
+    js_runtime: JsRuntime,
+    execution_state: Rc<RefCell<ExecutionState>>,
+    config: DebuggerConfig,
+    op_metrics: Option<Rc<OpMetricsSummaryTracker>>,
+    /// Declared after `js_runtime` so the isolate is disposed before the tracer it calls into
+    gc_tracer: Option<Rc<GcTracer>>,
+    capture_filter: Option<Regex>,
+    module_loader: Rc<TracingModuleLoader>,
+}
+
+impl TimeDebuggerRuntime {
+    /// Create a new time travel debugger runtime
+    pub fn new(config: DebuggerConfig) -> Result<Self> {
+        let capture_filter = config.capture_function_filter.as_deref()
+            .map(|pattern| Regex::new(pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid capture filter {:?}: {}", pattern, e)))
+            .transpose()?;
+        let snapshot_file = config.snapshot_file.as_ref().map(FileSnapshotStore::create).transpose()?;
+
+        let execution_state = Rc::new(RefCell::new(ExecutionState {
+            output_width: config.output_width,
+            variable_aliases: config.variable_aliases.clone(),
+            max_argument_depth: config.max_argument_depth,
+            max_return_value_depth: config.max_return_value_depth,
+            snapshot_limit: Arc::new(AtomicUsize::new(config.max_snapshots)),
+            eviction_policy: config.eviction_policy,
+            coupling_window: config.coupling_window,
+            compress_call_history: config.compress_call_history,
+            snapshot_file,
+            write_batch_size: config.write_batch_size.max(1),
+            max_snapshot_bytes: config.max_snapshot_memory_mb.map(|mb| mb * 1024 * 1024),
+            dedup_snapshots: config.dedup_snapshots,
+            capture_heap_stats: config.capture_heap_stats,
+            max_call_depth: config.max_call_depth,
+            max_depth_warn_only: config.max_depth_warn_only,
+            repeated_call_threshold: config.repeated_call_threshold,
+            current_trace_id: config.trace_id.clone(),
+            process_trace_id: config.trace_id.clone(),
+            ..Default::default()
+        }));
+
+        if config.panic_trace.enabled {
+            install_panic_hook(&execution_state, config.panic_trace.output_path.clone());
+        }
+
+        let op_metrics = config.trace_event_loop.then(|| Rc::new(OpMetricsSummaryTracker::default()));
+
+        let module_loader = Rc::new(TracingModuleLoader::new(Rc::downgrade(&execution_state)));
+        let mut js_runtime = JsRuntime::new(RuntimeOptions {
+            extensions: vec![time_debugger_extension::init_ops_and_esm()],
+            module_loader: Some(module_loader.clone()),
+            inspector: config.collect_coverage,
+            op_metrics_factory_fn: op_metrics.clone()
+                .map(|tracker| tracker.op_metrics_factory_fn(|_| true)),
+            ..Default::default()
+        });
+
+        // Put the execution state in op state so ops can access it
+        js_runtime.op_state().borrow_mut().put(execution_state.clone());
+        if let Some(tracker) = &op_metrics {
+            js_runtime.op_state().borrow_mut().put(tracker.clone());
+        }
+
+        let gc_tracer = config.trace_gc.then(|| GcTracer::install(js_runtime.v8_isolate()));
+        if let Some(tracer) = &gc_tracer {
+            js_runtime.op_state().borrow_mut().put(tracer.clone());
+        }
+        if let Some(filter) = &capture_filter {
+            js_runtime.op_state().borrow_mut().put(CaptureFilter(filter.clone()));
+        }
+        js_runtime.op_state().borrow_mut().put(SnapshotAssertions::new(SNAPSHOT_DIR, config.update_snapshots));
+        js_runtime.op_state().borrow_mut().put(module_loader.clone());
+
+        Ok(Self {
+            js_runtime,
+            execution_state,
+            config,
+            op_metrics,
+            gc_tracer,
+            capture_filter,
+            module_loader,
+        })
+    }
+
+    /// Load and evaluate the main module, then run the event loop to completion
+    async fn evaluate_main_module(&mut self, main_module: &ModuleSpecifier) -> Result<ModuleId> {
+        let mod_id = self.js_runtime.load_main_es_module(main_module).await?;
+        if self.config.capture_on_entry {
+            self.capture_global_state(main_module.as_str(), "entry", None)?;
+        }
+        let result = self.js_runtime.mod_evaluate(mod_id);
+        
+        // Run the event loop to completion
+        match self.op_metrics.clone() {
+            Some(tracker) => {
+                let sampler = sample_event_loop(tracker.clone(), self.execution_state.clone());
+                tokio::select! {
+                    result = self.js_runtime.run_event_loop(Default::default()) => result?,
+                    _ = sampler => unreachable!("event loop sampler never completes"),
+                }
+                // Always keep the final counters, even for runs shorter than one interval
+                self.execution_state.borrow_mut().event_loop_samples
+                    .push((current_time_ms(), EventLoopStats::from_summary(&tracker.aggregate())));
+            },
+            None => self.js_runtime.run_event_loop(Default::default()).await?,
+        }
+        result.await?;
+        Ok(mod_id)
+    }
+
+    /// Stop capturing, then save what was recorded before `signal` to
+    /// `DebuggerConfig::crash_output`, for loading into the REPL. Returns the `Interrupted`
+    /// error that ends the run.
+    fn save_partial_trace(&mut self, signal: InterruptSignal) -> anyhow::Error {
+        let gc_events = self.gc_tracer.as_ref().map(|tracer| tracer.take_events(self.js_runtime.v8_isolate()));
+        let path = &self.config.crash_output;
+        let mut execution_state = self.execution_state.borrow_mut();
+        execution_state.stop_capture();
+        execution_state.gc_events.extend(gc_events.unwrap_or_default());
+        if let Some(start_time) = execution_state.execution_start_time {
+            execution_state.total_execution_time = start_time.elapsed();
+        }
+        let message = match StateSerializer::save(&execution_state, path) {
+            Ok(()) => {
+                warn!(path = %path.display(), ?signal, calls = execution_state.function_call_history.len(),
+                    snapshots = execution_state.variable_snapshots.len(), "interrupted; saved partial trace");
+                format!("Execution interrupted; partial trace saved to {}", path.display())
+            },
+            Err(e) => format!("Execution interrupted; failed to save partial trace to {}: {}", path.display(), e),
+        };
+        Interrupted { signal, message }.into()
+    }
+
+    /// Execute a JavaScript file
+    pub async fn execute_file(&mut self, file_path: &str) -> Result<()> {
+        self.execution_state.borrow_mut().start_execution();
+        
+        debug!(
+            file = file_path,
+            trace_function_calls = self.config.trace_function_calls,
+            capture_enabled = self.config.capture_enabled,
+            max_snapshots = self.config.max_snapshots,
+            eviction_policy = ?self.config.eviction_policy,
+            capture_filter = self.capture_filter.as_ref().map(Regex::as_str),
+            snapshot_file = self.config.snapshot_file.as_ref().map(|path| path.display().to_string()),
+            "loading script",
+        );
+
+        self.js_runtime.op_state().borrow_mut().put(ScriptArgs(self.config.script_args.clone()));
+        self.install_method_traces()?;
+        self.install_promise_hooks()?;
+
+        // Resolve the file path
+        let main_module = deno_core::resolve_path(
+            file_path, 
+            &std::env::current_dir()?
+        )?;
+
+        // The monitor shares the execution state, so it runs on this thread alongside the event loop
+        let local = tokio::task::LocalSet::new();
+        let memory_monitor = (self.config.capture_enabled
+            && self.config.memory_pressure_mode == MemoryPressureMode::Adaptive)
+            .then(|| MemoryPressureMonitor::new(self.config.max_memory_mb, &self.execution_state).spawn(&local));
+
+        let interrupt = InterruptWatcher::spawn(self.js_runtime.v8_isolate().thread_safe_handle());
+        let evaluated = local.run_until(self.evaluate_main_module(&main_module)).await;
+        if let Some(signal) = interrupt.signal() {
+            if let Some(monitor) = memory_monitor {
+                monitor.abort();
+            }
+            return Err(self.save_partial_trace(signal));
+        }
+        let mod_id = evaluated.inspect_err(|_| self.execution_state.borrow_mut().flush_snapshot_file())?;
+
+        if self.config.capture_on_exit {
+            self.capture_global_state(main_module.as_str(), "exit", Some(mod_id))?;
+        }
+        if self.config.capture_global_on_exit {
+            self.js_runtime.execute_script(
+                "ext:time_debugger_extension/capture_global.js",
+                "timeDebugger.captureGlobalState();",
+            )?;
+        }
+
+        if let Some(monitor) = memory_monitor {
+            monitor.abort();
+        }
+
+        // Events are buffered in the tracer because a GC can run while an op holds the state
+        if let Some(tracer) = &self.gc_tracer {
+            let events = tracer.take_events(self.js_runtime.v8_isolate());
+            self.execution_state.borrow_mut().gc_events.extend(events);
+        }
+        {
+            let mut execution_state = self.execution_state.borrow_mut();
+            execution_state.module_loads.extend(self.module_loader.take_loads());
+            execution_state.dynamic_imports.extend(self.module_loader.take_dynamic_imports());
+        }
+
+        // Update execution statistics
+        {
+            let mut execution_state = self.execution_state.borrow_mut();
+            if let Some(start_time) = execution_state.execution_start_time {
+                execution_state.total_execution_time = start_time.elapsed();
+            }
+            execution_state.flush_snapshot_file();
+        }
+        
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            let execution_state = self.execution_state.borrow();
+            debug!(elapsed = ?execution_state.total_execution_time, "execution completed\n{}", execution_state.get_execution_trace());
+        }
+
+        Ok(())
+    }
+
+    /// Wrap each method in `prototype_method_trace` before user code runs
+    fn install_method_traces(&mut self) -> Result<()> {
+        if self.config.prototype_method_trace.is_empty() {
+            return Ok(());
+        }
+        let methods = serde_json::to_string(&self.config.prototype_method_trace)?;
+        self.js_runtime.execute_script(
+            "ext:time_debugger_extension/trace_methods.js",
+            format!("{}.forEach((method) => timeDebugger.traceMethod(method));", methods),
+        )?;
+        Ok(())
+    }
+
+    /// Install the promise hooks behind `trace_promises` before user code runs
+    fn install_promise_hooks(&mut self) -> Result<()> {
+        if self.config.trace_promises {
+            self.js_runtime.execute_script(
+                "ext:time_debugger_extension/trace_promises.js",
+                "timeDebugger.tracePromises();",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Each module loaded so far mapped to the modules it statically imports
+    pub fn module_dependency_graph(&self) -> HashMap<String, Vec<String>> {
+        self.module_loader.dependency_graph()
+    }
+
+    /// `module_dependency_graph` as a Graphviz DOT file, with each module's load order and time
+    pub fn module_graph_dot(&self) -> String {
+        module_graph_to_dot(&self.module_dependency_graph(), &self.execution_state.borrow().module_loads)
+    }
+
+    /// Hand the recorded state to a `ReplaySession` for stepping through it after execution
+    pub fn into_replay_session(self) -> ReplaySession {
+        ReplaySession::new(std::mem::take(&mut *self.execution_state.borrow_mut()))
+    }
+
+    /// Snapshot the enumerable globals (minus the debugger's own) and, if given, the
+    /// exports of `module_id`. Module-scoped bindings that aren't exported are not reachable.
+    fn capture_global_state(&mut self, module_name: &str, snapshot_type: &str, module_id: Option<ModuleId>) -> Result<()> {
+        let namespace = module_id.map(|id| self.js_runtime.get_module_namespace(id)).transpose()?;
+        let scope = &mut self.js_runtime.handle_scope();
+
+        let mut variables = script_globals(scope);
+        if let Some(namespace) = namespace {
+            let namespace = v8::Local::new(scope, namespace);
+            variables.extend(scope_object_properties(scope, namespace.into()));
+        }
+
+        self.execution_state.borrow_mut()
+            .capture_variables(scope, module_name.to_string(), snapshot_type.to_string(), variables)
+    }
+
+    /// Execute a file under V8 precise coverage and write an Istanbul JSON report.
+    /// Requires `DebuggerConfig::collect_coverage` so the inspector is available.
+    pub async fn run_with_coverage(&mut self, file_path: &str, coverage_output: &Path) -> Result<CoverageReport> {
+        if !self.config.collect_coverage {
+            return Err(anyhow::anyhow!("Coverage requires DebuggerConfig::collect_coverage"));
+        }
+
+        let mut session = self.js_runtime.inspector().borrow().create_local_session();
+        self.js_runtime.with_event_loop_future(
+            session.post_message::<()>("Profiler.enable", None).boxed_local(),
+            Default::default(),
+        ).await?;
+        self.js_runtime.with_event_loop_future(
+            session.post_message("Profiler.startPreciseCoverage", Some(serde_json::json!({
+                "callCount": true,
+                "detailed": true,
+            }))).boxed_local(),
+            Default::default(),
+        ).await?;
+
+        self.execute_file(file_path).await?;
+
+        let coverage = self.js_runtime.with_event_loop_future(
+            session.post_message::<()>("Profiler.takePreciseCoverage", None).boxed_local(),
+            Default::default(),
+        ).await?;
+        self.js_runtime.with_event_loop_future(
+            session.post_message::<()>("Profiler.stopPreciseCoverage", None).boxed_local(),
+            Default::default(),
+        ).await?;
+
+        let report = CoverageReport::from_v8_coverage(
+            &coverage,
+            |url| {
+                let path = deno_core::url::Url::parse(url).ok()?.to_file_path().ok()?;
+                std::fs::read_to_string(path).ok()
+            },
+            &self.execution_state.borrow().function_call_counts,
+        )?;
+
+        std::fs::write(coverage_output, serde_json::to_vec_pretty(&report.to_istanbul_json())?)?;
+        info!(path = %coverage_output.display(), "wrote coverage report");
+
+        Ok(report)
+    }
+
+    /// Branch the execution at snapshot `snapshot_index`: a fresh runtime with the same
+    /// config and the history up to that point. V8 can't resume mid-execution, so the fork
+    /// re-runs a script which restores the snapshot via `timeDebugger.replayInject()`.
+    pub fn fork(&self, snapshot_index: usize) -> Result<TimeDebuggerRuntime> {
+        let forked_state = self.execution_state.borrow().fork_at(snapshot_index)?;
+        let fork = TimeDebuggerRuntime::new(self.config.clone())?;
+        *fork.execution_state.borrow_mut() = forked_state;
+
+        debug!(snapshot_index, "forked execution");
+        Ok(fork)
+    }
+
+    /// Override one variable of the state a fork will inject, to explore a different path
+    pub fn set_injected_variable(&self, name: &str, value: JSValue) -> Result<()> {
+        let mut execution_state = self.execution_state.borrow_mut();
+        let injection = execution_state.replay_injection.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Runtime is not a fork; nothing to inject"))?;
+        injection.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Full variable set of snapshot `index`, with any diff chain applied
+    pub fn reconstruct_snapshot(&self, index: usize) -> Option<VariableSnapshot> {
+        self.execution_state.borrow().reconstruct_snapshot(index)
+    }
+
+    /// Get current execution state for debugging
+    pub fn get_execution_state(&self) -> &Rc<RefCell<ExecutionState>> {
+        &self.execution_state
+    }
+}
+
+// Custom operations for the time travel debugger
+#[op2(fast)]
+fn op_log_function_call(_state: &mut OpState, #[string] function_name: String) {
+    debug!(function = %function_name, "function called");
+}
+
+#[op2(fast)]  
+fn op_get_timestamp() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+/// Frames searched for the script that made a call; the debugger's own wrappers sit on top
+const CALLER_FRAME_LIMIT: usize = 8;
+
+/// URL and 1-based line of the innermost frame outside the extension's `ext:` scripts,
+/// i.e. where the user's script made the call being recorded
+fn caller_location(scope: &mut v8::HandleScope) -> (Option<String>, Option<u32>) {
+    let Some(stack) = v8::StackTrace::current_stack_trace(scope, CALLER_FRAME_LIMIT) else {
+        return (None, None);
+    };
+    for index in 0..stack.get_frame_count() {
+        let Some(frame) = stack.get_frame(scope, index) else {
+            continue;
+        };
+        let Some(script) = frame.get_script_name(scope) else {
+            continue;
+        };
+        let script = script.to_rust_string_lossy(scope);
+        if !script.starts_with("ext:") {
+            return (Some(script), Some(frame.get_line_number() as u32));
+        }
+    }
+    (None, None)
+}
+
+fn passes_capture_filter(state: &OpState, function_name: &str) -> bool {
+    state.try_borrow::<CaptureFilter>().is_none_or(|CaptureFilter(filter)| filter.is_match(function_name))
+        || state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+            .is_some_and(|execution_state| execution_state.borrow().breakpoints.contains(function_name))
+}
+
+/// Throw the error left by a call refused for exceeding `max_call_depth`, if any
+fn throw_depth_limit_error(scope: &mut v8::HandleScope, exec_state: &mut ExecutionState) {
+    let Some(message) = exec_state.depth_limit_error.take() else {
+        return;
+    };
+    let message = v8::String::new(scope, &message).unwrap_or_else(|| v8::String::empty(scope));
+    let exception = v8::Exception::error(scope, message);
+    scope.throw_exception(exception);
+}
+
+#[op2(fast)]
+fn op_function_entry(scope: &mut v8::HandleScope, state: &mut OpState, #[string] name: String) {
+    if !passes_capture_filter(state, &name) {
+        return;
+    }
+    trace!(function = %name, "entry op");
+    
+    // Update the execution state
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        let (location, line) = caller_location(scope);
+        let mut exec_state = execution_state.borrow_mut();
+        exec_state.log_function_entry(name, vec![], location, line);
+        throw_depth_limit_error(scope, &mut exec_state);
+    }
+}
+
+/// `op_function_entry` that also serializes `args`, a JS array of the call's arguments
+#[op2(fast)]
+fn op_function_entry_with_args(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] name: String,
+    args: v8::Local<v8::Value>,
+) {
+    if !passes_capture_filter(state, &name) {
+        return;
+    }
+    trace!(function = %name, "entry op");
+
+    let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() else {
+        return;
+    };
+    let mut exec_state = execution_state.borrow_mut();
+    let depth = exec_state.max_argument_depth;
+
+    let mut arg_values = Vec::new();
+    if let Ok(array) = v8::Local::<v8::Array>::try_from(args) {
+        for i in 0..array.length() {
+            let value = array.get_index(scope, i).unwrap_or_else(|| v8::undefined(scope).into());
+            let serialized = exec_state.serialization_context.serialize_value_with_depth(scope, value, depth)
+                .unwrap_or_else(|e| JSValue::Error(format!("Serialization failed: {}", e)));
+            arg_values.push(serialized);
+        }
+    }
+    let (location, line) = caller_location(scope);
+    exec_state.log_function_entry_with_values(name, arg_values, location, line);
+    if let Some(threshold) = exec_state.repeated_call_threshold
+        && let Some(warning) = exec_state.repeated_call_crossing(threshold) {
+        warn!(function = %warning.function_name, arguments = %warning.argument_signature, threshold,
+            "function called repeatedly with identical arguments; possible infinite loop");
+    }
+    throw_depth_limit_error(scope, &mut exec_state);
+}
+
+/// Runs of more than `threshold` identical consecutive calls, as
+/// `{ functionName, argumentSignature, count }`
+#[op2]
+#[serde]
+fn op_check_repeated_calls(state: &mut OpState, threshold: u32) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
+    Ok(serde_json::Value::Array(exec_state.detect_repeated_calls(threshold).into_iter().map(|warning| {
+        serde_json::json!({
+            "functionName": warning.function_name,
+            "argumentSignature": warning.argument_signature,
+            "count": warning.count,
+        })
+    }).collect()))
+}
+
+/// Set the deepest call nesting allowed from the script; 0 removes the limit
+#[op2(fast)]
+fn op_set_max_depth(state: &mut OpState, depth: u32) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().max_call_depth = (depth > 0).then_some(depth as usize);
+    }
+}
+
+/// Log a function exit; a `return_value` other than `undefined` is stored on the call
+#[op2(fast)]
+fn op_function_exit(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] name: String,
+    duration_ms: f64,
+    return_value: v8::Local<v8::Value>,
+) {
+    if !passes_capture_filter(state, &name) {
+        return;
+    }
+    trace!(function = %name, duration_ms, "exit op");
+    
+    // Update the execution state
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        let mut exec_state = execution_state.borrow_mut();
+        if !return_value.is_undefined() {
+            let value = serialize_return_value(&mut exec_state, scope, return_value);
+            exec_state.set_return_value(&name, value);
+        }
+        exec_state.log_function_exit(name, duration_ms);
+    }
+}
+
+#[op2(fast)]
+fn op_mark_hot_path_start(state: &mut OpState, #[string] label: String) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().start_hot_path(label);
+    }
+}
+
+/// End a hot path and return its elapsed time in milliseconds
+#[op2(fast)]
+fn op_mark_hot_path_end(state: &mut OpState, #[string] label: String) -> Result<f64, anyhow::Error> {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().end_hot_path(&label)
+    } else {
+        Err(anyhow::anyhow!("Execution state not available"))
+    }
+}
+
+#[op2(fast)]
+fn op_log_state_machine_transition(
+    state: &mut OpState,
+    #[string] machine_name: String,
+    #[string] from_state: String,
+    #[string] to_state: String,
+    #[string] event: String,
+) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        info!(machine = %machine_name, from = %from_state, to = %to_state, event = %event, "state transition");
+        execution_state.borrow_mut().log_state_machine_transition(machine_name, from_state, to_state, event);
+    }
+}
+
+/// Record which way branch `branch_id` of `function_name` went
+#[op2(fast)]
+fn op_track_branch(state: &mut OpState, #[string] function_name: String, branch_id: u32, taken: bool) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().log_branch(function_name, branch_id, taken);
+    }
+}
+
+/// Record iteration `iteration` of loop `loop_id`
+#[op2(fast)]
+fn op_track_loop_iteration(state: &mut OpState, loop_id: u32, iteration: u32) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().log_loop_iteration(loop_id, iteration);
+    }
+}
+
+/// Id a promise is recorded under in `ExecutionState::promise_events`
+fn promise_id(promise: v8::Local<v8::Object>) -> String {
+    format!("promise#{}", promise.get_identity_hash())
+}
+
+/// Promise hook `init`: record the new promise and, when it derives from `parent`, the link
+#[op2(fast)]
+fn op_promise_created(state: &mut OpState, promise: v8::Local<v8::Value>, parent: v8::Local<v8::Value>) {
+    let Ok(promise) = v8::Local::<v8::Object>::try_from(promise) else { return };
+    let Some(execution_state) = state.try_borrow::<Rc<RefCell<ExecutionState>>>() else { return };
+    let mut execution_state = execution_state.borrow_mut();
+    let id = promise_id(promise);
+    execution_state.log_promise_event(id.clone(), PromiseEventType::Created, None);
+    if let Ok(parent) = v8::Local::<v8::Object>::try_from(parent) {
+        execution_state.log_promise_event(id, PromiseEventType::Chained, Some(JSValue::String(promise_id(parent))));
+    }
+}
+
+fn log_promise_settled(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    promise: v8::Local<v8::Value>,
+    value: v8::Local<v8::Value>,
+    event_type: PromiseEventType,
+) {
+    let Ok(promise) = v8::Local::<v8::Object>::try_from(promise) else { return };
+    let Some(execution_state) = state.try_borrow::<Rc<RefCell<ExecutionState>>>() else { return };
+    let mut execution_state = execution_state.borrow_mut();
+    let value = execution_state.serialization_context.serialize_value(scope, value)
+        .unwrap_or_else(|e| JSValue::Error(e.to_string()));
+    execution_state.log_promise_event(promise_id(promise), event_type, Some(value));
+}
+
+/// Record that `promise` was fulfilled with `value`
+#[op2(fast)]
+fn op_promise_resolved(scope: &mut v8::HandleScope, state: &mut OpState, promise: v8::Local<v8::Value>, value: v8::Local<v8::Value>) {
+    log_promise_settled(scope, state, promise, value, PromiseEventType::Resolved);
+}
+
+/// Record that `promise` was rejected with `reason`
+#[op2(fast)]
+fn op_promise_rejected(scope: &mut v8::HandleScope, state: &mut OpState, promise: v8::Local<v8::Value>, reason: v8::Local<v8::Value>) {
+    log_promise_settled(scope, state, promise, reason, PromiseEventType::Rejected);
+}
+
+/// `performance.now()`: milliseconds since the performance time origin
+#[op2(fast)]
+fn op_performance_now(state: &mut OpState) -> f64 {
+    state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .map_or(0.0, |execution_state| execution_state.borrow().perf_now())
+}
+
+/// Record a `performance.mark` at `timestamp` (milliseconds since the time origin)
+#[op2(fast)]
+fn op_performance_mark(state: &mut OpState, #[string] name: String, timestamp: f64) -> Result<(), anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let mut exec_state = execution_state.borrow_mut();
+    let mark = exec_state.performance_mark(name, timestamp)?;
+    debug!(name = %mark.name, start_time = mark.start_time, "performance mark");
+    Ok(())
+}
+
+/// Record a `performance.measure` between two marks (empty for origin / now) and return
+/// the entry as `{ name, entryType, startTime, duration }`
+#[op2]
+#[serde]
+fn op_performance_measure(
+    state: &mut OpState,
+    #[string] name: String,
+    #[string] start_mark: String,
+    #[string] end_mark: String,
+) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let mut exec_state = execution_state.borrow_mut();
+    let measure = exec_state.performance_measure(name, start_mark, end_mark)?;
+    info!(name = %measure.name, start_time = measure.start_time, duration_ms = measure.duration, "performance measure");
+    Ok(serde_json::json!({
+        "name": measure.name,
+        "entryType": "measure",
+        "startTime": measure.start_time,
+        "duration": measure.duration,
+    }))
+}
+
+#[op2(fast)]
+fn op_add_watchpoint(state: &mut OpState, #[string] name: String) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        info!(variable = %name, "watchpoint added");
+        execution_state.borrow_mut().add_watchpoint(name);
+    }
+}
+
+#[op2(fast)]
+fn op_register_source_map(state: &mut OpState, #[string] map_json: String) -> Result<(), anyhow::Error> {
+    let execution_state = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let file = execution_state.borrow_mut().source_maps.register(&map_json)?;
+    info!(file = %file, "source map registered");
+    Ok(())
+}
+
+#[op2(fast)]
+fn op_begin_async_trace(state: &mut OpState, #[string] trace_id: String) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().begin_async_trace(trace_id);
+    }
+}
+
+#[op2(fast)]
+fn op_end_async_trace(state: &mut OpState, #[string] trace_id: String) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().end_async_trace(&trace_id);
+    }
+}
+
+/// Set the process trace ID; an empty string clears it
+#[op2(fast)]
+fn op_set_trace_id(state: &mut OpState, #[string] trace_id: String) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        execution_state.borrow_mut().set_process_trace_id(Some(trace_id).filter(|id| !id.is_empty()));
+    }
+}
+
+/// Trace ID being stamped right now (an async trace's, else the process one), or ""
+#[op2]
+#[string]
+fn op_get_trace_id(state: &mut OpState) -> String {
+    state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .and_then(|execution_state| execution_state.borrow().current_trace_id.clone())
+        .unwrap_or_default()
+}
+
+/// Show and store captures of `real_name` under `alias`
+#[op2(fast)]
+fn op_set_scope_alias(state: &mut OpState, #[string] real_name: String, #[string] alias: String) {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        debug!(variable = %real_name, alias = %alias, "alias registered");
+        execution_state.borrow_mut().variable_aliases.insert(real_name, alias);
+    }
+}
+
+#[op2]
+fn op_capture_execution_context(
+    #[string] context_type: String,
+    #[serde] data: serde_json::Value
+) {
+    info!(context_type = %context_type, data = %data, "execution context");
+}
+
+/// Serialize a value exactly as snapshots would and return its JSON form, so the
+/// serializer can be tested from JS
+#[op2]
+#[serde]
+fn op_serialize_js_value(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    value: v8::Local<v8::Value>,
+) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let serialized = execution_state.borrow_mut().serialization_context.serialize_value(scope, value)?;
+    Ok(serialized.to_json_value())
+}
+
+/// Capture a single variable value for serialization
+#[op2]
+#[string]
+fn op_capture_variable(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] variable_name: String,
+    value: v8::Local<v8::Value>,
+) -> Result<String, anyhow::Error> {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        let mut exec_state = execution_state.borrow_mut();
         
-        let mut js_runtime = JsRuntime::new(RuntimeOptions {
-            extensions: vec![time_debugger_extension::init_ops_and_esm()],
-            module_loader: Some(Rc::new(deno_core::FsModuleLoader)),
-            ..Default::default()
-        });
+        // Serialize the value
+        match exec_state.serialization_context.serialize_value(scope, value) {
+            Ok(serialized_value) => {
+                let display_str = serialized_value.to_display_string();
+                let pretty_config = PrettyPrintConfig {
+                    max_line_width: exec_state.output_width,
+                    ..Default::default()
+                };
+                info!(variable = %exec_state.display_name(&variable_name),
+                    value = %serialized_value.pretty_print(&pretty_config), "variable captured");
+                Ok(display_str)
+            },
+            Err(e) => {
+                let error_msg = format!("Failed to serialize {}: {}", variable_name, e);
+                error!(variable = %variable_name, "{}", error_msg);
+                Err(anyhow::anyhow!(error_msg))
+            }
+        }
+    } else {
+        Err(anyhow::anyhow!("Execution state not available"))
+    }
+}
 
-        // Put the execution state in op state so ops can access it
-        js_runtime.op_state().borrow_mut().put(execution_state.clone());
+/// Snapshot a thrown value. Errors keep their non-enumerable `name`, `message` and `stack`.
+#[op2(fast)]
+fn op_log_exception(scope: &mut v8::HandleScope, state: &mut OpState, error_value: v8::Local<v8::Value>) {
+    let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() else {
+        return;
+    };
+    let mut exec_state = execution_state.borrow_mut();
 
-        Ok(Self {
-            js_runtime,
-            execution_state,
-            config,
-        })
+    let mut error = exec_state.serialization_context.serialize_value(scope, error_value)
+        .unwrap_or_else(|e| JSValue::Error(format!("Serialization failed: {}", e)));
+    if let (JSValue::Object(properties), Ok(object)) = (&mut error, v8::Local::<v8::Object>::try_from(error_value)) {
+        for field in ["name", "message", "stack"] {
+            let Some(key) = v8::String::new(scope, field) else { continue };
+            if let Some(value) = object.get(scope, key.into()).filter(|value| !value.is_undefined()) {
+                properties.insert(field.to_string(), JSValue::String(value.to_rust_string_lossy(scope)));
+            }
+        }
     }
 
-    /// Execute a JavaScript file
-    pub async fn execute_file(&mut self, file_path: &str) -> Result<()> {
-        self.execution_state.borrow_mut().start_execution();
-        
-        if self.config.verbose {
-            println!("🎯 Loading JavaScript file: {}", file_path);
-            println!("🔧 Monitoring configuration:");
-            println!("   - Function call tracing: {}", self.config.trace_function_calls);
-            println!("   - State capture: {}", self.config.capture_enabled);
-            println!("   - Max snapshots: {}", self.config.max_snapshots);
+    warn!(error = %error.to_display_string(), "exception");
+    exec_state.record_exception(error);
+}
+
+/// Store `value` as the return value of the latest call to `function_name` without one
+#[op2(fast)]
+fn op_capture_return_value(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] function_name: String,
+    value: v8::Local<v8::Value>,
+) -> bool {
+    let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() else {
+        return false;
+    };
+    let mut exec_state = execution_state.borrow_mut();
+    let value = serialize_return_value(&mut exec_state, scope, value);
+    exec_state.set_return_value(&function_name, value)
+}
+
+fn serialize_return_value(
+    exec_state: &mut ExecutionState,
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> JSValue {
+    let max_depth = exec_state.max_return_value_depth;
+    exec_state.serialization_context.serialize_value_with_depth(scope, value, max_depth)
+        .unwrap_or_else(|e| JSValue::Error(format!("Serialization failed: {}", e)))
+}
+
+/// Set global `name` to a reconstruction of `variable_name` from snapshot `snapshot_index`
+/// (diff snapshots are expanded first), for replaying an earlier state
+#[op2(fast)]
+fn op_inject_value(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] name: String,
+    snapshot_index: u32,
+    #[string] variable_name: String,
+) -> Result<(), anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let snapshot = execution_state.borrow().reconstruct_snapshot(snapshot_index as usize)
+        .ok_or_else(|| anyhow::anyhow!("No snapshot at index {}", snapshot_index))?;
+    let value = snapshot.variables.get(&variable_name)
+        .ok_or_else(|| anyhow::anyhow!("Snapshot {} has no variable {}", snapshot_index, variable_name))?;
+
+    let reconstructed = value.to_v8_value(scope)?;
+    let key = v8::String::new(scope, &name)
+        .ok_or_else(|| anyhow::anyhow!("Invalid global name: {}", name))?;
+    let global = scope.get_current_context().global(scope);
+    global.set(scope, key.into(), reconstructed);
+    info!(global = %name, value = %value.to_display_string(), snapshot_index, "value injected");
+    Ok(())
+}
+
+/// Save snapshot `snapshot_index` (diff snapshots are expanded first) to `<dir>/<label>.ttd`.
+/// Paths outside the working directory are refused.
+#[op2(fast)]
+fn op_save_snapshot(
+    state: &mut OpState,
+    #[string] label: String,
+    snapshot_index: u32,
+    #[string] dir: String,
+) -> Result<(), anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let snapshot = execution_state.borrow().reconstruct_snapshot(snapshot_index as usize)
+        .ok_or_else(|| anyhow::anyhow!("No snapshot at index {}", snapshot_index))?;
+    let path = resolve_within(&std::env::current_dir()?,
+        &Path::new(&dir).join(format!("{}{}", label, SNAPSHOT_EXTENSION)))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    SnapshotSerializer::save(&[snapshot], &path)?;
+    info!(label = %label, snapshot_index, path = %path.display(), "snapshot saved");
+    Ok(())
+}
+
+/// Record the snapshots saved at `path` as if just captured (limits, eviction and dedup
+/// apply), returning the index of the first one kept. Paths outside the working directory
+/// are refused.
+#[op2(fast)]
+fn op_load_snapshot(state: &mut OpState, #[string] path: String) -> Result<u32, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let resolved = resolve_within(&std::env::current_dir()?, Path::new(&path))?;
+    let file = SnapshotSerializer::load(&resolved)?;
+    if file.snapshots.is_empty() {
+        return Err(anyhow::anyhow!("{} holds no snapshots", path));
+    }
+
+    let mut execution_state = execution_state.borrow_mut();
+    let mut count = 0;
+    for snapshot in file.snapshots {
+        if execution_state.push_snapshot(snapshot) {
+            count += 1;
         }
+    }
+    if count == 0 {
+        return Err(anyhow::anyhow!("No snapshot from {} was kept", path));
+    }
+    // Loaded snapshots are the newest, so eviction only shifts them towards the front
+    let index = execution_state.variable_snapshots.len().saturating_sub(count);
+    info!(path = %resolved.display(), snapshot_index = index, count, "snapshot loaded");
+    Ok(index as u32)
+}
 
-        // Resolve the file path
-        let main_module = deno_core::resolve_path(
-            file_path, 
-            &std::env::current_dir()?
-        )?;
+/// Serialize `value` and check it against the saved snapshot for `label` in `.snapshots/`,
+/// saving it on first use (or with `--update-snapshots`)
+#[op2(fast)]
+fn op_assert_snapshot(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] label: String,
+    value: v8::Local<v8::Value>,
+) -> Result<(), anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let serialized = execution_state.borrow_mut().serialization_context.serialize_value(scope, value)?;
+    let assertions = state.try_borrow::<SnapshotAssertions>().cloned().unwrap_or_default();
 
-        // Load and evaluate the main module
-        let mod_id = self.js_runtime.load_main_es_module(&main_module).await?;
-        let result = self.js_runtime.mod_evaluate(mod_id);
-        
-        // Run the event loop to completion
-        self.js_runtime.run_event_loop(Default::default()).await?;
-        result.await?;
+    match assertions.assert(&label, &serialized) {
+        Ok(SnapshotOutcome::Created) => info!(label = %label, path = %assertions.path_for(&label).display(), "snapshot created"),
+        Ok(SnapshotOutcome::Updated) => info!(label = %label, "snapshot updated"),
+        Ok(SnapshotOutcome::Matched) => debug!(label = %label, "snapshot matches"),
+        Err(e) => {
+            error!(label = %label, "{}", e);
+            return Err(e);
+        },
+    }
+    Ok(())
+}
 
-        // Update execution statistics
-        {
-            let mut execution_state = self.execution_state.borrow_mut();
-            if let Some(start_time) = execution_state.execution_start_time {
-                execution_state.total_execution_time = start_time.elapsed();
-            }
+/// Snapshot the script-visible globals as a `checkpoint` named `label`
+#[op2(fast)]
+fn op_checkpoint(scope: &mut v8::HandleScope, state: &mut OpState, #[string] label: String) -> u32 {
+    let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() else {
+        error!("execution state not available");
+        return 0;
+    };
+
+    let variables = script_globals(scope);
+    let var_count = variables.len() as u32;
+    info!(label = %label, "checkpoint");
+    if let Err(e) = execution_state.borrow_mut().capture_variables(scope, label, "checkpoint".to_string(), variables) {
+        error!("failed to capture checkpoint: {}", e);
+        return 0;
+    }
+    var_count
+}
+
+/// Capture multiple variables at once (e.g., function arguments or local scope)
+#[op2(fast)]
+fn op_capture_scope(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] function_name: String,
+    #[string] snapshot_type: String,
+    scope_object: v8::Local<v8::Value>,
+) -> u32 {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        let mut exec_state = execution_state.borrow_mut();
+        let variables = scope_object_properties(scope, scope_object);
+
+        let var_count = variables.len() as u32;
+        
+        // Capture the variables using the execution state method
+        if let Err(e) = exec_state.capture_variables(scope, function_name, snapshot_type, variables) {
+            error!("failed to capture variables: {}", e);
+            return 0;
         }
         
-        if self.config.verbose {
-            let execution_state = self.execution_state.borrow();
-            println!("✅ Execution completed in {:?}", execution_state.total_execution_time);
-            println!("\n{}", execution_state.get_execution_trace());
+        var_count
+    } else {
+        error!("execution state not available");
+        0
+    }
+}
+
+/// Snapshot everything a script stored on `globalThis` as a `global` snapshot, returning
+/// how many bindings were captured
+#[op2(fast)]
+fn op_capture_global_state(scope: &mut v8::HandleScope, state: &mut OpState) -> Result<u32, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let variables = global_state_variables(scope);
+    let count = variables.len() as u32;
+    execution_state.borrow_mut().capture_variables(scope, "globalThis".to_string(), "global".to_string(), variables)?;
+    Ok(count)
+}
+
+/// Resolve a module specifier (a URL, or a path relative to the working directory) to the
+/// URL its module is loaded under
+#[op2]
+#[string]
+fn op_resolve_module_specifier(#[string] specifier: String) -> Result<String, anyhow::Error> {
+    Ok(deno_core::resolve_url_or_path(&specifier, &std::env::current_dir()?)?.to_string())
+}
+
+/// Keep the debugger's own next `import()` of `url` out of `ExecutionState::dynamic_imports`
+#[op2(fast)]
+fn op_ignore_next_import(state: &mut OpState, #[string] url: String) {
+    if let Some(loader) = state.try_borrow::<Rc<TracingModuleLoader>>() {
+        loader.ignore_next_import(url);
+    }
+}
+
+/// Snapshot the exported bindings of `module_specifier` as a `module_scope` snapshot,
+/// returning how many were captured. Ops can't reach the module map, so
+/// `timeDebugger.captureModuleScope` passes in the namespace from a dynamic import.
+#[op2(fast)]
+fn op_capture_module_scope(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] module_specifier: String,
+    namespace: v8::Local<v8::Value>,
+) -> Result<u32, anyhow::Error> {
+    if !namespace.is_module_namespace_object() {
+        return Err(anyhow::anyhow!("{} did not resolve to a module namespace", module_specifier));
+    }
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let variables = scope_object_properties(scope, namespace);
+    let count = variables.len() as u32;
+    execution_state.borrow_mut().capture_variables(scope, module_specifier, "module_scope".to_string(), variables)?;
+    Ok(count)
+}
+
+/// Snapshot what `function_value` appears to close over as a `closure` snapshot, returning
+/// how many variables were captured. V8 doesn't expose a function's captured scope, so
+/// the names come from its source (see `closure::free_variables`) and the values are read
+/// from `scope_object`, or from the globals when it isn't an object. Locals of the
+/// enclosing function can only be seen if the caller passes them in `scope_object`.
+#[op2(fast)]
+fn op_capture_closure(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    function_value: v8::Local<v8::Value>,
+    scope_object: v8::Local<v8::Value>,
+) -> Result<u32, anyhow::Error> {
+    let function = v8::Local::<v8::Function>::try_from(function_value)
+        .map_err(|_| anyhow::anyhow!("captureClosure expects a function"))?;
+    let source = function.to_string(scope)
+        .map(|source| source.to_rust_string_lossy(scope))
+        .unwrap_or_default();
+    let function_name = Some(function.get_name(scope).to_rust_string_lossy(scope))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "anonymous".to_string());
+    let lookup = match scope_object.to_object(scope) {
+        Some(object) if scope_object.is_object() => object,
+        _ => scope.get_current_context().global(scope),
+    };
+
+    let mut variables = HashMap::new();
+    for name in free_variables(&source) {
+        let Some(key) = v8::String::new(scope, &name) else { continue };
+        if lookup.has(scope, key.into()) == Some(true)
+            && let Some(value) = lookup.get(scope, key.into()) {
+            variables.insert(name, value);
         }
+    }
 
-        Ok(())
+    let execution_state = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let count = variables.len() as u32;
+    execution_state.borrow_mut().capture_variables(scope, function_name, "closure".to_string(), variables)?;
+    Ok(count)
+}
+
+/// Like `op_capture_scope`, but only when `condition` is true. The scope object isn't even
+/// enumerated otherwise, so skipped iterations cost almost nothing.
+#[op2(fast)]
+fn op_capture_scope_if(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    condition: bool,
+    #[string] function_name: String,
+    #[string] snapshot_type: String,
+    scope_object: v8::Local<v8::Value>,
+) -> u32 {
+    if !condition {
+        return 0;
     }
 
-    /// Get current execution state for debugging
-    pub fn get_execution_state(&self) -> &Rc<RefCell<ExecutionState>> {
-        &self.execution_state
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        let mut exec_state = execution_state.borrow_mut();
+        let variables = scope_object_properties(scope, scope_object);
+        let var_count = variables.len() as u32;
+        let function_name = if function_name.is_empty() {
+            exec_state.current_function.clone().unwrap_or_else(|| "anonymous".to_string())
+        } else {
+            function_name
+        };
+
+        match exec_state.capture_variables_if(condition, scope, function_name, snapshot_type, variables) {
+            Ok(true) => var_count,
+            Ok(false) => 0,
+            Err(e) => {
+                error!("failed to capture variables: {}", e);
+                0
+            }
+        }
+    } else {
+        error!("execution state not available");
+        0
     }
 }
 
-// Custom operations for the time travel debugger
+/// Capture only the variables of `scope_object` that changed since snapshot `baseline_index`
 #[op2(fast)]
-fn op_log_function_call(_state: &mut OpState, #[string] function_name: String) {
-    println!("📞 Function called: {}", function_name);
+fn op_capture_scope_diff(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    #[string] function_name: String,
+    scope_object: v8::Local<v8::Value>,
+    baseline_index: u32,
+) -> Result<u32, anyhow::Error> {
+    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
+        let mut exec_state = execution_state.borrow_mut();
+        let variables = scope_object_properties(scope, scope_object);
+        let function_name = if function_name.is_empty() {
+            exec_state.current_function.clone().unwrap_or_else(|| "anonymous".to_string())
+        } else {
+            function_name
+        };
+
+        let changed = exec_state.capture_variables_diff(scope, function_name, variables, baseline_index as usize)?;
+        Ok(changed as u32)
+    } else {
+        Err(anyhow::anyhow!("Execution state not available"))
+    }
 }
 
-#[op2(fast)]  
-fn op_get_timestamp() -> f64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs_f64()
+/// Enumerable globals of the current context, minus the debugger's own
+fn script_globals<'s>(scope: &mut v8::HandleScope<'s>) -> HashMap<String, v8::Local<'s, v8::Value>> {
+    let global = scope.get_current_context().global(scope);
+    let mut variables = scope_object_properties(scope, global.into());
+    variables.retain(|name, _| !RUNTIME_GLOBALS.contains(&name.as_str()));
+    variables
+}
+
+/// Every own string-keyed global of the current context, enumerable or not, minus
+/// `STANDARD_GLOBALS` and the debugger's own
+fn global_state_variables<'s>(scope: &mut v8::HandleScope<'s>) -> HashMap<String, v8::Local<'s, v8::Value>> {
+    let global = scope.get_current_context().global(scope);
+    let mut variables = HashMap::new();
+    let args = v8::GetPropertyNamesArgs { property_filter: v8::PropertyFilter::SKIP_SYMBOLS, ..Default::default() };
+    let Some(property_names) = global.get_own_property_names(scope, args) else {
+        return variables;
+    };
+
+    for i in 0..property_names.length() {
+        let Some(key) = property_names.get_index(scope, i) else { continue };
+        let name = key.to_rust_string_lossy(scope);
+        if STANDARD_GLOBALS.contains(&name.as_str()) || RUNTIME_GLOBALS.contains(&name.as_str()) {
+            continue;
+        }
+        if let Some(value) = global.get(scope, key) {
+            variables.insert(name, value);
+        }
+    }
+    variables
+}
+
+/// Own properties of `scope_object`, or nothing if it isn't an object
+fn scope_object_properties<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    scope_object: v8::Local<v8::Value>,
+) -> HashMap<String, v8::Local<'s, v8::Value>> {
+    let mut variables = HashMap::new();
+
+    if let Ok(object) = v8::Local::<v8::Object>::try_from(scope_object)
+        && let Some(property_names) = object.get_own_property_names(scope, v8::GetPropertyNamesArgs::default()) {
+        let length = property_names.length();
+
+        for i in 0..length {
+            if let Some(key) = property_names.get_index(scope, i) {
+                let key_string = key.to_rust_string_lossy(scope);
+
+                if let Some(property_value) = object.get(scope, key) {
+                    variables.insert(key_string, property_value);
+                }
+            }
+        }
+    }
+
+    variables
+}
+
+/// Current op counters; requires `DebuggerConfig::trace_event_loop`
+#[op2]
+#[serde]
+fn op_get_event_loop_stats(state: &mut OpState) -> Result<serde_json::Value, anyhow::Error> {
+    let tracker = state.try_borrow::<Rc<OpMetricsSummaryTracker>>()
+        .ok_or_else(|| anyhow::anyhow!("Event loop tracing is disabled (run with --trace-event-loop)"))?;
+    Ok(serde_json::to_value(EventLoopStats::from_summary(&tracker.aggregate()))?)
+}
+
+/// Field-level diff between two (reconstructed) snapshots
+#[op2]
+#[serde]
+fn op_diff_snapshots(state: &mut OpState, a_index: u32, b_index: u32) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
+    let snapshot = |index: u32| exec_state.reconstruct_snapshot(index as usize)
+        .ok_or_else(|| anyhow::anyhow!("No snapshot at index {}", index));
+    Ok(snapshot(a_index)?.diff(&snapshot(b_index)?).to_json_value())
+}
+
+/// GC pauses recorded so far; requires `DebuggerConfig::trace_gc`
+#[op2]
+#[serde]
+fn op_trace_gc_events(state: &mut OpState) -> Result<serde_json::Value, anyhow::Error> {
+    let tracer = state.try_borrow::<Rc<GcTracer>>()
+        .ok_or_else(|| anyhow::anyhow!("GC tracing is disabled (run with --trace-gc)"))?;
+    Ok(serde_json::to_value(tracer.events())?)
+}
+
+/// Arguments passed after `--script-args`, backing `Deno.args`
+#[op2]
+#[serde]
+fn op_get_script_args(state: &mut OpState) -> Vec<String> {
+    state.try_borrow::<ScriptArgs>()
+        .map(|args| args.0.clone())
+        .unwrap_or_default()
+}
+
+/// Variables a forked runtime should restore, or null outside a fork
+#[op2]
+#[serde]
+fn op_replay_inject(state: &mut OpState) -> Result<serde_json::Value, anyhow::Error> {
+    if let Some(execution_state) = state.try_borrow::<Rc<RefCell<ExecutionState>>>() {
+        let exec_state = execution_state.borrow();
+        let Some(snapshot) = &exec_state.replay_injection else {
+            return Ok(serde_json::Value::Null);
+        };
+
+        info!(variables = snapshot.variables.len(), function = %snapshot.function_name,
+            snapshot_type = %snapshot.snapshot_type, "injecting replay state");
+        Ok(serde_json::json!({
+            "function": snapshot.function_name,
+            "type": snapshot.snapshot_type,
+            "variables": snapshot.variables.iter()
+                .map(|(name, value)| (name.clone(), value.to_json_value()))
+                .collect::<serde_json::Map<_, _>>(),
+        }))
+    } else {
+        Err(anyhow::anyhow!("Execution state not available"))
+    }
+}
+
+/// The `n` most called functions as `{ name, count }`, most calls first
+#[op2]
+#[serde]
+fn op_get_hot_functions(state: &mut OpState, n: u32) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
+    Ok(serde_json::Value::Array(exec_state.hot_functions(n as usize).into_iter().map(|(name, count)| {
+        serde_json::json!({ "name": name, "count": count })
+    }).collect()))
+}
+
+/// The instrumented call stack, innermost frame first
+#[op2]
+#[serde]
+fn op_get_call_stack(state: &mut OpState) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
+
+    Ok(serde_json::Value::Array(exec_state.current_call_stack().into_iter().map(|call| {
+        serde_json::json!({
+            "name": call.name,
+            "depth": call.call_depth,
+            "timestamp": call.timestamp,
+            "location": call.file_location,
+            "line": call.line_number,
+        })
+    }).collect()))
+}
+
+/// Every captured value of one variable as `{ timestamp, value, type, typeChanged }`,
+/// `typeChanged` marking entries whose type differs from the entry before
+#[op2]
+#[serde]
+fn op_get_variable_timeline(state: &mut OpState, #[string] name: String) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
+
+    let mut previous_type = None;
+    Ok(serde_json::Value::Array(exec_state.variable_timeline(&name).into_iter().map(|(timestamp, value)| {
+        let value_type = value.type_name();
+        let type_changed = previous_type.replace(value_type).is_some_and(|previous| previous != value_type);
+        serde_json::json!({
+            "timestamp": timestamp,
+            "value": value.to_json_value(),
+            "type": value_type,
+            "typeChanged": type_changed,
+        })
+    }).collect()))
 }
 
+/// Keys of the data object backing a watched property
+const WATCHED_VALUE_KEY: &str = "value";
+const WATCHED_OBJECT_REF_KEY: &str = "objectRef";
+
+/// Turn `property_name` on `object` into a native accessor that records every write in
+/// `ExecutionState::property_mutations`. The current value moves into the accessor's
+/// data object, so reads see the same values as before.
 #[op2(fast)]
-fn op_function_entry(state: &mut OpState, #[string] name: String) {
-    println!("🔍 ENTER: {}", name);
-    
-    // Update the execution state
-    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
-        execution_state.borrow_mut().log_function_entry(name, vec![], None, None);
+fn op_watch_property(
+    scope: &mut v8::HandleScope,
+    object: v8::Local<v8::Value>,
+    #[string] property_name: String,
+) -> Result<(), anyhow::Error> {
+    let object = v8::Local::<v8::Object>::try_from(object)
+        .map_err(|_| anyhow::anyhow!("watchProperty expects an object"))?;
+    let key = v8::String::new(scope, &property_name)
+        .ok_or_else(|| anyhow::anyhow!("Invalid property name: {}", property_name))?;
+    let current = object.get(scope, key.into()).unwrap_or_else(|| v8::undefined(scope).into());
+
+    let data = v8::Object::new(scope);
+    let value_key = v8::String::new(scope, WATCHED_VALUE_KEY).unwrap();
+    let ref_key = v8::String::new(scope, WATCHED_OBJECT_REF_KEY).unwrap();
+    let object_ref = v8::String::new(scope, &format!("object#{}", object.get_identity_hash())).unwrap();
+    data.set(scope, value_key.into(), current);
+    data.set(scope, ref_key.into(), object_ref.into());
+
+    let configuration = v8::AccessorConfiguration::new(watched_property_getter)
+        .setter(watched_property_setter)
+        .data(data.into());
+    if object.set_accessor_with_configuration(scope, key.into(), configuration) != Some(true) {
+        return Err(anyhow::anyhow!("Cannot watch {}: the property is not configurable", property_name));
     }
+    debug!(property = %property_name, "property watched");
+    Ok(())
 }
 
-#[op2(fast)]
-fn op_function_exit(state: &mut OpState, #[string] name: String, duration_ms: f64) {
-    println!("🔍 EXIT:  {} ({}ms)", name, duration_ms);
-    
-    // Update the execution state
-    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
-        execution_state.borrow_mut().log_function_exit(name, duration_ms);
+fn watched_property_getter(
+    scope: &mut v8::HandleScope,
+    _key: v8::Local<v8::Name>,
+    args: v8::PropertyCallbackArguments,
+    mut rv: v8::ReturnValue<v8::Value>,
+) {
+    if let Ok(data) = v8::Local::<v8::Object>::try_from(args.data())
+        && let Some(value_key) = v8::String::new(scope, WATCHED_VALUE_KEY)
+        && let Some(value) = data.get(scope, value_key.into()) {
+        rv.set(value);
     }
 }
 
-#[op2]
-fn op_capture_execution_context(
-    #[string] context_type: String,
-    #[serde] data: serde_json::Value
+/// Store the new value, then log the write. It goes unrecorded if the op state is busy,
+/// i.e. when an op itself assigns to a watched property.
+fn watched_property_setter(
+    scope: &mut v8::HandleScope,
+    key: v8::Local<v8::Name>,
+    value: v8::Local<v8::Value>,
+    args: v8::PropertyCallbackArguments,
+    _rv: v8::ReturnValue<()>,
 ) {
-    println!("📸 CONTEXT: {} -> {:?}", context_type, data);
+    let Ok(data) = v8::Local::<v8::Object>::try_from(args.data()) else { return };
+    let (Some(value_key), Some(ref_key)) = (v8::String::new(scope, WATCHED_VALUE_KEY), v8::String::new(scope, WATCHED_OBJECT_REF_KEY)) else {
+        return;
+    };
+    let old_value = data.get(scope, value_key.into()).unwrap_or_else(|| v8::undefined(scope).into());
+    data.set(scope, value_key.into(), value);
+
+    let object_ref = data.get(scope, ref_key.into())
+        .map(|object_ref| object_ref.to_rust_string_lossy(scope))
+        .unwrap_or_default();
+    let property_name = key.to_rust_string_lossy(scope);
+    let op_state = JsRuntime::op_state_from(scope);
+    let Ok(op_state) = op_state.try_borrow() else { return };
+    let Some(execution_state) = op_state.try_borrow::<Rc<RefCell<ExecutionState>>>() else { return };
+    let Ok(mut execution_state) = execution_state.try_borrow_mut() else { return };
+
+    let mut serialize = |value| execution_state.serialization_context.serialize_value(scope, value)
+        .unwrap_or_else(|e| JSValue::Error(e.to_string()));
+    let (old_value, new_value) = (serialize(old_value), serialize(value));
+    debug!(object = %object_ref, property = %property_name, old = %old_value.to_display_string(),
+        new = %new_value.to_display_string(), "watched property written");
+    execution_state.log_property_mutation(object_ref, property_name, old_value, new_value);
 }
 
-/// Capture a single variable value for serialization
+/// Writes to watched properties named `property_name`, oldest first
 #[op2]
-#[string]
-fn op_capture_variable(
-    scope: &mut v8::HandleScope,
-    state: &mut OpState,
-    #[string] variable_name: String,
-    value: v8::Local<v8::Value>,
-) -> Result<String, anyhow::Error> {
-    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
-        let mut exec_state = execution_state.borrow_mut();
-        
-        // Serialize the value
-        match exec_state.serialization_context.serialize_value(scope, value) {
-            Ok(serialized_value) => {
-                let display_str = serialized_value.to_display_string();
-                println!("📝 Variable captured: {} = {}", variable_name, display_str);
-                Ok(display_str)
-            },
-            Err(e) => {
-                let error_msg = format!("Failed to serialize {}: {}", variable_name, e);
-                println!("❌ {}", error_msg);
-                Err(anyhow::anyhow!(error_msg))
-            }
-        }
-    } else {
-        Err(anyhow::anyhow!("Execution state not available"))
-    }
+#[serde]
+fn op_get_property_history(state: &mut OpState, #[string] property_name: String) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
+    Ok(serde_json::Value::Array(exec_state.property_history(&property_name).into_iter().map(|mutation| {
+        serde_json::json!({
+            "objectRef": mutation.object_ref,
+            "propertyName": mutation.property_name,
+            "oldValue": mutation.old_value.to_json_value(),
+            "newValue": mutation.new_value.to_json_value(),
+            "timestamp": mutation.timestamp,
+            "callDepth": mutation.call_depth,
+        })
+    }).collect()))
 }
 
-/// Capture multiple variables at once (e.g., function arguments or local scope)
-#[op2(fast)]
-fn op_capture_scope(
-    scope: &mut v8::HandleScope,
-    state: &mut OpState,
-    #[string] function_name: String,
-    #[string] snapshot_type: String,
-    scope_object: v8::Local<v8::Value>,
-) -> u32 {
-    if let Some(execution_state) = state.try_borrow_mut::<Rc<RefCell<ExecutionState>>>() {
-        let mut exec_state = execution_state.borrow_mut();
-        let mut variables = HashMap::new();
-
-        // If the scope_object is an object, extract its properties
-        if scope_object.is_object() {
-            if let Ok(object) = v8::Local::<v8::Object>::try_from(scope_object) {
-                if let Some(property_names) = object.get_own_property_names(scope, v8::GetPropertyNamesArgs::default()) {
-                    let length = property_names.length();
-                    
-                    for i in 0..length {
-                        if let Some(key) = property_names.get_index(scope, i) {
-                            let key_string = key.to_rust_string_lossy(scope);
-                            
-                            if let Some(property_value) = object.get(scope, key) {
-                                variables.insert(key_string, property_value);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+/// Snapshots matching a `SnapshotQuery::apply_filter` object, with their indices
+#[op2]
+#[serde]
+fn op_query_snapshots(state: &mut OpState, #[serde] filter: serde_json::Value) -> Result<serde_json::Value, anyhow::Error> {
+    let execution_state = state.try_borrow::<Rc<RefCell<ExecutionState>>>()
+        .ok_or_else(|| anyhow::anyhow!("Execution state not available"))?;
+    let exec_state = execution_state.borrow();
 
-        let var_count = variables.len() as u32;
-        
-        // Capture the variables using the execution state method
-        if let Err(e) = exec_state.capture_variables(scope, function_name, snapshot_type, variables) {
-            println!("❌ Failed to capture variables: {}", e);
-            return 0;
-        }
-        
-        var_count
-    } else {
-        println!("❌ Execution state not available");
-        0
-    }
+    let mut query = SnapshotQuery::new(&exec_state);
+    query.apply_filter(&filter)?;
+    Ok(serde_json::Value::Array(query.collect_indexed().into_iter().map(|(index, snapshot)| {
+        serde_json::json!({
+            "index": index,
+            "timestamp": snapshot.timestamp,
+            "function": snapshot.function_name,
+            "type": snapshot.snapshot_type,
+            "depth": snapshot.call_depth,
+            "variables": snapshot.variables.iter()
+                .map(|(name, value)| (name.clone(), value.to_json_value()))
+                .collect::<serde_json::Map<_, _>>(),
+        })
+    }).collect()))
 }
 
 /// Get information about captured snapshots
@@ -459,12 +3887,801 @@ extension!(
         op_log_function_call,
         op_get_timestamp,
         op_function_entry,
+        op_function_entry_with_args,
+        op_set_max_depth,
         op_function_exit,
+        op_capture_return_value,
+        op_log_exception,
+        op_mark_hot_path_start,
+        op_mark_hot_path_end,
+        op_set_scope_alias,
+        op_add_watchpoint,
+        op_register_source_map,
+        op_begin_async_trace,
+        op_end_async_trace,
+        op_set_trace_id,
+        op_get_trace_id,
+        op_log_state_machine_transition,
+        op_track_branch,
+        op_track_loop_iteration,
+        op_performance_now,
+        op_performance_mark,
+        op_performance_measure,
         op_capture_execution_context,
         op_capture_variable,
+        op_serialize_js_value,
         op_capture_scope,
+        op_capture_closure,
+        op_capture_global_state,
+        op_resolve_module_specifier,
+        op_ignore_next_import,
+        op_capture_module_scope,
+        op_checkpoint,
+        op_capture_scope_if,
+        op_capture_scope_diff,
         op_get_snapshot_info,
+        op_query_snapshots,
+        op_get_call_stack,
+        op_get_hot_functions,
+        op_check_repeated_calls,
+        op_get_variable_timeline,
+        op_watch_property,
+        op_promise_created,
+        op_promise_resolved,
+        op_promise_rejected,
+        op_get_property_history,
+        op_inject_value,
+        op_save_snapshot,
+        op_load_snapshot,
+        op_assert_snapshot,
+        op_replay_inject,
+        op_get_event_loop_stats,
+        op_trace_gc_events,
+        op_diff_snapshots,
+        op_get_script_args,
     ],
     esm_entry_point = "ext:time_debugger_extension/runtime.js",
     esm = [dir "src/runtime", "runtime.js"],
-); 
\ No newline at end of file
+); 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(function_name: &str, variables: &[(&str, JSValue)]) -> VariableSnapshot {
+        VariableSnapshot {
+            timestamp: 0.0,
+            function_name: function_name.to_string(),
+            call_depth: 1,
+            variables: variables.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+            snapshot_type: "entry".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        }
+    }
+
+    #[test]
+    fn test_similarity_score() {
+        let a = snapshot("f", &[("x", JSValue::Number(1.0)), ("y", JSValue::Number(2.0))]);
+        let b = snapshot("f", &[("x", JSValue::Number(1.0)), ("y", JSValue::Number(3.0))]);
+        let c = snapshot("g", &[("z", JSValue::Null)]);
+
+        assert_eq!(a.similarity_score(&a), 1.0);
+        assert_eq!(a.similarity_score(&b), 0.5);
+        assert_eq!(a.similarity_score(&c), 0.0);
+    }
+
+    #[test]
+    fn test_cluster_snapshots() {
+        let mut state = ExecutionState::default();
+        let ok = || snapshot("handle", &[("status", JSValue::String("ok".to_string()))]);
+        let err = || snapshot("handle", &[("error", JSValue::String("boom".to_string()))]);
+        state.variable_snapshots = vec![ok(), err(), ok(), err(), ok()].into();
+
+        let clusters = state.cluster_snapshots(0.9);
+        assert_eq!(clusters, vec![vec![0, 2, 4], vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_mermaid_sequence_diagram() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        state.log_function_entry("add".to_string(), vec!["1".to_string(), "2".to_string()], None, None);
+
+        let diagram = state.generate_mermaid_sequence_diagram(10);
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines[0], "sequenceDiagram");
+        assert_eq!(lines[1], "    Program->>main: main()");
+        assert_eq!(lines[2], "    main->>add: add(1, 2)");
+    }
+
+    #[test]
+    fn test_snapshot_size_analysis() {
+        let state = ExecutionState {
+            variable_snapshots: vec![
+                snapshot("small", &[("n", JSValue::Number(1.0))]),
+                snapshot("large", &[("s", JSValue::String("x".repeat(4096)))]),
+            ].into(),
+            ..Default::default()
+        };
+
+        let top = state.top_n_snapshots_by_size(1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].0, 1);
+
+        let distribution = state.size_distribution();
+        assert_eq!(distribution.len(), 10);
+        assert_eq!(distribution[0], (0, 1));
+        assert_eq!(distribution[1], (1024, 1));
+    }
+
+    #[test]
+    fn test_event_loop_saturation() {
+        let mut state = ExecutionState::default();
+        assert_eq!(state.event_loop_saturation(), 0.0);
+
+        let stats = |pending_ops, completed_ops| EventLoopStats { pending_ops, completed_ops, ..Default::default() };
+        state.event_loop_samples = vec![(0.0, stats(2, 0)), (10.0, stats(4, 2))];
+
+        // avg pending 3 / avg(completed + 1) 2
+        assert_eq!(state.event_loop_saturation(), 1.5);
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        // The documented example spells out the defaults
+        let example = DebuggerConfig::from_toml(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.time-travel.example.toml"))).unwrap();
+        let defaults = DebuggerConfig::default();
+        assert_eq!((example.max_snapshots, example.eviction_policy, example.crash_output), (defaults.max_snapshots, defaults.eviction_policy, defaults.crash_output));
+        assert_eq!(example.panic_trace, defaults.panic_trace);
+
+        let path = std::env::temp_dir().join(format!("ttd_config_{}.toml", std::process::id()));
+        std::fs::write(&path, "max_snapshots = 50\neviction_policy = \"lowest-depth\"\nmemory_pressure_mode = \"adaptive\"\n[variable_aliases]\na = \"total\"\n").unwrap();
+        let config = DebuggerConfig::from_toml(&path).unwrap();
+        assert_eq!(config.max_snapshots, 50);
+        assert_eq!(config.eviction_policy, EvictionPolicy::LowestDepth);
+        assert_eq!(config.memory_pressure_mode, MemoryPressureMode::Adaptive);
+        assert_eq!(config.variable_aliases["a"], "total");
+        assert!(config.capture_enabled);
+
+        std::fs::write(&path, "eviction_policy = \"newest\"\n").unwrap();
+        assert!(DebuggerConfig::from_toml(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_state_machine_transitions() {
+        let mut state = ExecutionState::default();
+        for (from, to, event) in [("idle", "loading", "fetch"), ("loading", "idle", "done"),
+            ("idle", "loading", "fetch"), ("loading", "error", "fail")] {
+            state.log_state_machine_transition("request".into(), from.into(), to.into(), event.into());
+        }
+        state.log_state_machine_transition("other".into(), "a".into(), "b".into(), "go".into());
+
+        let diagram = state.state_machine_diagram("request");
+        assert!(diagram.starts_with("stateDiagram-v2\n    [*] --> idle\n"));
+        assert!(diagram.contains("    idle --> loading: fetch (×2)\n"));
+        assert!(!diagram.contains("go"));
+
+        let valid = [("idle".to_string(), "loading".to_string()), ("loading".to_string(), "idle".to_string())];
+        let invalid = state.invalid_transitions("request", &valid);
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].to_state, "error");
+    }
+
+    #[test]
+    fn test_branch_coverage_summary() {
+        let mut state = ExecutionState::default();
+        for (function_name, branch_id, taken) in [("parse", 1, true), ("parse", 1, false), ("parse", 1, true), ("parse", 2, false)] {
+            state.log_branch(function_name.to_string(), branch_id, taken);
+        }
+
+        let summary = state.branch_coverage_summary();
+        assert_eq!(summary[&("parse".to_string(), 1)], (2, 1));
+        assert_eq!(summary[&("parse".to_string(), 2)], (0, 1));
+        assert_eq!(summary.len(), 2);
+    }
+
+    #[test]
+    fn test_loop_statistics() {
+        let mut state = ExecutionState::default();
+        // Loop 1 runs twice (iterations restart at 0); loop 2 runs once
+        for (loop_id, iteration, timestamp) in [(1, 0, 0.0), (1, 1, 2.0), (1, 2, 8.0), (1, 0, 50.0), (1, 1, 54.0), (2, 0, 60.0)] {
+            state.loop_iterations.push(LoopRecord { loop_id, iteration, timestamp, function_name: "main".to_string() });
+        }
+
+        let stats = state.loop_statistics();
+        assert_eq!(stats[&1], LoopStats { total_iterations: 5, min_duration_ms: 2.0, max_duration_ms: 6.0, avg_duration_ms: 4.0 });
+        assert_eq!(stats[&2].total_iterations, 1);
+        assert_eq!(stats[&2].max_duration_ms, 0.0);
+    }
+
+    #[test]
+    fn test_detect_repeated_calls() {
+        let mut state = ExecutionState::default();
+        let call = |state: &mut ExecutionState, arg: f64| {
+            state.log_function_entry_with_values("poll".to_string(), vec![JSValue::Number(arg)], None, None);
+            state.log_function_exit("poll".to_string(), 1.0);
+        };
+        for _ in 0..3 {
+            call(&mut state, 1.0);
+        }
+        assert_eq!(state.repeated_call_crossing(2).map(|warning| warning.count), Some(3));
+        call(&mut state, 1.0);
+        assert_eq!(state.repeated_call_crossing(2), None);
+        call(&mut state, 2.0);
+        call(&mut state, 1.0);
+
+        assert_eq!(state.detect_repeated_calls(2), vec![RepeatedCallWarning {
+            function_name: "poll".to_string(),
+            argument_signature: "1".to_string(),
+            count: 4,
+        }]);
+        assert!(state.detect_repeated_calls(4).is_empty());
+    }
+
+    #[test]
+    fn test_hot_functions_and_call_paths() {
+        let mut state = ExecutionState::default();
+        for name in ["parse", "eval", "print", "parse", "eval", "print", "parse"] {
+            state.log_function_entry(name.to_string(), vec![], None, None);
+            state.log_function_exit(name.to_string(), 1.0);
+        }
+
+        assert_eq!(state.hot_functions(2), vec![("parse", 3), ("eval", 2)]);
+        let paths = state.hot_call_paths(1);
+        assert_eq!(paths, vec![(vec!["eval".to_string(), "print".to_string(), "parse".to_string()], 2)]);
+    }
+
+    #[test]
+    fn test_heap_stats_timeline_skips_snapshots_without_stats() {
+        let mut state = ExecutionState::default();
+        let stats = |used| HeapStats { total_heap_size: 4096, used_heap_size: used, heap_size_limit: 1 << 30, external_memory: 0 };
+        for (timestamp, heap_stats) in [(1.0, Some(stats(100))), (2.0, None), (3.0, Some(stats(300)))] {
+            state.variable_snapshots.push(VariableSnapshot { timestamp, heap_stats, ..snapshot("tick", &[]) });
+        }
+        assert_eq!(state.heap_stats_timeline(), vec![(1.0, stats(100)), (3.0, stats(300))]);
+    }
+
+    #[test]
+    fn test_promise_chain_follows_chained_promises() {
+        let mut state = ExecutionState::default();
+        let parent = |id: &str| Some(JSValue::String(id.to_string()));
+        state.log_promise_event("promise#1".to_string(), PromiseEventType::Created, None);
+        state.log_promise_event("promise#2".to_string(), PromiseEventType::Created, None);
+        state.log_promise_event("promise#3".to_string(), PromiseEventType::Chained, parent("promise#1"));
+        state.log_promise_event("promise#4".to_string(), PromiseEventType::Chained, parent("promise#3"));
+        state.log_promise_event("promise#1".to_string(), PromiseEventType::Resolved, Some(JSValue::Number(1.0)));
+        state.log_promise_event("promise#4".to_string(), PromiseEventType::Rejected, Some(JSValue::Error("boom".to_string())));
+
+        let chain = state.promise_chain("promise#1");
+        let ids: Vec<&str> = chain.iter().map(|event| event.promise_id.as_str()).collect();
+        assert_eq!(ids, ["promise#1", "promise#3", "promise#4", "promise#1", "promise#4"]);
+        assert_eq!(chain.last().unwrap().event_type, PromiseEventType::Rejected);
+        assert_eq!(state.promise_chain("promise#2").len(), 1);
+    }
+
+    #[test]
+    fn test_property_history_filters_by_name() {
+        let mut state = ExecutionState::default();
+        state.log_property_mutation("object#1".to_string(), "balance".to_string(), JSValue::Number(0.0), JSValue::Number(10.0));
+        state.log_property_mutation("object#1".to_string(), "owner".to_string(), JSValue::Undefined, JSValue::String("ada".to_string()));
+        state.log_property_mutation("object#2".to_string(), "balance".to_string(), JSValue::Number(5.0), JSValue::Number(-5.0));
+
+        let history = state.property_history("balance");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].object_ref, "object#2");
+        assert_eq!(history[1].new_value, JSValue::Number(-5.0));
+    }
+
+    #[test]
+    fn test_performance_marks_and_measures() {
+        let mut state = ExecutionState::default();
+        state.performance_mark("load".to_string(), 5.0).unwrap();
+        state.performance_mark("parse".to_string(), 12.5).unwrap();
+        assert!(state.performance_mark("bad".to_string(), -1.0).is_err());
+
+        assert_eq!(state.performance_measure("parsing".to_string(), "load".to_string(), "parse".to_string()).unwrap().duration, 7.5);
+        assert_eq!(state.performance_measure("startup".to_string(), String::new(), "load".to_string()).unwrap().start_time, 0.0);
+        assert!(state.performance_measure("missing".to_string(), "nope".to_string(), String::new()).is_err());
+
+        let timeline = state.perf_timeline_json();
+        let entries: Vec<(&str, &str)> = timeline["entries"].as_array().unwrap().iter()
+            .map(|entry| (entry["name"].as_str().unwrap(), entry["entryType"].as_str().unwrap()))
+            .collect();
+        assert_eq!(entries, [("startup", "measure"), ("load", "mark"), ("parsing", "measure"), ("parse", "mark")]);
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let mut state = ExecutionState { max_call_depth: Some(2), ..Default::default() };
+        for name in ["a", "b", "c"] {
+            state.log_function_entry(name.to_string(), vec![], None, None);
+        }
+        assert_eq!(state.call_stack_depth, 2);
+        assert_eq!(state.function_call_history.len(), 2);
+        assert!(state.depth_limit_error.take().is_some_and(|e| e.contains("entering c")));
+
+        state.max_depth_warn_only = true;
+        state.log_function_entry("c".to_string(), vec![], None, None);
+        assert!(state.depth_limit_error.is_none());
+        assert_eq!(state.max_depth_reached, 3);
+    }
+
+    #[test]
+    fn test_tail_windows() {
+        let mut state = ExecutionState::default();
+        for name in ["a", "b", "c"] {
+            state.variable_snapshots.push(snapshot(name, &[]));
+            state.log_function_entry(name.to_string(), vec![], None, None);
+        }
+
+        let names: Vec<&str> = state.tail_snapshot_window(2).map(|s| s.function_name.as_str()).collect();
+        assert_eq!(names, ["b", "c"]);
+        assert_eq!(state.tail_snapshot_window(10).count(), 3);
+        assert_eq!(state.tail_call_window(2).next_back().map(|c| c.name.as_str()), Some("c"));
+        assert_eq!(state.tail_call_window(0).count(), 0);
+    }
+
+    #[test]
+    fn test_detect_memory_leaks() {
+        let mut state = ExecutionState::default();
+        for i in 0..12 {
+            let cache = JSValue::Array((0..i * 10).map(|n| JSValue::Number(n as f64)).collect());
+            state.variable_snapshots.push(snapshot("handle", &[
+                ("cache", cache),
+                ("count", JSValue::Number(i as f64)),
+            ]));
+        }
+        // Too few samples to judge
+        state.variable_snapshots.push(snapshot("rare", &[("blob", JSValue::String("x".repeat(10_000)))]));
+
+        let leaks = state.detect_memory_leaks(100.0);
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].variable_name, "cache");
+        assert!(leaks[0].size_at_end > leaks[0].size_at_start);
+        // 10 extra elements per call
+        let element = std::mem::size_of::<JSValue>() as f64;
+        assert!((leaks[0].growth_rate - 10.0 * element).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_compress_call_history() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        for i in 0..1000 {
+            state.log_function_entry("step".to_string(), vec![i.to_string()], None, None);
+            state.log_function_exit("step".to_string(), 0.5);
+        }
+        // Still running, so it stays separate
+        state.log_function_entry("step".to_string(), vec!["last".to_string()], None, None);
+
+        assert_eq!(state.compress_call_history(false), 0);
+        assert_eq!(state.compress_call_history(true), 999);
+
+        let history = &state.function_call_history;
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[1].call_count, 1000);
+        assert_eq!(history[1].arguments, ["0"]);
+        assert_eq!(history[1].duration_ms, Some(500.0));
+        assert_eq!(history[2].call_count, 1);
+    }
+
+    #[test]
+    fn test_compress_call_history_folds_recursion() {
+        let mut state = ExecutionState::default();
+        for n in 0..1000 {
+            state.log_function_entry("countdown".to_string(), vec![n.to_string()], None, None);
+            if n == 0 {
+                state.record_snapshot("countdown".to_string(), "entry".to_string(), HashMap::new(), HashMap::new());
+            }
+        }
+        for n in 0..1000 {
+            state.log_function_exit("countdown".to_string(), (n + 1) as f64);
+        }
+
+        assert_eq!(state.compress_call_history(true), 999);
+        let history = &state.function_call_history;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].call_count, 1000);
+        assert_eq!(history[0].arguments, ["0"]);
+        assert_eq!(history[0].duration_ms, Some(1000.0));
+        assert_eq!(state.function_call_to_snapshot_index(0), Some(0));
+    }
+
+    #[test]
+    fn test_async_trace_stamps_calls_and_snapshots() {
+        let mut state = ExecutionState::default();
+        state.begin_async_trace("req-1".to_string());
+        state.log_function_entry("fetchUser".to_string(), vec![], None, None);
+        state.record_snapshot("fetchUser".to_string(), "custom".to_string(), HashMap::new(), HashMap::new());
+        // Ending a different trace leaves req-1 active
+        state.end_async_trace("req-2");
+        state.record_snapshot("fetchUser".to_string(), "exit".to_string(), HashMap::new(), HashMap::new());
+        state.end_async_trace("req-1");
+        state.record_snapshot("render".to_string(), "custom".to_string(), HashMap::new(), HashMap::new());
+
+        assert_eq!(state.function_call_history[0].trace_id.as_deref(), Some("req-1"));
+        let traced: Vec<&str> = state.snapshots_for_trace("req-1").iter().map(|s| s.snapshot_type.as_str()).collect();
+        assert_eq!(traced, ["custom", "exit"]);
+        assert!(state.variable_snapshots[2].trace_id.is_none());
+    }
+
+    #[test]
+    fn test_merge_groups_snapshots_by_trace() {
+        let snapshot = |timestamp: f64, trace_id: &str| VariableSnapshot {
+            timestamp,
+            function_name: "work".to_string(),
+            call_depth: 1,
+            variables: HashMap::new(),
+            snapshot_type: "custom".to_string(),
+            metadata: HashMap::new(),
+            trace_id: Some(trace_id.to_string()),
+            heap_stats: None,
+        };
+        let mut parent = ExecutionState::default();
+        parent.set_process_trace_id(Some("job-1".to_string()));
+        parent.begin_async_trace("req-9".to_string());
+        parent.end_async_trace("req-9");
+        assert_eq!(parent.current_trace_id.as_deref(), Some("job-1"));
+        parent.log_function_entry("dispatch".to_string(), vec![], None, None);
+        parent.variable_snapshots.extend([snapshot(1.0, "job-1"), snapshot(4.0, "job-2")]);
+
+        let mut worker = ExecutionState::default();
+        worker.log_function_entry("dispatch".to_string(), vec![], None, None);
+        worker.variable_snapshots.extend([snapshot(2.0, "job-2"), snapshot(3.0, "job-1")]);
+
+        let merged = parent.merge(worker);
+        let order: Vec<(f64, &str)> = merged.variable_snapshots.iter()
+            .map(|s| (s.timestamp, s.trace_id.as_deref().unwrap()))
+            .collect();
+        assert_eq!(order, [(1.0, "job-1"), (3.0, "job-1"), (2.0, "job-2"), (4.0, "job-2")]);
+        assert_eq!(merged.function_call_counts["dispatch"], 2);
+        assert_eq!(merged.function_call_history[0].trace_id.as_deref(), Some("job-1"));
+    }
+
+    #[test]
+    fn test_watchpoints_fire_on_structural_change() {
+        let mut state = ExecutionState::default();
+        state.add_watchpoint("user".to_string());
+        let user = |name: &str| JSValue::Object(HashMap::from([("name".to_string(), JSValue::String(name.to_string()))]));
+        let vars = |value: JSValue| HashMap::from([("user".to_string(), value), ("other".to_string(), JSValue::Null)]);
+
+        // First sighting only records the value
+        assert!(state.check_watchpoints(&vars(user("ada"))).is_empty());
+        // An equal but distinct object doesn't fire
+        assert!(state.check_watchpoints(&vars(user("ada"))).is_empty());
+
+        let changed = state.check_watchpoints(&vars(user("grace")));
+        assert_eq!(changed.len(), 1);
+        let (name, previous, current) = changed.into_iter().next().unwrap();
+        state.record_watchpoint_hit("main".to_string(), name, previous, current);
+
+        let hit = state.variable_snapshots.last().unwrap();
+        assert_eq!(hit.snapshot_type, "watchpoint");
+        assert!(hit.variables["user"].structural_eq(&user("grace")));
+        assert!(hit.metadata["previous"].structural_eq(&user("ada")));
+    }
+
+    #[test]
+    fn test_snapshot_diff_recurses_into_objects() {
+        let user = |name: &str, age: f64| JSValue::Object(HashMap::from([
+            ("name".to_string(), JSValue::String(name.to_string())),
+            ("age".to_string(), JSValue::Number(age)),
+        ]));
+        let before = snapshot("main", &[("user", user("ada", 36.0)), ("gone", JSValue::Null)]);
+        let after = snapshot("main", &[("user", user("ada", 37.0)), ("fresh", JSValue::Boolean(true))]);
+
+        assert!(before.diff(&before).is_empty());
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.changed.len(), 1);
+        assert!(diff.changed["user.age"].1.structural_eq(&JSValue::Number(37.0)));
+        assert_eq!(diff.to_display_string(), "  + fresh: true\n  - gone: null\n  ~ user.age: 36 → 37\n");
+    }
+
+    #[test]
+    fn test_export_ndjson_records() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec!["1".to_string()], None, None);
+        state.variable_snapshots.push(snapshot("main", &[("ratio", JSValue::Number(f64::NAN))]));
+        state.dynamic_imports.push(ImportRecord {
+            specifier: "./chart.js".to_string(),
+            resolved_url: "file:///app/chart.js".to_string(),
+            timestamp: 12.5,
+            from_function: Some("main".to_string()),
+        });
+
+        let mut output = Vec::new();
+        state.export_ndjson(&mut output).unwrap();
+        let records: Vec<serde_json::Value> = String::from_utf8(output).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let types: Vec<&str> = records.iter().map(|r| r["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["header", "call", "snapshot", "dynamic_import"]);
+        assert_eq!(records[3]["from_function"], "main");
+        assert_eq!(records[1]["arguments"], serde_json::json!(["1"]));
+        assert_eq!(records[2]["variables"]["ratio"], serde_json::json!({ "type": "number", "value": "NaN" }));
+    }
+
+    #[test]
+    fn test_gc_overhead() {
+        let mut state = ExecutionState::default();
+        assert_eq!(state.gc_overhead_pct(), 0.0);
+
+        let event = |duration_ms, kind| GcEvent { timestamp_ms: 0.0, duration_ms, kind, before_used_bytes: 4096, after_used_bytes: 3072 };
+        state.gc_events = vec![event(1.5, GcKind::Minor), event(3.5, GcKind::Major)];
+        state.total_execution_time = std::time::Duration::from_millis(50);
+
+        assert_eq!(state.total_gc_time_ms(), 5.0);
+        assert!((state.gc_overhead_pct() - 10.0).abs() < 1e-9);
+        assert!(state.get_execution_trace().contains("2 pauses (1 minor, 1 major), 5.00ms"));
+    }
+
+    #[test]
+    fn test_entry_with_values_derives_display_arguments() {
+        let mut state = ExecutionState::default();
+        let args = vec![JSValue::String("id".to_string()), JSValue::Array(vec![JSValue::Number(1.0)])];
+        state.log_function_entry_with_values("load".to_string(), args, None, None);
+
+        let call = &state.function_call_history[0];
+        assert_eq!(call.arguments, ["\"id\"", "[1]"]);
+        assert!(call.arg_values[1].structural_eq(&JSValue::Array(vec![JSValue::Number(1.0)])));
+    }
+
+    #[test]
+    fn test_return_values_attach_to_latest_unset_call() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("fact".to_string(), vec!["2".to_string()], None, None);
+        state.log_function_entry("fact".to_string(), vec!["1".to_string()], None, None);
+        // Inner call returns first, then the outer one
+        assert!(state.set_return_value("fact", JSValue::Number(1.0)));
+        assert!(state.set_return_value("fact", JSValue::Number(2.0)));
+        assert!(!state.set_return_value("fact", JSValue::Number(3.0)));
+
+        assert!(state.function_call_history[0].return_value.as_ref().unwrap().structural_eq(&JSValue::Number(2.0)));
+        assert!(state.get_execution_trace().contains("fact(1) → 1"));
+    }
+
+    #[test]
+    fn test_exceptions_survive_eviction() {
+        let mut state = ExecutionState {
+            snapshot_limit: Arc::new(AtomicUsize::new(1)),
+            ..Default::default()
+        };
+        state.log_function_entry("parse".to_string(), vec![], None, None);
+        state.record_exception(JSValue::Object(HashMap::from([
+            ("message".to_string(), JSValue::String("bad input".to_string())),
+        ])));
+        state.record_snapshot("parse".to_string(), "custom".to_string(), HashMap::new(), HashMap::new());
+
+        assert_eq!(state.variable_snapshots[0].snapshot_type, "custom");
+        let exception = state.last_exception().unwrap();
+        assert_eq!(exception.function_name, "parse");
+        assert_eq!(exception.snapshot_type, "exception");
+        assert!(exception.variables.contains_key("error"));
+    }
+
+    #[test]
+    fn test_current_call_stack_skips_finished_calls() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        state.log_function_entry("load".to_string(), vec![], None, None);
+        state.log_function_exit("load".to_string(), 1.0);
+        state.log_function_entry("render".to_string(), vec![], None, None);
+        state.log_function_entry("draw".to_string(), vec![], None, None);
+
+        let names: Vec<&str> = state.current_call_stack().iter().map(|call| call.name.as_str()).collect();
+        assert_eq!(names, ["draw", "render", "main"]);
+    }
+
+    #[test]
+    fn test_variable_timeline() {
+        let mut state = ExecutionState::default();
+        for (timestamp, value) in [(3.0, Some(JSValue::String("3".to_string()))), (1.0, Some(JSValue::Number(1.0))),
+                                   (2.0, None), (1.5, Some(JSValue::Number(2.0)))] {
+            state.variable_snapshots.push(VariableSnapshot {
+                timestamp,
+                function_name: "f".to_string(),
+                call_depth: 1,
+                variables: value.into_iter().map(|value| ("x".to_string(), value)).collect(),
+                snapshot_type: "custom".to_string(),
+                metadata: HashMap::new(),
+                trace_id: None,
+                heap_stats: None,
+            });
+        }
+
+        let timeline = state.variable_timeline("x");
+        assert_eq!(timeline.iter().map(|(t, _)| *t).collect::<Vec<_>>(), [1.0, 1.5, 3.0]);
+        assert_eq!(timeline[2].1, &JSValue::String("3".to_string()));
+        assert!(state.variable_timeline("y").is_empty());
+    }
+
+    #[test]
+    fn test_checkpoints_filter_by_type() {
+        let mut state = ExecutionState::default();
+        state.record_snapshot("after-init".to_string(), "checkpoint".to_string(), HashMap::new(), HashMap::new());
+        state.record_snapshot("main".to_string(), "entry".to_string(), HashMap::new(), HashMap::new());
+        state.record_snapshot("done".to_string(), "checkpoint".to_string(), HashMap::new(), HashMap::new());
+
+        let labels: Vec<&str> = state.checkpoints().iter().map(|s| s.function_name.as_str()).collect();
+        assert_eq!(labels, ["after-init", "done"]);
+    }
+
+    #[test]
+    fn test_eviction_policies() {
+        let record = |state: &mut ExecutionState, depth: usize| {
+            state.call_stack_depth = depth;
+            state.record_snapshot(format!("depth{}", depth), "custom".to_string(), HashMap::new(), HashMap::new());
+        };
+        let limited = |policy| ExecutionState {
+            snapshot_limit: Arc::new(AtomicUsize::new(3)),
+            eviction_policy: policy,
+            ..Default::default()
+        };
+
+        let mut state = limited(EvictionPolicy::LowestDepth);
+        for depth in [2, 1, 3, 1, 4] {
+            record(&mut state, depth);
+        }
+        let depths: Vec<usize> = state.variable_snapshots.iter().map(|s| s.call_depth).collect();
+        assert_eq!(depths, [2, 3, 4]);
+        assert_eq!(state.eviction_count, 2);
+
+        let mut state = limited(EvictionPolicy::Error);
+        for depth in [2, 1, 3, 1] {
+            record(&mut state, depth);
+        }
+        let depths: Vec<usize> = state.variable_snapshots.iter().map(|s| s.call_depth).collect();
+        assert_eq!(depths, [2, 1, 3]);
+        assert_eq!(state.eviction_count, 0);
+        assert!(state.at_rejecting_limit());
+    }
+
+    #[test]
+    fn test_dedup_skips_repeated_snapshots() {
+        let mut state = ExecutionState { dedup_snapshots: true, ..Default::default() };
+        let point = |x: f64| HashMap::from([
+            ("i".to_string(), JSValue::Number(0.0)),
+            ("p".to_string(), JSValue::Object(HashMap::from([("x".to_string(), JSValue::Number(x)), ("y".to_string(), JSValue::Null)]))),
+        ]);
+        for (kind, x) in [("custom", 1.0), ("custom", 1.0), ("custom", 2.0), ("exit", 2.0), ("custom", 1.0)] {
+            state.record_snapshot("tick".to_string(), kind.to_string(), point(x), HashMap::new());
+        }
+
+        assert_eq!(state.variable_snapshots.len(), 4);
+        assert_eq!(state.dedup_skipped, 1);
+        assert!(state.get_execution_trace().contains("Duplicate snapshots skipped: 1"));
+    }
+
+    #[test]
+    fn test_byte_budget_evicts_oldest() {
+        let payload = || HashMap::from([("payload".to_string(), JSValue::String("x".repeat(100)))]);
+        let mut state = ExecutionState { max_snapshot_bytes: Some(usize::MAX), ..Default::default() };
+        state.record_snapshot("f".to_string(), "custom".to_string(), payload(), HashMap::new());
+        let size = state.snapshot_bytes;
+        assert_eq!(size, state.variable_snapshots[0].estimated_bytes());
+
+        state.max_snapshot_bytes = Some(size * 2);
+        for _ in 0..2 {
+            state.record_snapshot("f".to_string(), "custom".to_string(), payload(), HashMap::new());
+        }
+        assert_eq!((state.variable_snapshots.len(), state.snapshot_bytes), (2, size * 2));
+        assert_eq!(state.eviction_count, 1);
+
+        // The newest snapshot stays even when it alone is over budget
+        state.max_snapshot_bytes = Some(0);
+        state.record_snapshot("f".to_string(), "custom".to_string(), payload(), HashMap::new());
+        assert_eq!((state.variable_snapshots.len(), state.snapshot_bytes), (1, size));
+    }
+
+    #[test]
+    fn test_snapshot_file_written_in_batches() {
+        let path = std::env::temp_dir().join(format!("ttd_batch_{}.ttdl", std::process::id()));
+        let mut state = ExecutionState {
+            snapshot_file: Some(FileSnapshotStore::create(&path).unwrap()),
+            write_batch_size: 3,
+            ..Default::default()
+        };
+        let written = |state: &ExecutionState| state.snapshot_file.as_ref().unwrap().len();
+        for i in 0..4 {
+            state.record_snapshot("tick".to_string(), "custom".to_string(),
+                HashMap::from([("i".to_string(), JSValue::Number(i as f64))]), HashMap::new());
+            assert_eq!(written(&state), if i < 2 { 0 } else { 3 });
+        }
+
+        state.stop_capture();
+        let reopened = FileSnapshotStore::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.len(), 4);
+        assert_eq!(reopened.get(3).unwrap().variables["i"], JSValue::Number(3.0));
+    }
+
+    #[test]
+    fn test_reconstruct_diff_chain() {
+        let diff = |baseline: usize, variables: &[(&str, JSValue)]| VariableSnapshot {
+            snapshot_type: "diff".to_string(),
+            metadata: HashMap::from([
+                ("is_diff".to_string(), JSValue::Boolean(true)),
+                ("baseline_index".to_string(), JSValue::Number(baseline as f64)),
+            ]),
+            ..snapshot("f", variables)
+        };
+        let mut state = ExecutionState {
+            variable_snapshots: vec![
+                snapshot("f", &[("a", JSValue::Number(1.0)), ("b", JSValue::Number(2.0))]),
+                diff(0, &[("b", JSValue::Number(3.0))]),
+                diff(1, &[("c", JSValue::Null)]),
+            ].into(),
+            ..Default::default()
+        };
+
+        let full = state.reconstruct_snapshot(2).unwrap();
+        assert_eq!(full.variables.len(), 3);
+        assert!(full.variables["b"].structural_eq(&JSValue::Number(3.0)));
+
+        // Dropping the base snapshot materializes the diff that depended on it
+        state.clear_old_snapshots(2);
+        assert_eq!(state.variable_snapshots[0].baseline_index(), None);
+        assert_eq!(state.variable_snapshots[1].baseline_index(), Some(0));
+        assert_eq!(state.reconstruct_snapshot(1).unwrap().variables.len(), 3);
+    }
+
+    #[test]
+    fn test_function_coupling() {
+        let mut state = ExecutionState { coupling_window: 2, ..Default::default() };
+        for name in ["load", "parse", "load", "parse", "render"] {
+            state.log_function_entry(name.to_string(), vec![], None, None);
+            state.log_function_exit(name.to_string(), 0.0);
+        }
+
+        let matrix = state.function_coupling_matrix();
+        assert_eq!(matrix[&("load".to_string(), "parse".to_string())], 3);
+        assert_eq!(matrix[&("parse".to_string(), "render".to_string())], 1);
+        assert!(!matrix.contains_key(&("load".to_string(), "render".to_string())));
+
+        let strong = state.strongly_coupled_pairs(2);
+        assert_eq!(strong, vec![(("load".to_string(), "parse".to_string()), 3)]);
+    }
+
+    #[test]
+    fn test_fork_at_snapshot() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        state.variable_snapshots = vec![
+            snapshot("main", &[("x", JSValue::Number(1.0))]),
+            snapshot("main", &[("x", JSValue::Number(2.0))]),
+        ].into();
+        state.variable_snapshots[1].timestamp = f64::MAX;
+
+        let fork = state.fork_at(0).unwrap();
+        assert_eq!(fork.variable_snapshots.len(), 1);
+        assert!(fork.function_call_history.is_empty());
+        assert!(fork.replay_injection.unwrap().variables["x"].structural_eq(&JSValue::Number(1.0)));
+        assert!(state.fork_at(5).is_err());
+    }
+
+    #[test]
+    fn test_hot_path_summary() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("outer".to_string(), vec![], None, None);
+        state.start_hot_path("loop".to_string());
+        for _ in 0..3 {
+            state.log_function_entry("step".to_string(), vec![], None, None);
+            state.log_function_exit("step".to_string(), 0.0);
+        }
+        state.end_hot_path("loop").unwrap();
+
+        assert!(!state.in_hot_path());
+        assert_eq!(state.function_call_counts["step"], 3);
+        assert_eq!(state.hot_paths.len(), 1);
+        assert_eq!(state.hot_paths[0].call_count_in_path, 3);
+
+        let summary = &state.variable_snapshots[state.hot_paths[0].summary_snapshot_index.unwrap()];
+        assert_eq!(summary.snapshot_type, "hot_path");
+        assert!(matches!(&summary.variables["calls"], JSValue::Object(calls) if calls.len() == 1));
+        assert!(state.end_hot_path("loop").is_err());
+    }
+}
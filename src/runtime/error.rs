@@ -0,0 +1,21 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Failures callers may want to tell apart, carried inside `anyhow::Error` like every
+/// other error (match with `downcast_ref::<DebuggerError>()`)
+#[derive(Debug)]
+pub enum DebuggerError {
+    /// A snapshot log written with a format version this build can't read
+    UnsupportedSchemaVersion { path: PathBuf, found: u8, expected: u8 },
+}
+
+impl fmt::Display for DebuggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebuggerError::UnsupportedSchemaVersion { path, found, expected } => write!(f,
+                "{} uses snapshot store format {} (expected {})", path.display(), found, expected),
+        }
+    }
+}
+
+impl std::error::Error for DebuggerError {}
@@ -0,0 +1,107 @@
+use crate::runtime::engine::ExecutionState;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+/// The `[test]` section of a config file: execution properties `--test-mode` asserts.
+/// Only the keys present are checked.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestExpectations {
+    pub expected_function_calls: Option<u64>,
+    pub expected_snapshots: Option<usize>,
+    /// Exactly the set of functions that must be called, in any order
+    pub expected_functions: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    test: Option<TestExpectations>,
+}
+
+/// One expectation the run did not meet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectationMismatch {
+    pub key: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for ExpectationMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: expected {}, got {}", self.key, self.expected, self.actual)
+    }
+}
+
+impl TestExpectations {
+    /// Read the `[test]` section of the TOML config at `path`
+    pub fn from_toml(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read config {}: {}", path.display(), e))?;
+        let file: ConfigFile = toml::from_str(&text)
+            .map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?;
+        file.test.ok_or_else(|| anyhow!("{} has no [test] section", path.display()))
+    }
+
+    pub fn check(&self, state: &ExecutionState) -> Vec<ExpectationMismatch> {
+        let mut mismatches = Vec::new();
+        if let Some(expected) = self.expected_function_calls
+            && expected != state.function_calls {
+            mismatches.push(ExpectationMismatch {
+                key: "expected_function_calls",
+                expected: expected.to_string(),
+                actual: state.function_calls.to_string(),
+            });
+        }
+        if let Some(expected) = self.expected_snapshots
+            && expected != state.variable_snapshots.len() {
+            mismatches.push(ExpectationMismatch {
+                key: "expected_snapshots",
+                expected: expected.to_string(),
+                actual: state.variable_snapshots.len().to_string(),
+            });
+        }
+        if let Some(expected) = &self.expected_functions {
+            let expected: BTreeSet<&str> = expected.iter().map(String::as_str).collect();
+            let actual: BTreeSet<&str> = state.function_call_counts.keys().map(String::as_str).collect();
+            let missing: Vec<&str> = expected.difference(&actual).copied().collect();
+            let unexpected: Vec<&str> = actual.difference(&expected).copied().collect();
+            if !missing.is_empty() || !unexpected.is_empty() {
+                mismatches.push(ExpectationMismatch {
+                    key: "expected_functions",
+                    expected: format!("[{}]", expected.into_iter().collect::<Vec<_>>().join(", ")),
+                    actual: format!("missing [{}], unexpected [{}]", missing.join(", "), unexpected.join(", ")),
+                });
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expectations_report_mismatches() {
+        let path = std::env::temp_dir().join(format!("ttd_expectations_{}.toml", std::process::id()));
+        std::fs::write(&path, "max_snapshots = 10\n[test]\nexpected_function_calls = 3\nexpected_functions = [\"foo\", \"bar\"]\n").unwrap();
+        let expectations = TestExpectations::from_toml(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(expectations.expected_snapshots, None);
+
+        let mut state = ExecutionState::default();
+        for name in ["foo", "bar", "foo"] {
+            state.log_function_entry(name.to_string(), vec![], None, None);
+            state.log_function_exit(name.to_string(), 1.0);
+        }
+        assert!(expectations.check(&state).is_empty());
+
+        state.log_function_entry("baz".to_string(), vec![], None, None);
+        let mismatches = expectations.check(&state);
+        assert_eq!(mismatches[0].to_string(), "expected_function_calls: expected 3, got 4");
+        assert_eq!(mismatches[1].actual, "missing [], unexpected [baz]");
+    }
+}
@@ -0,0 +1,130 @@
+use deno_core::v8;
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::time::Instant;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GcKind {
+    /// Young generation collection (scavenge / minor mark-compact)
+    Minor,
+    /// Full mark-sweep-compact
+    Major,
+}
+
+/// A single garbage collection pause
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcEvent {
+    pub timestamp_ms: f64,
+    pub duration_ms: f64,
+    pub kind: GcKind,
+    /// Used heap size when the collection started and once it had finished
+    pub before_used_bytes: usize,
+    pub after_used_bytes: usize,
+}
+
+impl GcEvent {
+    pub fn freed_bytes(&self) -> usize {
+        self.before_used_bytes.saturating_sub(self.after_used_bytes)
+    }
+}
+
+/// GC that has started but whose end hasn't been observed yet
+struct PendingGc {
+    timestamp_ms: f64,
+    started: Instant,
+    kind: GcKind,
+    used_heap_before: usize,
+}
+
+/// Records GC pauses through V8's GC prologue callback.
+///
+/// rusty_v8 only binds `AddGCPrologueCallback`, so the end of a pause is observed by an
+/// interrupt requested from the prologue: V8 services it as soon as JS resumes after the
+/// collection. A GC that is never followed by JS is closed by the next GC or `take_events`.
+pub struct GcTracer {
+    isolate_handle: v8::IsolateHandle,
+    pending: RefCell<Option<PendingGc>>,
+    events: RefCell<Vec<GcEvent>>,
+}
+
+impl GcTracer {
+    /// Register the tracer with `isolate`. The returned tracer must outlive the isolate.
+    pub fn install(isolate: &mut v8::Isolate) -> Rc<GcTracer> {
+        let tracer = Rc::new(GcTracer {
+            isolate_handle: isolate.thread_safe_handle(),
+            pending: RefCell::new(None),
+            events: RefCell::new(Vec::new()),
+        });
+
+        isolate.add_gc_prologue_callback(
+            gc_prologue,
+            Rc::as_ptr(&tracer) as *mut c_void,
+            v8::GCType::SCAVENGE | v8::GCType::MINOR_MARK_COMPACT | v8::GCType::MARK_SWEEP_COMPACT,
+        );
+        tracer
+    }
+
+    /// GC events recorded so far, without closing a pause still in progress
+    pub fn events(&self) -> Vec<GcEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Close any pending pause and drain the recorded events
+    pub fn take_events(&self, isolate: &mut v8::Isolate) -> Vec<GcEvent> {
+        self.finish_pending(isolate);
+        std::mem::take(&mut *self.events.borrow_mut())
+    }
+
+    fn finish_pending(&self, isolate: &mut v8::Isolate) {
+        let Some(pending) = self.pending.borrow_mut().take() else { return };
+        let used_heap_after = used_heap_size(isolate);
+
+        self.events.borrow_mut().push(GcEvent {
+            timestamp_ms: pending.timestamp_ms,
+            duration_ms: pending.started.elapsed().as_secs_f64() * 1000.0,
+            kind: pending.kind,
+            before_used_bytes: pending.used_heap_before,
+            after_used_bytes: used_heap_after,
+        });
+    }
+}
+
+fn used_heap_size(isolate: &mut v8::Isolate) -> usize {
+    let mut stats = v8::HeapStatistics::default();
+    isolate.get_heap_statistics(&mut stats);
+    stats.used_heap_size()
+}
+
+extern "C" fn gc_prologue(
+    isolate: *mut v8::Isolate,
+    gc_type: v8::GCType,
+    _flags: v8::GCCallbackFlags,
+    data: *mut c_void,
+) {
+    // SAFETY: `data` is the `GcTracer` registered in `install`, which outlives the isolate,
+    // and V8 invokes GC callbacks on the isolate's own thread
+    let (tracer, isolate) = unsafe { (&*(data as *const GcTracer), &mut *isolate) };
+    tracer.finish_pending(isolate);
+
+    let kind = if gc_type == v8::GCType::MARK_SWEEP_COMPACT { GcKind::Major } else { GcKind::Minor };
+    *tracer.pending.borrow_mut() = Some(PendingGc {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64() * 1000.0,
+        started: Instant::now(),
+        kind,
+        used_heap_before: used_heap_size(isolate),
+    });
+
+    tracer.isolate_handle.request_interrupt(gc_interrupt, data);
+}
+
+extern "C" fn gc_interrupt(isolate: &mut v8::Isolate, data: *mut c_void) {
+    // SAFETY: see `gc_prologue`
+    let tracer = unsafe { &*(data as *const GcTracer) };
+    tracer.finish_pending(isolate);
+}
@@ -0,0 +1,95 @@
+use crate::runtime::engine::SnapshotDiff;
+use crate::runtime::serialization::JSValue;
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// Directory `timeDebugger.assertSnapshot` saves values to, relative to the working directory
+pub const SNAPSHOT_DIR: &str = ".snapshots";
+
+/// What `SnapshotAssertions::assert` did with a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    Created,
+    Matched,
+    Updated,
+}
+
+/// Saved values for `timeDebugger.assertSnapshot`, one JSON file per label. The first
+/// assertion of a label records its value; later ones must be structurally equal to it.
+#[derive(Debug, Clone)]
+pub struct SnapshotAssertions {
+    dir: PathBuf,
+    /// Overwrite saved values instead of comparing against them
+    update: bool,
+}
+
+impl SnapshotAssertions {
+    pub fn new(dir: impl Into<PathBuf>, update: bool) -> Self {
+        Self { dir: dir.into(), update }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File holding `label`'s saved value; characters other than alphanumerics, `-` and
+    /// `_` are replaced so any label maps to a file name
+    pub fn path_for(&self, label: &str) -> PathBuf {
+        let name: String = label.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.snap.json", name))
+    }
+
+    /// Compare `value` with the saved snapshot for `label`, saving it when there is none
+    /// (or when updating). A mismatch is an error carrying the diff.
+    pub fn assert(&self, label: &str, value: &JSValue) -> Result<SnapshotOutcome> {
+        let path = self.path_for(label);
+        if !self.update && path.exists() {
+            let saved: JSValue = serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(|e| anyhow!("Cannot read snapshot {}: {}", path.display(), e))?;
+            if saved.structural_eq(value) {
+                return Ok(SnapshotOutcome::Matched);
+            }
+            let diff = SnapshotDiff::between_values(label, &saved, value);
+            return Err(anyhow!("Snapshot {:?} does not match {}:\n{}Run with --update-snapshots to accept the new value",
+                label, path.display(), diff.to_display_string()));
+        }
+
+        let existed = path.exists();
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&path, serde_json::to_string_pretty(value)?)?;
+        Ok(if existed { SnapshotOutcome::Updated } else { SnapshotOutcome::Created })
+    }
+}
+
+impl Default for SnapshotAssertions {
+    fn default() -> Self {
+        Self::new(SNAPSHOT_DIR, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_assert_creates_matches_and_updates() {
+        let dir = std::env::temp_dir().join(format!("ttd_snapshots_{}", std::process::id()));
+        let value = |n: f64| JSValue::Object(HashMap::from([("n".to_string(), JSValue::Number(n))]));
+
+        let assertions = SnapshotAssertions::new(&dir, false);
+        assert_eq!(assertions.assert("cart total", &value(1.0)).unwrap(), SnapshotOutcome::Created);
+        assert!(assertions.path_for("cart total").ends_with("cart_total.snap.json"));
+        assert_eq!(assertions.assert("cart total", &value(1.0)).unwrap(), SnapshotOutcome::Matched);
+
+        let error = assertions.assert("cart total", &value(2.0)).unwrap_err().to_string();
+        assert!(error.contains("~ cart total.n: 1 → 2"), "{}", error);
+
+        let updating = SnapshotAssertions::new(&dir, true);
+        assert_eq!(updating.assert("cart total", &value(2.0)).unwrap(), SnapshotOutcome::Updated);
+        assert_eq!(assertions.assert("cart total", &value(2.0)).unwrap(), SnapshotOutcome::Matched);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use crate::runtime::engine::ExecutionState;
+use crate::runtime::persistence::StateSerializer;
+use deno_core::v8;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Once};
+
+/// Where `execute_file` writes the partial trace of an interrupted run by default
+pub const DEFAULT_CRASH_OUTPUT: &str = "partial_trace.ttd";
+/// Where the panic hook writes the trace by default
+pub const DEFAULT_PANIC_OUTPUT: &str = "panic_trace.ttd";
+
+/// `DebuggerConfig::panic_trace`: save the recorded state if the debugger itself panics.
+///
+/// The file holds the state as it was when the panic hit, possibly halfway through an
+/// update, not a clean end-of-run trace. Nothing is saved when the panic happens while
+/// the state is being modified (it is borrowed then).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PanicTraceConfig {
+    pub enabled: bool,
+    pub output_path: PathBuf,
+}
+
+impl Default for PanicTraceConfig {
+    fn default() -> Self {
+        Self { enabled: true, output_path: PathBuf::from(DEFAULT_PANIC_OUTPUT) }
+    }
+}
+
+thread_local! {
+    /// State the panic hook saves for a panic on this thread, and where to
+    static PANIC_TRACE: RefCell<Option<(Weak<RefCell<ExecutionState>>, PathBuf)>> = const { RefCell::new(None) };
+}
+
+/// Save `execution_state` to `output_path` if this thread panics while it is alive. The
+/// process-wide hook is installed once and runs the previously installed hook afterwards.
+pub fn install_panic_hook(execution_state: &Rc<RefCell<ExecutionState>>, output_path: PathBuf) {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            save_panic_trace();
+            previous(info);
+        }));
+    });
+    let _ = PANIC_TRACE.try_with(|slot| {
+        if let Ok(mut slot) = slot.try_borrow_mut() {
+            *slot = Some((Rc::downgrade(execution_state), output_path));
+        }
+    });
+}
+
+/// Runs inside the panic hook, where a second panic would abort the process: every
+/// step that can fail is checked and skipped instead
+fn save_panic_trace() {
+    let _ = PANIC_TRACE.try_with(|slot| {
+        let Ok(slot) = slot.try_borrow() else { return };
+        let Some((execution_state, path)) = slot.as_ref() else { return };
+        let Some(execution_state) = execution_state.upgrade() else { return };
+        let Ok(execution_state) = execution_state.try_borrow() else { return };
+        let message = match StateSerializer::save(&execution_state, path) {
+            Ok(()) => format!("💾 Debugger panicked; trace recorded so far saved to {}", path.display()),
+            Err(e) => format!("❌ Debugger panicked; failed to save trace to {}: {}", path.display(), e),
+        };
+        let _ = writeln!(std::io::stderr(), "{}", message);
+    });
+}
+
+/// The signal that cut a run short
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptSignal {
+    /// Ctrl-C (SIGINT)
+    Interrupt,
+    /// SIGTERM, e.g. from a process supervisor
+    Terminate,
+}
+
+impl InterruptSignal {
+    /// Exit status of a process ended by this signal: 128 plus the signal number
+    pub fn exit_code(self) -> i32 {
+        match self {
+            InterruptSignal::Interrupt => 130,
+            InterruptSignal::Terminate => 143,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(InterruptSignal::Interrupt),
+            2 => Some(InterruptSignal::Terminate),
+            _ => None,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            InterruptSignal::Interrupt => 1,
+            InterruptSignal::Terminate => 2,
+        }
+    }
+}
+
+/// Error ending a run stopped by a signal, once the partial trace has been dealt with.
+/// The CLI exits with `signal.exit_code()` when it sees one.
+#[derive(Debug)]
+pub struct Interrupted {
+    pub signal: InterruptSignal,
+    pub message: String,
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Waits for Ctrl-C (or SIGTERM on Unix) while a script runs. The isolate is terminated
+/// when one arrives, so a script stuck in synchronous code stops too and the trace
+/// recorded so far can be saved. The watch ends when this is dropped.
+pub struct InterruptWatcher {
+    signal: Arc<AtomicU8>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl InterruptWatcher {
+    pub fn spawn(isolate: v8::IsolateHandle) -> Self {
+        let signal = Arc::new(AtomicU8::new(0));
+        let received = signal.clone();
+        let shutdown = shutdown_signal();
+        let task = tokio::spawn(async move {
+            received.store(shutdown.await.as_u8(), Ordering::SeqCst);
+            isolate.terminate_execution();
+        });
+        Self { signal, task }
+    }
+
+    /// The signal that arrived, if any
+    pub fn signal(&self) -> Option<InterruptSignal> {
+        InterruptSignal::from_u8(self.signal.load(Ordering::SeqCst))
+    }
+
+    pub fn interrupted(&self) -> bool {
+        self.signal().is_some()
+    }
+}
+
+impl Drop for InterruptWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Resolves on the first Ctrl-C or SIGTERM; never resolves if no handler can be installed.
+/// The handlers are registered before this returns, so a signal sent as soon as the
+/// script starts is not missed (or left to kill the process outright).
+#[cfg(unix)]
+fn shutdown_signal() -> impl Future<Output = InterruptSignal> {
+    use tokio::signal::unix::{Signal, SignalKind, signal};
+
+    async fn recv(stream: std::io::Result<Signal>) {
+        match stream {
+            Ok(mut stream) => { stream.recv().await; },
+            Err(_) => std::future::pending::<()>().await,
+        }
+    }
+
+    let interrupt = signal(SignalKind::interrupt());
+    let terminate = signal(SignalKind::terminate());
+    async move {
+        tokio::select! {
+            _ = recv(interrupt) => InterruptSignal::Interrupt,
+            _ = recv(terminate) => InterruptSignal::Terminate,
+        }
+    }
+}
+
+/// Resolves on the first Ctrl-C; there is no SIGTERM to wait for outside Unix
+#[cfg(not(unix))]
+fn shutdown_signal() -> impl Future<Output = InterruptSignal> {
+    async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        InterruptSignal::Interrupt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_trace_saves_unborrowed_state() {
+        let path = std::env::temp_dir().join(format!("ttd_panic_{}.ttd", std::process::id()));
+        let execution_state = Rc::new(RefCell::new(ExecutionState::default()));
+        execution_state.borrow_mut().log_function_entry("boom".to_string(), vec![], None, None);
+        PANIC_TRACE.with(|slot| *slot.borrow_mut() = Some((Rc::downgrade(&execution_state), path.clone())));
+
+        // A state borrowed for writing is skipped rather than risking a second panic
+        let borrowed = execution_state.borrow_mut();
+        save_panic_trace();
+        assert!(!path.exists());
+        drop(borrowed);
+
+        save_panic_trace();
+        let saved = StateSerializer::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.function_call_history[0].name, "boom");
+    }
+}
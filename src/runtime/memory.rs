@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use serde::Deserialize;
+use tracing::info;
+use crate::runtime::engine::ExecutionState;
+
+/// How `max_snapshots` reacts to memory usage
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryPressureMode {
+    /// Keep `max_snapshots` fixed
+    #[default]
+    Static,
+    /// Shrink and restore `max_snapshots` based on process RSS
+    Adaptive,
+}
+
+/// Snapshot limit shared between the monitor task and `ExecutionState`
+pub type SnapshotLimit = Arc<AtomicUsize>;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+/// Above this fraction of the memory budget the snapshot limit shrinks
+const HIGH_WATER: f64 = 0.6;
+/// Below this fraction the snapshot limit grows back toward its original value
+const LOW_WATER: f64 = 0.4;
+/// Fraction of the limit removed (or restored) per adjustment
+const ADJUSTMENT_STEP: f64 = 0.1;
+
+/// Background task that adapts the snapshot limit to the process's resident memory.
+/// When the limit shrinks, snapshots over it are evicted right away.
+pub struct MemoryPressureMonitor {
+    max_memory_bytes: u64,
+    original_max_snapshots: usize,
+    snapshot_limit: SnapshotLimit,
+    execution_state: Weak<RefCell<ExecutionState>>,
+}
+
+impl MemoryPressureMonitor {
+    pub fn new(max_memory_mb: u64, execution_state: &Rc<RefCell<ExecutionState>>) -> Self {
+        let snapshot_limit = execution_state.borrow().snapshot_limit.clone();
+        let original_max_snapshots = snapshot_limit.load(Ordering::Relaxed);
+        Self {
+            max_memory_bytes: max_memory_mb * 1024 * 1024,
+            original_max_snapshots,
+            snapshot_limit,
+            execution_state: Rc::downgrade(execution_state),
+        }
+    }
+
+    /// Apply one adjustment for the given RSS, returning the new limit if it changed
+    pub fn evaluate(&self, rss_bytes: u64) -> Option<usize> {
+        let usage = rss_bytes as f64 / self.max_memory_bytes as f64;
+        let current = self.snapshot_limit.load(Ordering::Relaxed);
+        let step = ((current as f64 * ADJUSTMENT_STEP) as usize).max(1);
+
+        let new_limit = if usage > HIGH_WATER && current > 1 {
+            current - step.min(current - 1)
+        } else if usage < LOW_WATER && current < self.original_max_snapshots {
+            let restore_step = ((self.original_max_snapshots as f64 * ADJUSTMENT_STEP) as usize).max(1);
+            (current + restore_step).min(self.original_max_snapshots)
+        } else {
+            return None;
+        };
+
+        self.snapshot_limit.store(new_limit, Ordering::Relaxed);
+        info!(rss_mb = rss_bytes as f64 / (1024.0 * 1024.0), budget_percent = usage * 100.0,
+            from = current, to = new_limit, "[MEM] max_snapshots adjusted");
+        // An op holding the state will trim on its next capture instead
+        if new_limit < current
+            && let Some(state) = self.execution_state.upgrade()
+            && let Ok(mut state) = state.try_borrow_mut() {
+            state.evict_snapshots(new_limit);
+        }
+        Some(new_limit)
+    }
+
+    /// Sample RSS every 500ms on `local`, the runtime's thread, until the returned task is aborted
+    pub fn spawn(self, local: &tokio::task::LocalSet) -> tokio::task::JoinHandle<()> {
+        local.spawn_local(async move {
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Some(rss_bytes) = current_rss_bytes() {
+                    self.evaluate(rss_bytes);
+                }
+            }
+        })
+    }
+}
+
+/// Resident set size of this process, from `/proc/self/status` (Linux only)
+pub fn current_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_monitor_triggers_at_threshold() {
+        let limit: SnapshotLimit = Arc::new(AtomicUsize::new(1000));
+        let state = Rc::new(RefCell::new(ExecutionState { snapshot_limit: limit.clone(), ..Default::default() }));
+        for index in 0..950 {
+            state.borrow_mut().record_snapshot(format!("f{}", index), "custom".to_string(), HashMap::new(), HashMap::new());
+        }
+        let monitor = MemoryPressureMonitor::new(100, &state);
+        let mb = 1024 * 1024;
+
+        // Between the water marks nothing changes
+        assert_eq!(monitor.evaluate(50 * mb), None);
+        assert_eq!(monitor.evaluate(60 * mb), None);
+
+        // Just over 60% shrinks the limit by 10%
+        assert_eq!(monitor.evaluate(61 * mb), Some(900));
+        assert_eq!(limit.load(Ordering::Relaxed), 900);
+        // The oldest snapshots go without waiting for another capture
+        let state_ref = state.borrow();
+        assert_eq!(state_ref.variable_snapshots.len(), 900);
+        assert_eq!(state_ref.variable_snapshots[0].function_name, "f50");
+        assert_eq!(state_ref.eviction_count, 50);
+        drop(state_ref);
+
+        // Under 40% restores toward, but never beyond, the original value
+        assert_eq!(monitor.evaluate(39 * mb), Some(1000));
+        assert_eq!(monitor.evaluate(10 * mb), None);
+    }
+}
@@ -1,4 +1,25 @@
 pub mod engine;
 pub mod serialization;
+pub mod protobuf;
+pub mod coverage;
+pub mod memory;
+pub mod otlp;
+pub mod gc;
+pub mod report;
+pub mod replay;
+pub mod persistence;
+pub mod call_tree;
+pub mod source_map;
+pub mod query;
+pub mod snapshot_store;
+pub mod delta;
+pub mod compare;
+pub mod inline_snapshot;
+pub mod interrupt;
+pub mod expectations;
+pub mod closure;
+pub mod purity;
+pub mod modules;
+pub mod error;
 
-pub use engine::*; 
\ No newline at end of file
+pub use engine::*;
@@ -0,0 +1,231 @@
+use crate::runtime::engine::ExecutionState;
+use deno_core::error::AnyError;
+use deno_core::{
+    FsModuleLoader, ModuleLoadResponse, ModuleLoader, ModuleSpecifier, RequestedModuleType, ResolutionKind,
+};
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::rc::Weak;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A module handed to V8 for compilation, in the order the loader saw them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleLoad {
+    pub specifier: String,
+    pub timestamp_ms: f64,
+    /// Loaded for an `import()` rather than a static import or the main module
+    pub dynamic: bool,
+}
+
+/// An `import()` call, as seen when the loader resolved it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportRecord {
+    /// The specifier as written in the `import()` call
+    pub specifier: String,
+    pub resolved_url: String,
+    /// Epoch seconds, like `FunctionCall::timestamp`
+    pub timestamp: f64,
+    /// `ExecutionState::current_function` when the import was made
+    pub from_function: Option<String>,
+}
+
+/// `FsModuleLoader` that records which modules each module statically imports and when
+/// each was loaded.
+///
+/// deno_core keeps its module map private, so the graph is built from the loader's side:
+/// every resolved `import` is an edge from its referrer. Loads are buffered here because
+/// dynamic imports are loaded while the event loop runs, and drained into
+/// `ExecutionState::module_loads` once execution finishes. `import()` calls are recorded
+/// the same way, since the syntax can't be wrapped from JS.
+pub struct TracingModuleLoader {
+    execution_state: Weak<RefCell<ExecutionState>>,
+    dependencies: RefCell<HashMap<String, Vec<String>>>,
+    loads: RefCell<Vec<ModuleLoad>>,
+    dynamic_imports: RefCell<Vec<ImportRecord>>,
+    /// URLs the debugger itself is about to `import()`, left out of `dynamic_imports`
+    ignored_imports: RefCell<Vec<String>>,
+}
+
+impl TracingModuleLoader {
+    /// `execution_state` supplies the function each `import()` is called from
+    pub fn new(execution_state: Weak<RefCell<ExecutionState>>) -> Self {
+        Self {
+            execution_state,
+            dependencies: RefCell::default(),
+            loads: RefCell::default(),
+            dynamic_imports: RefCell::default(),
+            ignored_imports: RefCell::default(),
+        }
+    }
+
+    /// Each loaded module's direct static imports, in source order
+    pub fn dependency_graph(&self) -> HashMap<String, Vec<String>> {
+        let mut graph = self.dependencies.borrow().clone();
+        for load in self.loads.borrow().iter() {
+            graph.entry(load.specifier.clone()).or_default();
+        }
+        graph
+    }
+
+    pub fn take_loads(&self) -> Vec<ModuleLoad> {
+        std::mem::take(&mut self.loads.borrow_mut())
+    }
+
+    pub fn take_dynamic_imports(&self) -> Vec<ImportRecord> {
+        std::mem::take(&mut self.dynamic_imports.borrow_mut())
+    }
+
+    /// Don't record the next `import()` of `url`, which the debugger makes on its own behalf
+    pub fn ignore_next_import(&self, url: String) {
+        self.ignored_imports.borrow_mut().push(url);
+    }
+
+    /// Whether `url` was passed to `ignore_next_import`, forgetting it if so
+    fn take_ignored_import(&self, url: &str) -> bool {
+        let mut ignored = self.ignored_imports.borrow_mut();
+        match ignored.iter().position(|ignored| ignored == url) {
+            Some(index) => {
+                ignored.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The function on top of the recorded stack, unless an op is updating the state
+    fn current_function(&self) -> Option<String> {
+        let execution_state = self.execution_state.upgrade()?;
+        let execution_state = execution_state.try_borrow().ok()?;
+        execution_state.current_function.clone()
+    }
+}
+
+impl ModuleLoader for TracingModuleLoader {
+    fn resolve(&self, specifier: &str, referrer: &str, kind: ResolutionKind) -> Result<ModuleSpecifier, AnyError> {
+        let is_static_import = kind == ResolutionKind::Import;
+        let is_dynamic_import = kind == ResolutionKind::DynamicImport;
+        let resolved = FsModuleLoader.resolve(specifier, referrer, kind)?;
+        if is_static_import {
+            let mut dependencies = self.dependencies.borrow_mut();
+            let imports = dependencies.entry(referrer.to_string()).or_default();
+            if !imports.iter().any(|import| import == resolved.as_str()) {
+                imports.push(resolved.to_string());
+            }
+        } else if is_dynamic_import && !self.take_ignored_import(resolved.as_str()) {
+            self.dynamic_imports.borrow_mut().push(ImportRecord {
+                specifier: specifier.to_string(),
+                resolved_url: resolved.to_string(),
+                timestamp: current_time_ms() / 1000.0,
+                from_function: self.current_function(),
+            });
+        }
+        Ok(resolved)
+    }
+
+    fn load(
+        &self,
+        module_specifier: &ModuleSpecifier,
+        maybe_referrer: Option<&ModuleSpecifier>,
+        is_dyn_import: bool,
+        requested_module_type: RequestedModuleType,
+    ) -> ModuleLoadResponse {
+        self.loads.borrow_mut().push(ModuleLoad {
+            specifier: module_specifier.to_string(),
+            timestamp_ms: current_time_ms(),
+            dynamic: is_dyn_import,
+        });
+        FsModuleLoader.load(module_specifier, maybe_referrer, is_dyn_import, requested_module_type)
+    }
+}
+
+fn current_time_ms() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64() * 1000.0
+}
+
+/// Graphviz `digraph` of `graph`, each module labelled with its position in `loads` and
+/// when it was loaded relative to the first. Modules loaded by `import()` are dashed;
+/// edges that close an import cycle are drawn in red.
+pub fn module_graph_to_dot(graph: &HashMap<String, Vec<String>>, loads: &[ModuleLoad]) -> String {
+    let first_load = loads.first().map_or(0.0, |load| load.timestamp_ms);
+    let load_info: HashMap<&str, (usize, &ModuleLoad)> = loads.iter().enumerate().rev()
+        .map(|(order, load)| (load.specifier.as_str(), (order + 1, load)))
+        .collect();
+    let sorted: BTreeMap<&String, &Vec<String>> = graph.iter().collect();
+
+    let mut dot = String::from("digraph modules {\n    node [shape=box, fontname=\"monospace\"];\n");
+    for specifier in sorted.keys() {
+        let mut label = specifier.to_string();
+        let mut style = "";
+        if let Some((order, load)) = load_info.get(specifier.as_str()) {
+            let _ = write!(label, "\\n#{} +{:.3}ms", order, load.timestamp_ms - first_load);
+            if load.dynamic {
+                style = ", style=dashed";
+            }
+        }
+        let _ = writeln!(dot, "    \"{}\" [label=\"{}\"{}];", escape(specifier), escape(&label), style);
+    }
+    for (specifier, imports) in &sorted {
+        for import in imports.iter() {
+            let color = if reaches(graph, import, specifier) { " [color=red]" } else { "" };
+            let _ = writeln!(dot, "    \"{}\" -> \"{}\"{};", escape(specifier), escape(import), color);
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Whether `to` is reachable from `from` through static imports
+fn reaches(graph: &HashMap<String, Vec<String>>, from: &str, to: &str) -> bool {
+    let mut stack = vec![from];
+    let mut seen = std::collections::HashSet::new();
+    while let Some(module) = stack.pop() {
+        if module == to {
+            return true;
+        }
+        if seen.insert(module) {
+            stack.extend(graph.get(module).into_iter().flatten().map(String::as_str));
+        }
+    }
+    false
+}
+
+fn escape(text: &str) -> String {
+    text.replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_graph_dot_marks_cycles() {
+        let graph = HashMap::from([
+            ("file:///main.js".to_string(), vec!["file:///a.js".to_string()]),
+            ("file:///a.js".to_string(), vec!["file:///b.js".to_string()]),
+            ("file:///b.js".to_string(), vec!["file:///a.js".to_string()]),
+        ]);
+        let load = |specifier: &str, timestamp_ms: f64| ModuleLoad { specifier: specifier.to_string(), timestamp_ms, dynamic: false };
+        let loads = [load("file:///main.js", 100.0), load("file:///a.js", 101.5), load("file:///b.js", 102.0)];
+
+        let dot = module_graph_to_dot(&graph, &loads);
+        assert!(dot.contains("\"file:///a.js\" [label=\"file:///a.js\\n#2 +1.500ms\"];"));
+        assert!(dot.contains("\"file:///main.js\" -> \"file:///a.js\";"));
+        assert!(dot.contains("\"file:///a.js\" -> \"file:///b.js\" [color=red];"));
+        assert!(dot.contains("\"file:///b.js\" -> \"file:///a.js\" [color=red];"));
+    }
+
+    #[test]
+    fn test_ignored_import_is_not_recorded() {
+        let loader = TracingModuleLoader::new(Weak::new());
+        loader.ignore_next_import("file:///app/util.js".to_string());
+        for _ in 0..2 {
+            loader.resolve("./util.js", "file:///app/main.js", ResolutionKind::DynamicImport).unwrap();
+        }
+
+        let imports = loader.take_dynamic_imports();
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].resolved_url, "file:///app/util.js");
+    }
+}
@@ -0,0 +1,171 @@
+use crate::runtime::engine::{ExecutionState, VariableSnapshot};
+use crate::runtime::serialization::JSValue;
+use anyhow::Result;
+use prost::Message;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// OTLP trace types generated by prost-build from `proto/otlp.proto`
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/otlp.rs"));
+}
+
+use proto::any_value::Value;
+
+const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+
+impl ExecutionState {
+    /// Encode the call history as an OTLP `ExportTraceServiceRequest`: one span per call,
+    /// parented to the enclosing call, with variable snapshots attached as span events
+    pub fn export_opentelemetry_traces(&self) -> Result<Vec<u8>> {
+        let trace_id = self.execution_fingerprint().to_be_bytes().to_vec();
+        let mut spans: Vec<proto::Span> = Vec::with_capacity(self.function_call_history.len());
+        // Index into `spans` of the open call at each depth
+        let mut open_at_depth: Vec<usize> = Vec::new();
+
+        for (index, call) in self.function_call_history.iter().enumerate() {
+            let depth = call.call_depth.max(1);
+            open_at_depth.truncate(depth - 1);
+            let parent_span_id = open_at_depth.last()
+                .map(|parent| spans[*parent].span_id.clone())
+                .unwrap_or_default();
+
+            let start = (call.timestamp * NANOS_PER_SECOND) as u64;
+            let end = start + call.duration_ms.map_or(0, |ms| (ms * 1_000_000.0) as u64);
+
+            let mut attributes = vec![key_value("code.call_depth", Value::IntValue(call.call_depth as i64))];
+            if !call.arguments.is_empty() {
+                attributes.push(key_value("code.arguments", Value::StringValue(call.arguments.join(", "))));
+            }
+            if let Some(location) = &call.file_location {
+                attributes.push(key_value("code.filepath", Value::StringValue(location.clone())));
+            }
+            if let Some(line) = call.line_number {
+                attributes.push(key_value("code.lineno", Value::IntValue(line as i64)));
+            }
+
+            spans.push(proto::Span {
+                trace_id: trace_id.clone(),
+                span_id: span_id(index),
+                parent_span_id,
+                name: call.name.clone(),
+                kind: proto::span::SpanKind::Internal as i32,
+                start_time_unix_nano: start,
+                end_time_unix_nano: end,
+                attributes,
+                ..Default::default()
+            });
+
+            while open_at_depth.len() < depth - 1 {
+                open_at_depth.push(open_at_depth.last().copied().unwrap_or(index));
+            }
+            open_at_depth.push(index);
+        }
+
+        for snapshot in &self.variable_snapshots {
+            if let Some(span) = self.owning_span(snapshot).and_then(|index| spans.get_mut(index)) {
+                span.events.push(snapshot_event(snapshot));
+            }
+        }
+
+        let request = proto::ExportTraceServiceRequest {
+            resource_spans: vec![proto::ResourceSpans {
+                resource: Some(proto::Resource {
+                    attributes: vec![key_value("service.name", Value::StringValue("time_travel_debugger".to_string()))],
+                    ..Default::default()
+                }),
+                scope_spans: vec![proto::ScopeSpans {
+                    scope: Some(proto::InstrumentationScope {
+                        name: "time_travel_debugger".to_string(),
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                    }),
+                    spans,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+
+        Ok(request.encode_to_vec())
+    }
+
+    /// Stable identifier for this recorded execution, used as the OTLP trace id
+    pub(crate) fn execution_fingerprint(&self) -> u128 {
+        let hash_with_seed = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            self.function_calls.hash(&mut hasher);
+            for call in &self.function_call_history {
+                call.name.hash(&mut hasher);
+                call.timestamp.to_bits().hash(&mut hasher);
+            }
+            hasher.finish()
+        };
+        ((hash_with_seed(0) as u128) << 64) | hash_with_seed(1) as u128
+    }
+
+    /// The most recent call at the snapshot's depth that started before it
+    fn owning_span(&self, snapshot: &VariableSnapshot) -> Option<usize> {
+        self.function_call_history.iter()
+            .rposition(|call| call.call_depth == snapshot.call_depth && call.timestamp <= snapshot.timestamp)
+    }
+}
+
+/// Span ids must be non-zero, so call index 0 maps to span id 1
+fn span_id(call_index: usize) -> Vec<u8> {
+    (call_index as u64 + 1).to_be_bytes().to_vec()
+}
+
+fn snapshot_event(snapshot: &VariableSnapshot) -> proto::span::Event {
+    let mut names: Vec<&String> = snapshot.variables.keys().collect();
+    names.sort();
+
+    proto::span::Event {
+        time_unix_nano: (snapshot.timestamp * NANOS_PER_SECOND) as u64,
+        name: snapshot.snapshot_type.clone(),
+        attributes: names.into_iter()
+            .map(|name| key_value(name, any_value(&snapshot.variables[name])))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Primitives keep their type; everything else is exported as its display string
+fn any_value(value: &JSValue) -> Value {
+    match value {
+        JSValue::Boolean(b) => Value::BoolValue(*b),
+        JSValue::Number(n) => Value::DoubleValue(*n),
+        JSValue::String(s) => Value::StringValue(s.clone()),
+        other => Value::StringValue(other.to_display_string()),
+    }
+}
+
+fn key_value(key: &str, value: Value) -> proto::KeyValue {
+    proto::KeyValue {
+        key: key.to_string(),
+        value: Some(proto::AnyValue { value: Some(value) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otlp_span_parenting() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        state.log_function_entry("helper".to_string(), vec![], None, None);
+        state.log_function_exit("helper".to_string(), 2.0);
+        state.log_function_exit("main".to_string(), 5.0);
+
+        let bytes = state.export_opentelemetry_traces().unwrap();
+        let request = proto::ExportTraceServiceRequest::decode(bytes.as_slice()).unwrap();
+        let spans = &request.resource_spans[0].scope_spans[0].spans;
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].trace_id.len(), 16);
+        assert!(spans[0].parent_span_id.is_empty());
+        assert_eq!(spans[1].parent_span_id, spans[0].span_id);
+        assert_eq!(spans[1].end_time_unix_nano - spans[1].start_time_unix_nano, 2_000_000);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::runtime::engine::{BranchRecord, EventLoopStats, ExecutionState, FunctionCall, HotPathRecord, StateMachineEvent, VariableSnapshot};
+use crate::runtime::gc::GcEvent;
+use crate::runtime::serialization::JSValue;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// Leading bytes of every saved state file
+const MAGIC: &[u8; 4] = b"TTDS";
+/// Leading bytes of LZ4-compressed state files
+const COMPRESSED_MAGIC: &[u8; 4] = b"TTDZ";
+/// Conventional file extension for compressed state files
+pub const COMPRESSED_EXTENSION: &str = ".ttd.lz4";
+/// Bumped whenever `PersistedState` changes shape
+const FORMAT_VERSION: u8 = 7;
+/// Leading bytes of a snapshot file written by `SnapshotSerializer`
+const SNAPSHOT_MAGIC: &[u8; 4] = b"TTDV";
+/// Bumped whenever `SnapshotFile` or `VariableSnapshot` changes shape; older versions
+/// need a `migrate_*` step in `SnapshotSerializer::decode`
+const SNAPSHOT_FORMAT_VERSION: u8 = 3;
+/// File extension `timeDebugger.saveSnapshot` gives saved snapshots
+pub const SNAPSHOT_EXTENSION: &str = ".ttd";
+
+/// The recorded parts of `ExecutionState`; runtime-only fields (serialization context,
+/// limits, active hot paths) are rebuilt with their defaults on load
+#[derive(Serialize, Deserialize)]
+struct PersistedState {
+    function_calls: u64,
+    total_execution_time: Duration,
+    function_call_history: Vec<FunctionCall>,
+    function_call_counts: HashMap<String, u32>,
+    variable_snapshots: Vec<VariableSnapshot>,
+    hot_paths: Vec<HotPathRecord>,
+    variable_aliases: HashMap<String, String>,
+    event_loop_samples: Vec<(f64, EventLoopStats)>,
+    gc_events: Vec<GcEvent>,
+    state_machine_events: Vec<StateMachineEvent>,
+    branch_log: Vec<BranchRecord>,
+}
+
+/// Saves and loads recorded executions as compact bincode files
+pub struct StateSerializer;
+
+impl StateSerializer {
+    /// Save `state`; paths ending in `.ttd.lz4` are written LZ4-compressed
+    pub fn save(state: &ExecutionState, path: &Path) -> Result<()> {
+        if path.to_string_lossy().ends_with(COMPRESSED_EXTENSION) {
+            return CompressedStateSerializer::save(state, path);
+        }
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend(Self::encode(state)?);
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Load a saved state, detecting compressed files by their header
+    pub fn load(path: &Path) -> Result<ExecutionState> {
+        let bytes = std::fs::read(path)?;
+        if bytes.starts_with(COMPRESSED_MAGIC) {
+            return CompressedStateSerializer::decode(&bytes, path);
+        }
+
+        let payload = bytes.strip_prefix(MAGIC.as_slice())
+            .ok_or_else(|| anyhow!("{} is not a saved execution state", path.display()))?;
+        let payload = check_version(payload, path)?;
+        Self::decode(payload)
+    }
+
+    /// The bincode payload `save` writes after the header
+    pub fn encode(state: &ExecutionState) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&PersistedState::from_state(state))?)
+    }
+
+    /// Read back a payload from `encode`
+    pub fn decode(payload: &[u8]) -> Result<ExecutionState> {
+        Ok(bincode::deserialize::<PersistedState>(payload)?.into_state())
+    }
+}
+
+/// Combines saved traces, e.g. from parallel test runs, into one with `ExecutionState::merge`
+pub struct TraceMerger;
+
+impl TraceMerger {
+    pub fn merge_files(paths: &[PathBuf]) -> Result<ExecutionState> {
+        let load = |path: &PathBuf| StateSerializer::load(path)
+            .map_err(|e| anyhow!("Cannot load trace {}: {}", path.display(), e));
+        let (first, rest) = paths.split_first().ok_or_else(|| anyhow!("No traces to merge"))?;
+        rest.iter().try_fold(load(first)?, |merged, path| Ok(merged.merge(load(path)?)))
+    }
+}
+
+/// `StateSerializer` with the bincode payload wrapped in an LZ4 frame
+pub struct CompressedStateSerializer;
+
+impl CompressedStateSerializer {
+    pub fn save(state: &ExecutionState, path: &Path) -> Result<()> {
+        let mut bytes = Vec::with_capacity(COMPRESSED_MAGIC.len() + 1);
+        bytes.extend_from_slice(COMPRESSED_MAGIC);
+        bytes.push(FORMAT_VERSION);
+
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(bytes);
+        bincode::serialize_into(&mut encoder, &PersistedState::from_state(state))?;
+        let bytes = encoder.finish()?;
+        std::fs::File::create(path)?.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<ExecutionState> {
+        Self::decode(&std::fs::read(path)?, path)
+    }
+
+    fn decode(bytes: &[u8], path: &Path) -> Result<ExecutionState> {
+        let payload = bytes.strip_prefix(COMPRESSED_MAGIC.as_slice())
+            .ok_or_else(|| anyhow!("{} is not a compressed execution state", path.display()))?;
+        let payload = check_version(payload, path)?;
+
+        let mut decompressed = Vec::new();
+        lz4_flex::frame::FrameDecoder::new(payload).read_to_end(&mut decompressed)?;
+        StateSerializer::decode(&decompressed)
+    }
+}
+
+/// Contents of a snapshot file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub version: u32,
+    pub snapshots: Vec<VariableSnapshot>,
+}
+
+/// Version 1 snapshot files held exactly one bare snapshot
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotFileV1 {
+    pub snapshot: VariableSnapshotV2,
+}
+
+/// Version 2 snapshot files, whose snapshots predate `VariableSnapshot::heap_stats`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotFileV2 {
+    pub version: u32,
+    pub snapshots: Vec<VariableSnapshotV2>,
+}
+
+/// `VariableSnapshot` as saved by snapshot format versions 1 and 2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariableSnapshotV2 {
+    pub timestamp: f64,
+    pub function_name: String,
+    pub call_depth: usize,
+    pub variables: HashMap<String, JSValue>,
+    pub snapshot_type: String,
+    pub metadata: HashMap<String, JSValue>,
+    pub trace_id: Option<String>,
+}
+
+pub fn migrate_v1_to_v2(old: SnapshotFileV1) -> SnapshotFileV2 {
+    SnapshotFileV2 { version: 2, snapshots: vec![old.snapshot] }
+}
+
+pub fn migrate_v2_to_v3(old: SnapshotFileV2) -> SnapshotFile {
+    let snapshots = old.snapshots.into_iter().map(|snapshot| VariableSnapshot {
+        timestamp: snapshot.timestamp,
+        function_name: snapshot.function_name,
+        call_depth: snapshot.call_depth,
+        variables: snapshot.variables,
+        snapshot_type: snapshot.snapshot_type,
+        metadata: snapshot.metadata,
+        trace_id: snapshot.trace_id,
+        heap_stats: None,
+    }).collect();
+    SnapshotFile { version: 3, snapshots }
+}
+
+/// Saves and loads `VariableSnapshot`s as standalone files, for checkpoints taken from a
+/// script. Files from older format versions are migrated on load.
+pub struct SnapshotSerializer;
+
+impl SnapshotSerializer {
+    pub fn save(snapshots: &[VariableSnapshot], path: &Path) -> Result<()> {
+        let file = SnapshotFile { version: SNAPSHOT_FORMAT_VERSION.into(), snapshots: snapshots.to_vec() };
+        let mut bytes = Vec::with_capacity(SNAPSHOT_MAGIC.len() + 1);
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.push(SNAPSHOT_FORMAT_VERSION);
+        bincode::serialize_into(&mut bytes, &file)?;
+        std::fs::write(path, bytes)
+            .map_err(|e| anyhow!("Cannot write snapshot {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<SnapshotFile> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow!("Cannot read snapshot {}: {}", path.display(), e))?;
+        let payload = bytes.strip_prefix(SNAPSHOT_MAGIC.as_slice())
+            .ok_or_else(|| anyhow!("{} is not a saved snapshot", path.display()))?;
+        let (&version, payload) = payload.split_first()
+            .ok_or_else(|| anyhow!("{} is truncated", path.display()))?;
+        Self::decode(version, payload, path)
+    }
+
+    /// Deserialize `payload` with the layout of format `version`, migrating it forward
+    fn decode(version: u8, payload: &[u8], path: &Path) -> Result<SnapshotFile> {
+        let corrupt = |e: bincode::Error| anyhow!("{} is not a valid version {} snapshot file: {}", path.display(), version, e);
+        let deprecated = || warn!(path = %path.display(), version, current = SNAPSHOT_FORMAT_VERSION,
+            "loading a deprecated snapshot file format; save it again to upgrade");
+        match version {
+            1 => {
+                deprecated();
+                Ok(migrate_v2_to_v3(migrate_v1_to_v2(bincode::deserialize(payload).map_err(corrupt)?)))
+            },
+            2 => {
+                deprecated();
+                Ok(migrate_v2_to_v3(bincode::deserialize(payload).map_err(corrupt)?))
+            },
+            SNAPSHOT_FORMAT_VERSION => bincode::deserialize(payload).map_err(corrupt),
+            _ => Err(anyhow!("{} uses snapshot format version {}, but only versions 1 to {} are supported",
+                path.display(), version, SNAPSHOT_FORMAT_VERSION)),
+        }
+    }
+}
+
+/// Resolve `path` against `root`, refusing any path that ends up outside it. deno_core
+/// has no permission system of its own, so this is what keeps scripts' snapshot reads
+/// and writes inside the working directory. The check is lexical (symlinks are not
+/// followed), which lets it vet files that don't exist yet.
+pub fn resolve_within(root: &Path, path: &Path) -> Result<PathBuf> {
+    let normalize = |path: &Path| {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {},
+                Component::ParentDir => { normalized.pop(); },
+                other => normalized.push(other),
+            }
+        }
+        normalized
+    };
+    let root = normalize(root);
+    let resolved = normalize(&root.join(path));
+    if !resolved.starts_with(&root) || resolved == root {
+        return Err(anyhow!("Access to {} denied: outside {}", path.display(), root.display()));
+    }
+    Ok(resolved)
+}
+
+/// Strip and validate the version byte that follows the magic header
+fn check_version<'a>(payload: &'a [u8], path: &Path) -> Result<&'a [u8]> {
+    let (&version, payload) = payload.split_first()
+        .ok_or_else(|| anyhow!("{} is truncated", path.display()))?;
+    if version != FORMAT_VERSION {
+        return Err(anyhow!("{} uses state format version {}, but only version {} is supported",
+            path.display(), version, FORMAT_VERSION));
+    }
+    Ok(payload)
+}
+
+impl PersistedState {
+    fn from_state(state: &ExecutionState) -> Self {
+        PersistedState {
+            function_calls: state.function_calls,
+            total_execution_time: state.total_execution_time,
+            function_call_history: state.function_call_history.clone(),
+            function_call_counts: state.function_call_counts.clone(),
+            variable_snapshots: state.variable_snapshots.to_vec(),
+            hot_paths: state.hot_paths.clone(),
+            variable_aliases: state.variable_aliases.clone(),
+            event_loop_samples: state.event_loop_samples.clone(),
+            gc_events: state.gc_events.clone(),
+            state_machine_events: state.state_machine_events.clone(),
+            branch_log: state.branch_log.clone(),
+        }
+    }
+
+    fn into_state(self) -> ExecutionState {
+        ExecutionState {
+            function_calls: self.function_calls,
+            total_execution_time: self.total_execution_time,
+            function_call_history: self.function_call_history,
+            function_call_counts: self.function_call_counts,
+            variable_snapshots: self.variable_snapshots.into(),
+            hot_paths: self.hot_paths,
+            variable_aliases: self.variable_aliases,
+            event_loop_samples: self.event_loop_samples,
+            gc_events: self.gc_events,
+            state_machine_events: self.state_machine_events,
+            branch_log: self.branch_log,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_roundtrip_and_version_check() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec!["1".to_string()], None, None);
+        state.log_function_exit("main".to_string(), 1.5);
+        state.variable_snapshots.push(VariableSnapshot {
+            timestamp: 1.0,
+            function_name: "main".to_string(),
+            call_depth: 1,
+            variables: HashMap::from([
+                ("items".to_string(), JSValue::Map(vec![(JSValue::Number(1.0), JSValue::String("one".to_string()))])),
+            ]),
+            snapshot_type: "exit".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        });
+
+        let path = std::env::temp_dir().join(format!("ttd_state_{}.bin", std::process::id()));
+        StateSerializer::save(&state, &path).unwrap();
+        let loaded = StateSerializer::load(&path).unwrap();
+
+        assert_eq!(loaded.function_calls, 1);
+        assert_eq!(loaded.function_call_history[0].duration_ms, Some(1.5));
+        assert!(loaded.variable_snapshots[0].variables["items"]
+            .structural_eq(&state.variable_snapshots[0].variables["items"]));
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[MAGIC.len()] = FORMAT_VERSION + 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let error = StateSerializer::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains(&format!("version {}", FORMAT_VERSION + 1)), "{}", error);
+    }
+
+    #[test]
+    fn test_v1_snapshot_file_is_migrated() {
+        let snapshot = VariableSnapshotV2 {
+            timestamp: 2.0,
+            function_name: "checkpoint".to_string(),
+            call_depth: 0,
+            variables: HashMap::from([("n".to_string(), JSValue::Number(1.0))]),
+            snapshot_type: "custom".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+        };
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.push(1);
+        bincode::serialize_into(&mut bytes, &snapshot).unwrap();
+        let path = std::env::temp_dir().join(format!("ttd_snapshot_v1_{}.ttd", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let file = SnapshotSerializer::load(&path).unwrap();
+        assert_eq!(file.version, 3);
+        assert_eq!(file.snapshots.len(), 1);
+        assert_eq!(file.snapshots[0].heap_stats, None);
+        assert_eq!(file.snapshots[0].variables["n"], JSValue::Number(1.0));
+
+        SnapshotSerializer::save(&file.snapshots, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[SNAPSHOT_MAGIC.len()], SNAPSHOT_FORMAT_VERSION);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_within_rejects_escapes() {
+        let root = Path::new("/work/project");
+        assert_eq!(resolve_within(root, Path::new("./checkpoints/a.ttd")).unwrap(), root.join("checkpoints/a.ttd"));
+        assert_eq!(resolve_within(root, Path::new("/work/project/x/../a.ttd")).unwrap(), root.join("a.ttd"));
+        assert!(resolve_within(root, Path::new("../secrets.ttd")).is_err());
+        assert!(resolve_within(root, Path::new("/etc/passwd")).is_err());
+        assert!(resolve_within(root, Path::new("a/../../b.ttd")).is_err());
+    }
+
+    #[test]
+    fn test_merge_files_combines_traces() {
+        let paths: Vec<PathBuf> = (0..2)
+            .map(|run| std::env::temp_dir().join(format!("ttd_merge_{}_{}.ttd", std::process::id(), run)))
+            .collect();
+        for (run, path) in paths.iter().enumerate() {
+            let mut state = ExecutionState::default();
+            state.log_function_entry("test".to_string(), vec![run.to_string()], None, None);
+            state.log_branch("test".to_string(), 1, run == 0);
+            StateSerializer::save(&state, path).unwrap();
+        }
+
+        let merged = TraceMerger::merge_files(&paths).unwrap();
+        for path in &paths {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(merged.function_calls, 2);
+        assert_eq!(merged.function_call_counts["test"], 2);
+        assert_eq!(merged.branch_log.iter().map(|record| record.taken).collect::<Vec<_>>(), [true, false]);
+        assert!(TraceMerger::merge_files(&[]).is_err());
+    }
+
+    #[test]
+    fn test_compressed_state_is_detected_on_load() {
+        let mut state = ExecutionState::default();
+        for i in 0..50 {
+            state.log_function_entry(format!("step{}", i % 3), vec![i.to_string()], None, None);
+        }
+
+        let path = std::env::temp_dir().join(format!("ttd_state_{}{}", std::process::id(), COMPRESSED_EXTENSION));
+        StateSerializer::save(&state, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let loaded = StateSerializer::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(bytes.starts_with(COMPRESSED_MAGIC));
+        assert_eq!(loaded.function_call_history.len(), 50);
+        assert_eq!(loaded.function_call_history[49].arguments, ["49"]);
+    }
+}
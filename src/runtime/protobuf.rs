@@ -0,0 +1,337 @@
+use crate::runtime::engine::{ExecutionState, FunctionCall, HeapStats, HotPathRecord, VariableSnapshot};
+use crate::runtime::serialization::{JSValue, PromiseState, TypedArrayKind};
+use anyhow::{Result, anyhow};
+use prost::Message;
+use std::collections::HashMap;
+
+/// Types generated by prost-build from `proto/snapshot.proto`
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/time_travel_debugger.rs"));
+}
+
+use proto::js_value::Kind;
+
+impl From<&JSValue> for proto::JsValue {
+    fn from(value: &JSValue) -> Self {
+        let kind = match value {
+            JSValue::Null => Kind::Null(proto::Empty {}),
+            JSValue::Undefined => Kind::Undefined(proto::Empty {}),
+            JSValue::Boolean(b) => Kind::Boolean(*b),
+            JSValue::Number(n) => Kind::Number(*n),
+            JSValue::String(s) => Kind::String(s.clone()),
+            JSValue::BigInt(s) => Kind::BigInt(s.clone()),
+            JSValue::Symbol(s) => Kind::Symbol(s.clone()),
+            JSValue::Object(obj) => Kind::Object(proto::ObjectValue {
+                properties: obj.iter().map(|(k, v)| (k.clone(), v.into())).collect(),
+            }),
+            JSValue::Array(arr) => Kind::Array(proto::ArrayValue {
+                elements: arr.iter().map(Into::into).collect(),
+            }),
+            JSValue::Function { name, source, location } => Kind::Function(proto::FunctionValue {
+                name: name.clone(),
+                source: source.clone(),
+                location: location.clone(),
+            }),
+            JSValue::Date(iso) => Kind::Date(iso.clone()),
+            JSValue::RegExp { pattern, flags } => Kind::RegExp(proto::RegExpValue {
+                pattern: pattern.clone(),
+                flags: flags.clone(),
+            }),
+            JSValue::Map(entries) => Kind::Map(proto::MapValue {
+                entries: entries.iter().map(|(k, v)| proto::MapEntry {
+                    key: Some(k.into()),
+                    value: Some(v.into()),
+                }).collect(),
+            }),
+            JSValue::Set(elements) => Kind::Set(proto::ArrayValue {
+                elements: elements.iter().map(Into::into).collect(),
+            }),
+            JSValue::Error(msg) => Kind::Error(msg.clone()),
+            JSValue::CircularReference(ref_id) => Kind::CircularReference(ref_id.clone()),
+            JSValue::Uint8ClampedArray(bytes) => Kind::Uint8ClampedArray(bytes.clone()),
+            JSValue::TypedArray { kind, elements, byte_length } => Kind::TypedArray(proto::TypedArrayValue {
+                kind: kind.name().to_string(),
+                elements: elements.clone(),
+                byte_length: *byte_length as u64,
+            }),
+            JSValue::ArrayBuffer { byte_length, data } => Kind::ArrayBuffer(proto::ArrayBufferValue {
+                byte_length: *byte_length as u64,
+                data: data.clone(),
+            }),
+            JSValue::Promise { state, value } => Kind::Promise(Box::new(proto::PromiseValue {
+                state: state.as_str().to_string(),
+                value: value.as_deref().map(|value| Box::new(value.into())),
+            })),
+            JSValue::Generator { done, value } => Kind::Generator(Box::new(proto::GeneratorValue {
+                done: *done,
+                value: Some(Box::new(value.as_ref().into())),
+            })),
+            JSValue::ImageData { width, height, data, preview_png } => Kind::ImageData(proto::ImageDataValue {
+                width: *width,
+                height: *height,
+                data: data.clone(),
+                preview_png: preview_png.clone(),
+            }),
+        };
+
+        proto::JsValue { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<proto::JsValue> for JSValue {
+    type Error = anyhow::Error;
+
+    fn try_from(value: proto::JsValue) -> Result<Self> {
+        let kind = value.kind.ok_or_else(|| anyhow!("JSValue message has no kind set"))?;
+
+        Ok(match kind {
+            Kind::Null(_) => JSValue::Null,
+            Kind::Undefined(_) => JSValue::Undefined,
+            Kind::Boolean(b) => JSValue::Boolean(b),
+            Kind::Number(n) => JSValue::Number(n),
+            Kind::String(s) => JSValue::String(s),
+            Kind::BigInt(s) => JSValue::BigInt(s),
+            Kind::Symbol(s) => JSValue::Symbol(s),
+            Kind::Object(obj) => JSValue::Object(decode_variables(obj.properties)?),
+            Kind::Array(arr) => JSValue::Array(decode_values(arr.elements)?),
+            Kind::Function(f) => JSValue::Function {
+                name: f.name,
+                source: f.source,
+                location: f.location,
+            },
+            Kind::Date(iso) => JSValue::Date(iso),
+            Kind::RegExp(r) => JSValue::RegExp { pattern: r.pattern, flags: r.flags },
+            Kind::Map(map) => {
+                let mut entries = Vec::with_capacity(map.entries.len());
+                for entry in map.entries {
+                    let key = entry.key.ok_or_else(|| anyhow!("Map entry is missing its key"))?;
+                    let value = entry.value.ok_or_else(|| anyhow!("Map entry is missing its value"))?;
+                    entries.push((key.try_into()?, value.try_into()?));
+                }
+                JSValue::Map(entries)
+            },
+            Kind::Set(set) => JSValue::Set(decode_values(set.elements)?),
+            Kind::Error(msg) => JSValue::Error(msg),
+            Kind::CircularReference(ref_id) => JSValue::CircularReference(ref_id),
+            Kind::Uint8ClampedArray(bytes) => JSValue::Uint8ClampedArray(bytes),
+            Kind::TypedArray(array) => JSValue::TypedArray {
+                kind: TypedArrayKind::from_name(&array.kind)
+                    .ok_or_else(|| anyhow!("Unknown typed array kind: {}", array.kind))?,
+                elements: array.elements,
+                byte_length: array.byte_length as usize,
+            },
+            Kind::ArrayBuffer(buffer) => JSValue::ArrayBuffer {
+                byte_length: buffer.byte_length as usize,
+                data: buffer.data,
+            },
+            Kind::Promise(promise) => JSValue::Promise {
+                state: PromiseState::from_name(&promise.state)
+                    .ok_or_else(|| anyhow!("Unknown promise state: {}", promise.state))?,
+                value: promise.value.map(|value| JSValue::try_from(*value).map(Box::new)).transpose()?,
+            },
+            Kind::Generator(generator) => JSValue::Generator {
+                done: generator.done,
+                value: Box::new(match generator.value {
+                    Some(value) => JSValue::try_from(*value)?,
+                    None => JSValue::Undefined,
+                }),
+            },
+            Kind::ImageData(image) => JSValue::ImageData {
+                width: image.width,
+                height: image.height,
+                data: image.data,
+                preview_png: image.preview_png,
+            },
+        })
+    }
+}
+
+fn decode_values(values: Vec<proto::JsValue>) -> Result<Vec<JSValue>> {
+    values.into_iter().map(JSValue::try_from).collect()
+}
+
+fn decode_variables(variables: HashMap<String, proto::JsValue>) -> Result<HashMap<String, JSValue>> {
+    variables.into_iter()
+        .map(|(name, value)| Ok((name, JSValue::try_from(value)?)))
+        .collect()
+}
+
+impl From<&FunctionCall> for proto::FunctionCall {
+    fn from(call: &FunctionCall) -> Self {
+        proto::FunctionCall {
+            name: call.name.clone(),
+            timestamp: call.timestamp,
+            call_depth: call.call_depth as u64,
+            arguments: call.arguments.clone(),
+            arg_values: call.arg_values.iter().map(Into::into).collect(),
+            return_value: call.return_value.as_ref().map(Into::into),
+            file_location: call.file_location.clone(),
+            line_number: call.line_number,
+            duration_ms: call.duration_ms,
+            call_count: call.call_count,
+            trace_id: call.trace_id.clone(),
+        }
+    }
+}
+
+impl TryFrom<proto::FunctionCall> for FunctionCall {
+    type Error = anyhow::Error;
+
+    fn try_from(call: proto::FunctionCall) -> Result<Self> {
+        Ok(FunctionCall {
+            name: call.name,
+            timestamp: call.timestamp,
+            call_depth: call.call_depth as usize,
+            arguments: call.arguments,
+            arg_values: decode_values(call.arg_values)?,
+            return_value: call.return_value.map(JSValue::try_from).transpose()?,
+            file_location: call.file_location,
+            line_number: call.line_number,
+            duration_ms: call.duration_ms,
+            // Absent in data written before calls could be merged
+            call_count: call.call_count.max(1),
+            trace_id: call.trace_id,
+        })
+    }
+}
+
+impl From<&VariableSnapshot> for proto::VariableSnapshot {
+    fn from(snapshot: &VariableSnapshot) -> Self {
+        proto::VariableSnapshot {
+            timestamp: snapshot.timestamp,
+            function_name: snapshot.function_name.clone(),
+            call_depth: snapshot.call_depth as u64,
+            variables: snapshot.variables.iter().map(|(k, v)| (k.clone(), v.into())).collect(),
+            snapshot_type: snapshot.snapshot_type.clone(),
+            metadata: snapshot.metadata.iter().map(|(k, v)| (k.clone(), v.into())).collect(),
+            trace_id: snapshot.trace_id.clone(),
+            heap_stats: snapshot.heap_stats.map(|stats| proto::HeapStats {
+                total_heap_size: stats.total_heap_size as u64,
+                used_heap_size: stats.used_heap_size as u64,
+                heap_size_limit: stats.heap_size_limit as u64,
+                external_memory: stats.external_memory as u64,
+            }),
+        }
+    }
+}
+
+impl TryFrom<proto::VariableSnapshot> for VariableSnapshot {
+    type Error = anyhow::Error;
+
+    fn try_from(snapshot: proto::VariableSnapshot) -> Result<Self> {
+        Ok(VariableSnapshot {
+            timestamp: snapshot.timestamp,
+            function_name: snapshot.function_name,
+            call_depth: snapshot.call_depth as usize,
+            variables: decode_variables(snapshot.variables)?,
+            snapshot_type: snapshot.snapshot_type,
+            metadata: decode_variables(snapshot.metadata)?,
+            trace_id: snapshot.trace_id,
+            heap_stats: snapshot.heap_stats.map(|stats| HeapStats {
+                total_heap_size: stats.total_heap_size as usize,
+                used_heap_size: stats.used_heap_size as usize,
+                heap_size_limit: stats.heap_size_limit as usize,
+                external_memory: stats.external_memory as usize,
+            }),
+        })
+    }
+}
+
+impl From<&HotPathRecord> for proto::HotPathRecord {
+    fn from(record: &HotPathRecord) -> Self {
+        proto::HotPathRecord {
+            label: record.label.clone(),
+            start_ms: record.start_ms,
+            end_ms: record.end_ms,
+            call_count_in_path: record.call_count_in_path,
+            summary_snapshot_index: record.summary_snapshot_index.map(|index| index as u64),
+        }
+    }
+}
+
+impl From<proto::HotPathRecord> for HotPathRecord {
+    fn from(record: proto::HotPathRecord) -> Self {
+        HotPathRecord {
+            label: record.label,
+            start_ms: record.start_ms,
+            end_ms: record.end_ms,
+            call_count_in_path: record.call_count_in_path,
+            summary_snapshot_index: record.summary_snapshot_index.map(|index| index as usize),
+        }
+    }
+}
+
+impl ExecutionState {
+    /// Encode the recorded history as a protobuf `ExecutionState` message
+    pub fn serialize_to_protobuf(&self) -> Result<Vec<u8>> {
+        let message = proto::ExecutionState {
+            function_calls: self.function_calls,
+            total_execution_time_nanos: self.total_execution_time.as_nanos() as u64,
+            current_function: self.current_function.clone(),
+            call_stack_depth: self.call_stack_depth as u64,
+            function_call_history: self.function_call_history.iter().map(Into::into).collect(),
+            function_call_counts: self.function_call_counts.clone(),
+            variable_snapshots: self.variable_snapshots.iter().map(Into::into).collect(),
+            hot_paths: self.hot_paths.iter().map(Into::into).collect(),
+        };
+
+        Ok(message.encode_to_vec())
+    }
+
+    /// Decode a protobuf `ExecutionState` message produced by `serialize_to_protobuf`
+    pub fn deserialize_from_protobuf(bytes: &[u8]) -> Result<ExecutionState> {
+        let message = proto::ExecutionState::decode(bytes)?;
+
+        let variable_snapshots = message.variable_snapshots.into_iter()
+            .map(VariableSnapshot::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ExecutionState {
+            function_calls: message.function_calls,
+            total_execution_time: std::time::Duration::from_nanos(message.total_execution_time_nanos),
+            current_function: message.current_function,
+            call_stack_depth: message.call_stack_depth as usize,
+            function_call_history: message.function_call_history.into_iter().map(FunctionCall::try_from).collect::<Result<_>>()?,
+            function_call_counts: message.function_call_counts,
+            variable_snapshots: variable_snapshots.into(),
+            hot_paths: message.hot_paths.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        let mut state = ExecutionState {
+            function_calls: 1,
+            ..Default::default()
+        };
+        state.function_call_counts.insert("main".to_string(), 1);
+        state.variable_snapshots.push(VariableSnapshot {
+            timestamp: 1.5,
+            function_name: "main".to_string(),
+            call_depth: 1,
+            variables: HashMap::from([
+                ("n".to_string(), JSValue::Number(42.0)),
+                ("m".to_string(), JSValue::Map(vec![(JSValue::String("k".to_string()), JSValue::Null)])),
+            ]),
+            snapshot_type: "entry".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        });
+
+        let bytes = state.serialize_to_protobuf().unwrap();
+        let decoded = ExecutionState::deserialize_from_protobuf(&bytes).unwrap();
+
+        assert_eq!(decoded.function_calls, 1);
+        assert_eq!(decoded.function_call_counts.get("main"), Some(&1));
+        assert_eq!(decoded.variable_snapshots.len(), 1);
+        assert_eq!(decoded.variable_snapshots[0].variables["n"].to_display_string(), "42");
+        assert_eq!(decoded.variable_snapshots[0].variables["m"].to_display_string(), "Map(1 entries)");
+    }
+}
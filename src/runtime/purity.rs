@@ -0,0 +1,124 @@
+use crate::runtime::engine::{ExecutionState, FunctionCall};
+use crate::runtime::serialization::JSValue;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Verdict of `PurityAnalyzer` for one function
+#[derive(Debug, Clone, PartialEq)]
+pub enum PurityResult {
+    /// Every repeated argument list returned structurally equal values
+    Pure,
+    /// Why the function is not pure: the same arguments returned different values
+    Impure(String),
+    /// Called once, or never twice with the same arguments
+    Insufficient,
+}
+
+impl fmt::Display for PurityResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurityResult::Pure => write!(f, "pure (memoization candidate)"),
+            PurityResult::Impure(reason) => write!(f, "impure: {}", reason),
+            PurityResult::Insufficient => write!(f, "not enough calls to tell"),
+        }
+    }
+}
+
+/// Finds functions that always returned the same value for the same arguments, judged
+/// from the recorded calls. Calls that never returned (e.g. threw) are ignored, and an
+/// unrecorded return value counts as `undefined`.
+pub struct PurityAnalyzer;
+
+impl PurityAnalyzer {
+    pub fn analyze(execution_state: &ExecutionState) -> HashMap<String, PurityResult> {
+        let mut calls_by_function: HashMap<&str, Vec<&FunctionCall>> = HashMap::new();
+        for call in execution_state.function_call_history.iter().filter(|call| call.duration_ms.is_some()) {
+            calls_by_function.entry(&call.name).or_default().push(call);
+        }
+
+        calls_by_function.into_iter()
+            .map(|(name, calls)| (name.to_string(), Self::analyze_function(name, &calls)))
+            .collect()
+    }
+
+    fn analyze_function(name: &str, calls: &[&FunctionCall]) -> PurityResult {
+        let total_calls: u32 = calls.iter().map(|call| call.call_count).sum();
+        if total_calls < 2 {
+            return PurityResult::Insufficient;
+        }
+
+        let undefined = JSValue::Undefined;
+        let mut first_return: HashMap<u64, (&FunctionCall, &JSValue)> = HashMap::new();
+        let mut repeated = calls.iter().any(|call| call.call_count > 1);
+        for call in calls {
+            let returned = call.return_value.as_ref().unwrap_or(&undefined);
+            match first_return.get(&argument_key(call)) {
+                Some((first, expected)) => {
+                    repeated = true;
+                    if !expected.structural_eq(returned) {
+                        return PurityResult::Impure(format!("{}({}) returned {}, then {}",
+                            name, first.arguments.join(", "), expected.to_display_string(), returned.to_display_string()));
+                    }
+                },
+                None => { first_return.insert(argument_key(call), (call, returned)); },
+            }
+        }
+
+        if repeated { PurityResult::Pure } else { PurityResult::Insufficient }
+    }
+
+    /// One line per function, sorted by name
+    pub fn summary(results: &HashMap<String, PurityResult>) -> String {
+        let mut names: Vec<&String> = results.keys().collect();
+        names.sort();
+        let mut summary = String::from("🧪 FUNCTION PURITY:\n");
+        for name in names {
+            summary.push_str(&format!("  {} → {}\n", name, results[name]));
+        }
+        summary
+    }
+}
+
+/// Identity of a call's argument list: the serialized values when they were captured,
+/// otherwise their display form
+fn argument_key(call: &FunctionCall) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if call.arg_values.is_empty() {
+        call.arguments.hash(&mut hasher);
+    } else {
+        call.arg_values.len().hash(&mut hasher);
+        call.arg_values.iter().for_each(|value| value.content_hash(&mut hasher));
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purity_verdicts() {
+        let mut state = ExecutionState::default();
+        let mut call = |name: &str, arg: f64, result: f64| {
+            state.log_function_entry_with_values(name.to_string(), vec![JSValue::Number(arg)], None, None);
+            state.set_return_value(name, JSValue::Number(result));
+            state.log_function_exit(name.to_string(), 1.0);
+        };
+        call("square", 3.0, 9.0);
+        call("square", 4.0, 16.0);
+        call("square", 3.0, 9.0);
+        call("nextId", 0.0, 1.0);
+        call("nextId", 0.0, 2.0);
+        call("main", 0.0, 0.0);
+        call("double", 1.0, 2.0);
+        call("double", 2.0, 4.0);
+
+        let results = PurityAnalyzer::analyze(&state);
+        assert_eq!(results["square"], PurityResult::Pure);
+        assert_eq!(results["nextId"], PurityResult::Impure("nextId(0) returned 1, then 2".to_string()));
+        assert_eq!(results["main"], PurityResult::Insufficient);
+        assert_eq!(results["double"], PurityResult::Insufficient);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::runtime::engine::{ExecutionState, VariableSnapshot};
+use anyhow::{Result, anyhow};
+use deno_core::serde_json;
+
+/// Chainable filter over `ExecutionState::variable_snapshots`. Every condition set must
+/// hold; the snapshots are scanned once when the query runs.
+#[derive(Debug, Clone)]
+pub struct SnapshotQuery<'a> {
+    state: &'a ExecutionState,
+    function_name: Option<String>,
+    call_depth: Option<usize>,
+    snapshot_type: Option<String>,
+    after_timestamp: Option<f64>,
+    before_timestamp: Option<f64>,
+}
+
+impl<'a> SnapshotQuery<'a> {
+    pub fn new(state: &'a ExecutionState) -> Self {
+        Self {
+            state,
+            function_name: None,
+            call_depth: None,
+            snapshot_type: None,
+            after_timestamp: None,
+            before_timestamp: None,
+        }
+    }
+
+    pub fn in_function(&mut self, name: &str) -> &mut Self {
+        self.function_name = Some(name.to_string());
+        self
+    }
+
+    pub fn at_depth(&mut self, depth: usize) -> &mut Self {
+        self.call_depth = Some(depth);
+        self
+    }
+
+    pub fn of_type(&mut self, snapshot_type: &str) -> &mut Self {
+        self.snapshot_type = Some(snapshot_type.to_string());
+        self
+    }
+
+    /// Snapshots taken strictly after `timestamp` (seconds since the epoch)
+    pub fn after_timestamp(&mut self, timestamp: f64) -> &mut Self {
+        self.after_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Snapshots taken strictly before `timestamp` (seconds since the epoch)
+    pub fn before_timestamp(&mut self, timestamp: f64) -> &mut Self {
+        self.before_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Set conditions from a JSON object with any of `function`, `depth`, `type`,
+    /// `after` and `before`, as sent by `timeDebugger.querySnapshots`
+    pub fn apply_filter(&mut self, filter: &serde_json::Value) -> Result<&mut Self> {
+        let Some(filter) = filter.as_object() else {
+            return Err(anyhow!("Snapshot filter must be an object"));
+        };
+
+        for (key, value) in filter {
+            let invalid = || anyhow!("Invalid snapshot filter value for {}: {}", key, value);
+            match key.as_str() {
+                "function" => { self.in_function(value.as_str().ok_or_else(invalid)?); },
+                "depth" => { self.at_depth(value.as_u64().ok_or_else(invalid)? as usize); },
+                "type" => { self.of_type(value.as_str().ok_or_else(invalid)?); },
+                "after" => { self.after_timestamp(value.as_f64().ok_or_else(invalid)?); },
+                "before" => { self.before_timestamp(value.as_f64().ok_or_else(invalid)?); },
+                _ => return Err(anyhow!("Unknown snapshot filter: {} (expected function, depth, type, after or before)", key)),
+            }
+        }
+        Ok(self)
+    }
+
+    fn matches(&self, snapshot: &VariableSnapshot) -> bool {
+        self.function_name.as_ref().is_none_or(|name| snapshot.function_name == *name)
+            && self.call_depth.is_none_or(|depth| snapshot.call_depth == depth)
+            && self.snapshot_type.as_ref().is_none_or(|kind| snapshot.snapshot_type == *kind)
+            && self.after_timestamp.is_none_or(|after| snapshot.timestamp > after)
+            && self.before_timestamp.is_none_or(|before| snapshot.timestamp < before)
+    }
+
+    fn matching(&self) -> impl DoubleEndedIterator<Item = (usize, &'a VariableSnapshot)> + '_ {
+        self.state.variable_snapshots.iter().enumerate().filter(|(_, snapshot)| self.matches(snapshot))
+    }
+
+    pub fn collect(&self) -> Vec<&'a VariableSnapshot> {
+        self.matching().map(|(_, snapshot)| snapshot).collect()
+    }
+
+    /// Matching snapshots paired with their index in `variable_snapshots`
+    pub fn collect_indexed(&self) -> Vec<(usize, &'a VariableSnapshot)> {
+        self.matching().collect()
+    }
+
+    pub fn first(&self) -> Option<&'a VariableSnapshot> {
+        self.matching().next().map(|(_, snapshot)| snapshot)
+    }
+
+    pub fn last(&self) -> Option<&'a VariableSnapshot> {
+        self.matching().next_back().map(|(_, snapshot)| snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_query_chains_filters() {
+        let mut state = ExecutionState::default();
+        for (timestamp, name, depth, kind) in [(1.0, "foo", 2, "entry"), (2.0, "bar", 2, "entry"), (3.0, "foo", 1, "entry"),
+                                               (4.0, "foo", 2, "exit"), (5.0, "foo", 2, "entry")] {
+            state.variable_snapshots.push(VariableSnapshot {
+                timestamp,
+                function_name: name.to_string(),
+                call_depth: depth,
+                variables: HashMap::new(),
+                snapshot_type: kind.to_string(),
+                metadata: HashMap::new(),
+                trace_id: None,
+                heap_stats: None,
+            });
+        }
+
+        let mut query = SnapshotQuery::new(&state);
+        query.in_function("foo").at_depth(2).of_type("entry");
+        let timestamps: Vec<f64> = query.collect().iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, [1.0, 5.0]);
+        assert_eq!(query.after_timestamp(1.0).first().map(|s| s.timestamp), Some(5.0));
+
+        let mut query = SnapshotQuery::new(&state);
+        query.apply_filter(&serde_json::json!({ "function": "foo", "before": 4.5 })).unwrap();
+        assert_eq!(query.last().map(|s| s.timestamp), Some(4.0));
+        assert!(query.apply_filter(&serde_json::json!({ "name": "foo" })).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::runtime::engine::{ExecutionState, VariableSnapshot};
+use anyhow::{Result, anyhow};
+use std::rc::Rc;
+
+/// Cursor over the snapshots of a finished execution. Cloning shares the recorded
+/// state, so a clone can wander off on its own without affecting the original.
+#[derive(Debug, Clone)]
+pub struct ReplaySession {
+    state: Rc<ExecutionState>,
+    cursor: usize,
+}
+
+impl ReplaySession {
+    pub fn new(state: ExecutionState) -> Self {
+        Self::from_shared(Rc::new(state))
+    }
+
+    pub fn from_shared(state: Rc<ExecutionState>) -> Self {
+        Self { state, cursor: 0 }
+    }
+
+    pub fn state(&self) -> &ExecutionState {
+        &self.state
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.variable_snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn current_snapshot(&self) -> Option<&VariableSnapshot> {
+        self.state.variable_snapshots.get(self.cursor)
+    }
+
+    /// Move to the next snapshot, staying on the last one at the end
+    pub fn step_forward(&mut self) -> Option<&VariableSnapshot> {
+        self.cursor = (self.cursor + 1).min(self.len().saturating_sub(1));
+        self.current_snapshot()
+    }
+
+    /// Move to the previous snapshot, staying on the first one at the start
+    pub fn step_backward(&mut self) -> Option<&VariableSnapshot> {
+        self.cursor = self.cursor.saturating_sub(1);
+        self.current_snapshot()
+    }
+
+    pub fn jump_to_index(&mut self, index: usize) -> Result<&VariableSnapshot> {
+        if index >= self.len() {
+            return Err(anyhow!("Snapshot index {} out of range ({} snapshots)", index, self.len()));
+        }
+        self.cursor = index;
+        Ok(&self.state.variable_snapshots[index])
+    }
+
+    /// The current snapshot with differential captures resolved against their baselines
+    pub fn current_variables(&self) -> Option<VariableSnapshot> {
+        self.state.reconstruct_snapshot(self.cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn session(function_names: &[&str]) -> ReplaySession {
+        let mut state = ExecutionState::default();
+        for name in function_names {
+            state.variable_snapshots.push(VariableSnapshot {
+                timestamp: 0.0,
+                function_name: name.to_string(),
+                call_depth: 0,
+                variables: HashMap::new(),
+                snapshot_type: "custom".to_string(),
+                metadata: HashMap::new(),
+                trace_id: None,
+                heap_stats: None,
+            });
+        }
+        ReplaySession::new(state)
+    }
+
+    #[test]
+    fn test_replay_cursor_saturates() {
+        let mut replay = session(&["a", "b", "c"]);
+        assert_eq!(replay.step_backward().unwrap().function_name, "a");
+        assert_eq!(replay.step_forward().unwrap().function_name, "b");
+        assert_eq!(replay.step_forward().unwrap().function_name, "c");
+        assert_eq!(replay.step_forward().unwrap().function_name, "c");
+
+        // A fork keeps its own cursor
+        let mut fork = replay.clone();
+        fork.jump_to_index(0).unwrap();
+        assert_eq!(replay.cursor(), 2);
+        assert!(fork.jump_to_index(3).is_err());
+
+        let mut empty = session(&[]);
+        assert!(empty.step_forward().is_none());
+        assert!(empty.step_backward().is_none());
+    }
+}
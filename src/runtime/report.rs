@@ -0,0 +1,160 @@
+use crate::runtime::engine::ExecutionState;
+use crate::runtime::serialization::{JSValue, PrettyPrintConfig};
+use anyhow::Result;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Snapshots shown in full in the report
+const REPORT_TOP_SNAPSHOTS: usize = 10;
+/// Calls listed in the report timeline
+const REPORT_TIMELINE_CALLS: usize = 50;
+/// GC overhead above this percentage is reported as an anomaly
+const GC_OVERHEAD_ANOMALY_PCT: f64 = 10.0;
+
+impl ExecutionState {
+    /// Write a markdown bug report (summary, statistics, largest snapshots, timeline)
+    pub fn export_markdown_report(&self, path: &Path, title: &str) -> Result<()> {
+        std::fs::write(path, self.render_markdown_report(title))?;
+        Ok(())
+    }
+
+    /// Render the report written by `export_markdown_report`. Only plain CommonMark
+    /// tables, lists and fenced code blocks are used so it renders on any markdown host.
+    pub fn render_markdown_report(&self, title: &str) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "# {}\n", title);
+
+        report.push_str("## Summary\n\n| Metric | Value |\n| --- | --- |\n");
+        let max_depth = self.function_call_history.iter().map(|c| c.call_depth).max().unwrap_or(0);
+        for (metric, value) in [
+            ("Function calls", self.function_calls.to_string()),
+            ("Unique functions", self.function_call_counts.len().to_string()),
+            ("Max call depth", max_depth.to_string()),
+            ("Variable snapshots", self.variable_snapshots.len().to_string()),
+            ("Execution time", format!("{:.3}ms", self.total_execution_time.as_secs_f64() * 1000.0)),
+            ("GC time", format!("{:.3}ms", self.total_gc_time_ms())),
+        ] {
+            let _ = writeln!(report, "| {} | {} |", metric, value);
+        }
+
+        report.push_str("\n## Function Call Statistics\n\n");
+        if self.function_call_counts.is_empty() {
+            report.push_str("_No function calls were traced._\n");
+        } else {
+            report.push_str("| Function | Calls | Total time (ms) |\n| --- | ---: | ---: |\n");
+            let mut counts: Vec<_> = self.function_call_counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (name, count) in counts {
+                let total_ms: f64 = self.function_call_history.iter()
+                    .filter(|call| &call.name == name)
+                    .filter_map(|call| call.duration_ms)
+                    .sum();
+                let _ = writeln!(report, "| `{}` | {} | {:.3} |", escape_table_cell(name), count, total_ms);
+            }
+        }
+
+        report.push_str("\n## Top 10 Snapshots\n\n");
+        if self.variable_snapshots.is_empty() {
+            report.push_str("_No snapshots were captured._\n");
+        }
+        let pretty = PrettyPrintConfig { max_line_width: 100, ..Default::default() };
+        for (index, size) in self.top_n_snapshots_by_size(REPORT_TOP_SNAPSHOTS) {
+            let snapshot = &self.variable_snapshots[index];
+            let _ = writeln!(report, "### #{} `{}` [{}] ({} bytes)\n",
+                index, snapshot.function_name, snapshot.snapshot_type, size);
+
+            let mut names: Vec<&String> = snapshot.variables.keys().collect();
+            names.sort();
+            report.push_str("```js\n");
+            for name in names {
+                let _ = writeln!(report, "{} = {}", self.display_name(name), snapshot.variables[name].pretty_print(&pretty));
+            }
+            report.push_str("```\n\n");
+        }
+
+        report.push_str("\n## Call Timeline\n\n");
+        if self.function_call_history.is_empty() {
+            report.push_str("_No calls were recorded._\n");
+        }
+        for (i, call) in self.function_call_history.iter().enumerate().take(REPORT_TIMELINE_CALLS) {
+            let duration = call.duration_ms.map(|ms| format!(" ({:.3}ms)", ms)).unwrap_or_default();
+            let _ = writeln!(report, "{}. {}`{}({})`{}", i + 1,
+                "↳ ".repeat(call.call_depth.saturating_sub(1)), call.name, call.arguments.join(", "), duration);
+        }
+        if self.function_call_history.len() > REPORT_TIMELINE_CALLS {
+            let _ = writeln!(report, "\n_... and {} more calls_",
+                self.function_call_history.len() - REPORT_TIMELINE_CALLS);
+        }
+
+        let anomalies = self.report_anomalies();
+        if !anomalies.is_empty() {
+            report.push_str("\n## Anomalies\n\n");
+            for anomaly in anomalies {
+                let _ = writeln!(report, "- {}", anomaly);
+            }
+        }
+
+        report.push_str("\n## Environment\n\n| Key | Value |\n| --- | --- |\n");
+        for (key, value) in [
+            ("Execution fingerprint", format!("`{:032x}`", self.execution_fingerprint())),
+            ("Debugger version", env!("CARGO_PKG_VERSION").to_string()),
+            ("Platform", format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)),
+            ("Generated", chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+        ] {
+            let _ = writeln!(report, "| {} | {} |", key, value);
+        }
+
+        report
+    }
+
+    /// Notable problems in the recording: thrown errors, unfinished calls and GC pressure
+    fn report_anomalies(&self) -> Vec<String> {
+        let mut anomalies = Vec::new();
+
+        for (index, snapshot) in self.variable_snapshots.iter().enumerate() {
+            if let Some(JSValue::String(message) | JSValue::Error(message)) = snapshot.variables.get("error") {
+                anomalies.push(format!("Snapshot #{} in `{}` captured an error: {}",
+                    index, snapshot.function_name, message));
+            }
+        }
+
+        let unfinished = self.function_call_history.iter().filter(|call| call.duration_ms.is_none()).count();
+        if unfinished > 0 {
+            anomalies.push(format!("{} call(s) never recorded an exit", unfinished));
+        }
+
+        if self.gc_overhead_pct() > GC_OVERHEAD_ANOMALY_PCT {
+            anomalies.push(format!("Garbage collection took {:.1}% of execution time", self.gc_overhead_pct()));
+        }
+
+        anomalies
+    }
+}
+
+/// `|` would end a table cell early
+fn escape_table_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_report_sections() {
+        let mut state = ExecutionState::default();
+        state.log_function_entry("main".to_string(), vec![], None, None);
+        state.log_function_exit("main".to_string(), 2.0);
+        state.log_function_entry("crash".to_string(), vec!["1".to_string()], None, None);
+
+        let report = state.render_markdown_report("Bug report");
+
+        assert!(report.starts_with("# Bug report\n"));
+        for section in ["## Summary", "## Function Call Statistics", "## Top 10 Snapshots",
+            "## Call Timeline", "## Anomalies", "## Environment"] {
+            assert!(report.contains(section), "missing {}", section);
+        }
+        assert!(report.contains("1. `main()` (2.000ms)"));
+        assert!(report.contains("1 call(s) never recorded an exit"));
+    }
+}
@@ -22,6 +22,15 @@
     }
   };
 
+  // Set while a traced built-in call is being recorded
+  let tracingMethodCall = false;
+  // Errors already snapshotted, so one error rethrown through several wrapped frames is logged once
+  const loggedExceptions = new WeakSet();
+  // Next iteration number for each loop id passed to trackLoop without one
+  const loopIterations = new Map();
+  // Set once tracePromises has installed its hooks
+  let tracingPromises = false;
+
   // Time travel debugger API
   const timeDebugger = {
     logFunctionCall(functionName) {
@@ -30,11 +39,35 @@
     getTimestamp() {
       return core.ops.op_get_timestamp();
     },
-    functionEntry(name) {
-      core.ops.op_function_entry(name);
+    // Pass `args` to record the argument values themselves, not just the call
+    functionEntry(name, args) {
+      if (args === undefined) {
+        core.ops.op_function_entry(name);
+      } else {
+        core.ops.op_function_entry_with_args(name, Array.from(args));
+      }
+    },
+    functionExit(name, durationMs, returnValue) {
+      core.ops.op_function_exit(name, durationMs, returnValue);
+    },
+    // Throw on calls nested deeper than `depth`; 0 removes the limit
+    setMaxDepth(depth) {
+      core.ops.op_set_max_depth(depth >>> 0);
+    },
+    // Snapshot a thrown value with its name, message and stack
+    logException(error) {
+      const isObject = (typeof error === 'object' && error !== null) || typeof error === 'function';
+      if (isObject) {
+        if (loggedExceptions.has(error)) {
+          return;
+        }
+        loggedExceptions.add(error);
+      }
+      core.ops.op_log_exception(error);
     },
-    functionExit(name, durationMs) {
-      core.ops.op_function_exit(name, durationMs);
+    // Record `value` as the return value of the latest call to `functionName`
+    captureReturnValue(functionName, value) {
+      return core.ops.op_capture_return_value(functionName, value);
     },
     captureContext(contextType, data) {
       core.ops.op_capture_execution_context(contextType, data);
@@ -48,6 +81,10 @@
         return null;
       }
     },
+    // JSON form of a value as the snapshot serializer sees it
+    serialize(value) {
+      return core.ops.op_serialize_js_value(value);
+    },
     captureScope(functionName, snapshotType, scopeObject) {
       try {
         return core.ops.op_capture_scope(functionName, snapshotType, scopeObject || {});
@@ -56,6 +93,72 @@
         return 0;
       }
     },
+    // Snapshot the variables `fn` closes over as a 'closure' snapshot; returns how many.
+    // V8 hides closure scopes, so names are guessed from fn's source and read from
+    // `scopeObject` (globals if omitted): captureClosure(callback, { i, handlers })
+    captureClosure(fn, scopeObject) {
+      return core.ops.op_capture_closure(fn, scopeObject);
+    },
+    // Snapshot everything stored on globalThis (minus built-ins) as a 'global' snapshot;
+    // returns how many bindings were captured
+    captureGlobalState() {
+      return core.ops.op_capture_global_state();
+    },
+    // Snapshot a module's exports as a 'module_scope' snapshot; resolves to how many.
+    // A module capturing itself passes import.meta.url, and must not await this at
+    // top level (the import would wait on the module's own evaluation)
+    async captureModuleScope(specifier) {
+      const url = core.ops.op_resolve_module_specifier(specifier);
+      // Not one of the script's own imports, so keep it out of the import timeline
+      core.ops.op_ignore_next_import(url);
+      const namespace = await import(url);
+      return core.ops.op_capture_module_scope(url, namespace);
+    },
+    // Snapshot the globals under `label` to mark a milestone, e.g. checkpoint('after-init')
+    checkpoint(label) {
+      try {
+        return core.ops.op_checkpoint(String(label));
+      } catch (e) {
+        console.error(`Failed to capture checkpoint ${label}:`, e);
+        return 0;
+      }
+    },
+    // Capture only when `condition` holds, e.g. captureIf(i % 100 === 0, { i, acc })
+    captureIf(condition, scopeObject, functionName, snapshotType) {
+      try {
+        return core.ops.op_capture_scope_if(Boolean(condition), functionName || '', snapshotType || 'custom', scopeObject || {});
+      } catch (e) {
+        console.error('Failed to capture conditional scope:', e);
+        return 0;
+      }
+    },
+    // Capture only what changed since snapshot `baselineIndex`
+    captureDiff(scopeObject, baselineIndex, functionName) {
+      try {
+        return core.ops.op_capture_scope_diff(functionName || '', scopeObject || {}, baselineIndex);
+      } catch (e) {
+        console.error(`Failed to capture diff against snapshot ${baselineIndex}:`, e);
+        return 0;
+      }
+    },
+    // In a forked runtime, the snapshot state to restore ({ function, type, variables })
+    replayInject() {
+      try {
+        return core.ops.op_replay_inject();
+      } catch (e) {
+        console.error('Failed to read replay injection:', e);
+        return null;
+      }
+    },
+    // Field-level changes between two snapshots: { added, removed, changed }
+    diffSnapshots(aIndex, bIndex) {
+      try {
+        return core.ops.op_diff_snapshots(aIndex, bIndex);
+      } catch (e) {
+        console.error(`Failed to diff snapshots ${aIndex} and ${bIndex}:`, e);
+        return null;
+      }
+    },
     getSnapshotInfo() {
       try {
         return core.ops.op_get_snapshot_info();
@@ -64,6 +167,190 @@
         return null;
       }
     },
+    // Resolve bundled call locations through a V3 source map (JSON string or object)
+    registerSourceMap(map) {
+      core.ops.op_register_source_map(typeof map === 'string' ? map : JSON.stringify(map));
+    },
+    // Tag calls and snapshots with `traceId` until endAsyncTrace, across awaits
+    beginAsyncTrace(traceId) {
+      core.ops.op_begin_async_trace(String(traceId));
+    },
+    endAsyncTrace(traceId) {
+      core.ops.op_end_async_trace(String(traceId));
+    },
+    // ID stamped on calls and snapshots right now (null if none); pass it to worker
+    // processes as --trace-id to correlate their recordings with this one
+    get traceId() {
+      return core.ops.op_get_trace_id() || null;
+    },
+    set traceId(traceId) {
+      core.ops.op_set_trace_id(traceId == null ? '' : String(traceId));
+    },
+    // Instrumented frames, innermost first: [{ name, depth, timestamp, location, line }]
+    getCallStack() {
+      return core.ops.op_get_call_stack();
+    },
+    // The most called functions so far as [{ name, count }], most calls first
+    getHotFunctions(n = 10) {
+      return core.ops.op_get_hot_functions(n >>> 0);
+    },
+    // Runs of more than `threshold` consecutive calls with identical arguments
+    checkRepeatedCalls(threshold) {
+      return core.ops.op_check_repeated_calls(threshold >>> 0);
+    },
+    // Set global `name` to `variableName` as captured in snapshot `snapshotIndex`
+    injectValue(name, snapshotIndex, variableName) {
+      core.ops.op_inject_value(name, snapshotIndex, variableName);
+    },
+    // Save snapshot `snapshotIndex` to `<dir>/<label>.ttd`, within the working directory
+    saveSnapshot(label, snapshotIndex, dir = '.') {
+      core.ops.op_save_snapshot(label, snapshotIndex, dir);
+    },
+    // Append the snapshots in a file saved by saveSnapshot to the recording; returns the first index
+    loadSnapshot(path) {
+      return core.ops.op_load_snapshot(path);
+    },
+    // Compare `value` with the one saved under `label` in .snapshots/, saving it on first use
+    assertSnapshot(label, value) {
+      core.ops.op_assert_snapshot(label, value);
+    },
+    // Every captured value of `name` as { timestamp, value, type, typeChanged }
+    getVariableTimeline(name) {
+      return core.ops.op_get_variable_timeline(name);
+    },
+    // Snapshots matching { function, depth, type, after, before }, each with its index
+    querySnapshots(filter) {
+      return core.ops.op_query_snapshots(filter ?? {});
+    },
+    // Snapshot again whenever a capture shows `name` with a new value
+    watch(name) {
+      core.ops.op_add_watchpoint(name);
+    },
+    // Record every write to `object[propertyName]`; see getPropertyHistory
+    watchProperty(object, propertyName) {
+      core.ops.op_watch_property(object, String(propertyName));
+    },
+    // Writes to watched properties with this name: [{ objectRef, oldValue, newValue, timestamp, ... }]
+    getPropertyHistory(propertyName) {
+      return core.ops.op_get_property_history(String(propertyName));
+    },
+    // Record captures of `minified` under a readable name
+    alias(minified, readable) {
+      core.ops.op_set_scope_alias(minified, readable);
+    },
+    // Record a state machine transition for diagramming and validation
+    transition(machine, from, to, event) {
+      core.ops.op_log_state_machine_transition(String(machine), String(from), String(to), String(event ?? ''));
+    },
+    // Record which way a branch went and pass the condition through:
+    // if (timeDebugger.trackBranch('parse', 1, token === '(')) { ... }
+    trackBranch(fnName, branchId, condition) {
+      const taken = Boolean(condition);
+      core.ops.op_track_branch(String(fnName), branchId >>> 0, taken);
+      return taken;
+    },
+    // Record one pass through a loop body: for (...) { timeDebugger.trackLoop(1); ... }
+    // Iterations are numbered per id unless given, e.g. trackLoop(1, i) to mark restarts
+    trackLoop(loopId, iteration) {
+      const id = loopId >>> 0;
+      const current = iteration ?? loopIterations.get(id) ?? 0;
+      loopIterations.set(id, current + 1);
+      core.ops.op_track_loop_iteration(id, current >>> 0);
+    },
+    // Record every promise's creation, chaining and settlement (see ExecutionState::promise_events)
+    tracePromises() {
+      if (tracingPromises) {
+        return;
+      }
+      tracingPromises = true;
+      const queueMicrotask = globalThis.queueMicrotask;
+      core.setPromiseHooks(
+        (promise, parent) => core.ops.op_promise_created(promise, parent),
+        null,
+        null,
+        // The resolve hook runs before the state changes, so read it a microtask later
+        (promise) => queueMicrotask(() => {
+          const [state, result] = core.getPromiseDetails(promise);
+          if (state === 1) {
+            core.ops.op_promise_resolved(promise, result);
+          } else if (state === 2) {
+            core.ops.op_promise_rejected(promise, result);
+          }
+        }),
+      );
+    },
+    // Trace every call to a built-in method given by path, e.g. 'Map.prototype.set'
+    traceMethod(path) {
+      const parts = path.split('.');
+      const methodName = parts.pop();
+      const owner = parts.reduce((object, key) => object?.[key], globalThis);
+      const descriptor = owner && Object.getOwnPropertyDescriptor(owner, methodName);
+      if (!descriptor || typeof descriptor.value !== 'function') {
+        throw new Error(`Cannot trace ${path}: not a method`);
+      }
+
+      const original = descriptor.value;
+      // Method syntax keeps the name and, like built-ins, isn't constructible
+      const wrapper = {
+        [methodName](...args) {
+          // Calls made while recording (e.g. by the debugger itself) are not traced
+          if (tracingMethodCall) {
+            return original.apply(this, args);
+          }
+          tracingMethodCall = true;
+          try {
+            core.ops.op_function_entry(path);
+            core.ops.op_capture_variable('__this__', this);
+          } finally {
+            tracingMethodCall = false;
+          }
+
+          const startTime = core.ops.op_get_timestamp();
+          try {
+            return original.apply(this, args);
+          } finally {
+            core.ops.op_function_exit(path, (core.ops.op_get_timestamp() - startTime) * 1000, undefined);
+          }
+        }
+      }[methodName];
+
+      Object.defineProperty(wrapper, 'length', { value: original.length });
+      Object.defineProperty(wrapper, 'toString', {
+        value: () => Function.prototype.toString.call(original),
+      });
+      Object.defineProperty(owner, methodName, { ...descriptor, value: wrapper });
+    },
+    // Run fn inside a hot path: calls are counted but not captured
+    hot(label, fn) {
+      if (typeof fn !== 'function') {
+        throw new Error('Second argument must be a function');
+      }
+
+      return function(...args) {
+        core.ops.op_mark_hot_path_start(label);
+        try {
+          return fn.apply(this, args);
+        } finally {
+          core.ops.op_mark_hot_path_end(label);
+        }
+      };
+    },
+    getEventLoopStats() {
+      try {
+        return core.ops.op_get_event_loop_stats();
+      } catch (e) {
+        console.error('Failed to get event loop stats:', e);
+        return null;
+      }
+    },
+    getGcEvents() {
+      try {
+        return core.ops.op_trace_gc_events();
+      } catch (e) {
+        console.error('Failed to get GC events:', e);
+        return null;
+      }
+    },
     // Enhanced function monitoring with variable capture
     captureFunction(fn, name) {
       if (typeof fn !== 'function') {
@@ -74,7 +361,7 @@
       
       return function(...args) {
         // Capture function entry with arguments
-        timeDebugger.functionEntry(functionName);
+        timeDebugger.functionEntry(functionName, args);
         
         // Capture arguments as variables
         const argScope = {};
@@ -88,20 +375,16 @@
         let error;
         
         try {
+          // functionExit records the return value on the call
           result = fn.apply(this, args);
-          
-          // Capture return value
-          if (result !== undefined) {
-            timeDebugger.captureVariable('return', result);
-          }
-          
         } catch (e) {
           error = e;
           timeDebugger.captureVariable('error', e.toString());
+          timeDebugger.logException(e);
         }
         
         const duration = (timeDebugger.getTimestamp() - startTime) * 1000;
-        timeDebugger.functionExit(functionName, duration);
+        timeDebugger.functionExit(functionName, duration, result);
         
         if (error) {
           throw error;
@@ -112,9 +395,44 @@
     }
   };
 
+  // Unhandled rejections are snapshotted, then left to the default handling
+  core.setUnhandledPromiseRejectionHandler((_promise, reason) => {
+    timeDebugger.logException(reason);
+    return false;
+  });
+
+  // User Timing: marks and measures are recorded in the execution state
+  const performance = {
+    timeOrigin: Date.now() - core.ops.op_performance_now(),
+    now() {
+      return core.ops.op_performance_now();
+    },
+    mark(name, options) {
+      const startTime = options?.startTime ?? core.ops.op_performance_now();
+      core.ops.op_performance_mark(String(name), startTime);
+      return { name: String(name), entryType: 'mark', startTime, duration: 0, detail: options?.detail ?? null };
+    },
+    // measure(name), measure(name, startMark, endMark) or measure(name, { start, end })
+    measure(name, startOrOptions, endMark) {
+      const options = typeof startOrOptions === 'object' && startOrOptions !== null ? startOrOptions : null;
+      const start = options ? options.start : startOrOptions;
+      const end = options ? options.end : endMark;
+      if (typeof start === 'number' || typeof end === 'number') {
+        throw new TypeError('performance.measure only supports mark names for start and end');
+      }
+      const entry = core.ops.op_performance_measure(String(name), start ?? '', end ?? '');
+      return { ...entry, detail: options?.detail ?? null };
+    },
+  };
+
   // Make APIs globally available
   globalThis.console = console;
   globalThis.timeDebugger = timeDebugger;
-
-  console.log('Time Travel Debugger Runtime v2.1 - JavaScript Value Serialization Enabled');
+  globalThis.performance = performance;
+  // Resolved lazily: script args are only known once a file is executed
+  Object.defineProperty(globalThis.Deno, 'args', {
+    get: () => core.ops.op_get_script_args(),
+    configurable: true,
+    enumerable: true,
+  });
 })(); 
\ No newline at end of file
@@ -1,7 +1,8 @@
 use deno_core::{v8, serde_json};
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use anyhow::Result;
+use std::hash::{Hash, Hasher};
+use anyhow::{Result, anyhow};
 
 /// Represents a serialized JavaScript value that can be stored and reconstructed
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,13 +33,315 @@ pub enum JSValue {
     },
     Map(Vec<(JSValue, JSValue)>), // Key-value pairs
     Set(Vec<JSValue>),
+    Uint8ClampedArray(Vec<u8>),
+    /// Numeric typed array contents widened to `f64` (exact for every kind but `Float64Array`,
+    /// which is already `f64`). `Uint8ClampedArray` keeps its dedicated byte variant.
+    TypedArray {
+        kind: TypedArrayKind,
+        elements: Vec<f64>,
+        byte_length: usize,
+    },
+    /// Raw binary buffer; `data` is its base64 content, omitted for buffers of
+    /// `SerializationConfig::max_array_length * 8` bytes or more
+    ArrayBuffer {
+        byte_length: usize,
+        data: Option<String>,
+    },
+    /// A promise and, once settled, the value it resolved or rejected with
+    Promise {
+        state: PromiseState,
+        value: Option<Box<JSValue>>,
+    },
+    /// Canvas pixel data: an object with `width`, `height` and a `Uint8ClampedArray` `data`
+    ImageData {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        /// Base64 PNG thumbnail, filled in when `SerializationConfig::generate_image_preview` is set
+        preview_png: Option<String>,
+    },
     
     // Error and circular reference handling
     Error(String),
     CircularReference(String), // Reference ID for circular structures
+
+    // Later variants go last so saved traces keep their variant tags
+    /// A generator and the result of one `next()` call on it, captured only when
+    /// `SerializationConfig::probe_generators` is set
+    Generator {
+        done: bool,
+        value: Box<JSValue>,
+    },
+}
+
+/// Settlement state of a captured promise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromiseState {
+    Pending,
+    Fulfilled,
+    Rejected,
+}
+
+impl PromiseState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PromiseState::Pending => "pending",
+            PromiseState::Fulfilled => "fulfilled",
+            PromiseState::Rejected => "rejected",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [PromiseState::Pending, PromiseState::Fulfilled, PromiseState::Rejected]
+            .into_iter()
+            .find(|state| state.as_str() == name)
+    }
+}
+
+/// The numeric JavaScript typed array constructors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypedArrayKind {
+    Int8,
+    Uint8,
+    Uint8Clamped,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl TypedArrayKind {
+    const ALL: [TypedArrayKind; 9] = [
+        TypedArrayKind::Int8,
+        TypedArrayKind::Uint8,
+        TypedArrayKind::Uint8Clamped,
+        TypedArrayKind::Int16,
+        TypedArrayKind::Uint16,
+        TypedArrayKind::Int32,
+        TypedArrayKind::Uint32,
+        TypedArrayKind::Float32,
+        TypedArrayKind::Float64,
+    ];
+
+    /// The JavaScript constructor name, e.g. `Float32Array`
+    pub fn name(self) -> &'static str {
+        match self {
+            TypedArrayKind::Int8 => "Int8Array",
+            TypedArrayKind::Uint8 => "Uint8Array",
+            TypedArrayKind::Uint8Clamped => "Uint8ClampedArray",
+            TypedArrayKind::Int16 => "Int16Array",
+            TypedArrayKind::Uint16 => "Uint16Array",
+            TypedArrayKind::Int32 => "Int32Array",
+            TypedArrayKind::Uint32 => "Uint32Array",
+            TypedArrayKind::Float32 => "Float32Array",
+            TypedArrayKind::Float64 => "Float64Array",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    fn of(value: v8::Local<v8::Value>) -> Option<Self> {
+        Some(if value.is_int8_array() {
+            TypedArrayKind::Int8
+        } else if value.is_uint8_array() {
+            TypedArrayKind::Uint8
+        } else if value.is_uint8_clamped_array() {
+            TypedArrayKind::Uint8Clamped
+        } else if value.is_int16_array() {
+            TypedArrayKind::Int16
+        } else if value.is_uint16_array() {
+            TypedArrayKind::Uint16
+        } else if value.is_int32_array() {
+            TypedArrayKind::Int32
+        } else if value.is_uint32_array() {
+            TypedArrayKind::Uint32
+        } else if value.is_float32_array() {
+            TypedArrayKind::Float32
+        } else if value.is_float64_array() {
+            TypedArrayKind::Float64
+        } else {
+            return None;
+        })
+    }
+
+    /// Decode native-endian element bytes, as V8 stores them
+    fn decode(self, bytes: &[u8]) -> Vec<f64> {
+        fn chunks<const N: usize>(bytes: &[u8], read: fn([u8; N]) -> f64) -> Vec<f64> {
+            bytes.chunks_exact(N).map(|chunk| read(chunk.try_into().expect("chunk has N bytes"))).collect()
+        }
+
+        match self {
+            TypedArrayKind::Int8 => bytes.iter().map(|&b| b as i8 as f64).collect(),
+            TypedArrayKind::Uint8 | TypedArrayKind::Uint8Clamped => bytes.iter().map(|&b| b as f64).collect(),
+            TypedArrayKind::Int16 => chunks(bytes, |b| i16::from_ne_bytes(b) as f64),
+            TypedArrayKind::Uint16 => chunks(bytes, |b| u16::from_ne_bytes(b) as f64),
+            TypedArrayKind::Int32 => chunks(bytes, |b| i32::from_ne_bytes(b) as f64),
+            TypedArrayKind::Uint32 => chunks(bytes, |b| u32::from_ne_bytes(b) as f64),
+            TypedArrayKind::Float32 => chunks(bytes, |b| f32::from_ne_bytes(b) as f64),
+            TypedArrayKind::Float64 => chunks(bytes, f64::from_ne_bytes),
+        }
+    }
+}
+
+fn v8_error<'s>(scope: &mut v8::HandleScope<'s>, message: &str) -> v8::Local<'s, v8::Value> {
+    let message = v8::String::new(scope, message).unwrap_or_else(|| v8::String::empty(scope));
+    v8::Exception::error(scope, message)
+}
+
+fn v8_string<'s>(scope: &mut v8::HandleScope<'s>, value: &str) -> Result<v8::Local<'s, v8::String>> {
+    v8::String::new(scope, value).ok_or_else(|| anyhow!("String too long to reconstruct"))
+}
+
+/// Body of the stubs standing in for reconstructed functions
+fn noop_function(_scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {}
+
+/// Sign and little-endian 64-bit words of a decimal BigInt (an optional `-` and trailing
+/// `n` are accepted), as `v8::BigInt::new_from_words` takes them
+fn bigint_words(digits: &str) -> Option<(bool, Vec<u64>)> {
+    let digits = digits.strip_suffix('n').unwrap_or(digits);
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, digits),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut words = vec![0u64];
+    for digit in digits.bytes() {
+        let mut carry = u128::from(digit - b'0');
+        for word in &mut words {
+            let product = u128::from(*word) * 10 + carry;
+            *word = product as u64;
+            carry = product >> 64;
+        }
+        if carry > 0 {
+            words.push(carry as u64);
+        }
+    }
+    Some((negative, words))
+}
+
+/// Structural equality, as in `JSValue::structural_eq`: NaN equals NaN, object keys
+/// compare as sets and map entries in insertion order
+impl PartialEq for JSValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.structural_eq(other)
+    }
 }
 
 impl JSValue {
+    /// Rebuild a live V8 value from this one, the inverse of `from_v8_value`. Functions
+    /// become no-op stubs carrying the original name; `Error` values become JS `Error`s.
+    /// Typed arrays, buffers, promises and image data are not reconstructed.
+    pub fn to_v8_value<'s>(&self, scope: &mut v8::HandleScope<'s>) -> Result<v8::Local<'s, v8::Value>> {
+        self.to_v8_value_with_refs(scope, &mut HashMap::new())
+    }
+
+    /// `to_v8_value` sharing `refs` across the whole reconstruction. Serialized objects
+    /// don't record their own reference id, so a `CircularReference` can't be pointed
+    /// back at its target; every reference to the same id shares one placeholder object.
+    fn to_v8_value_with_refs<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        refs: &mut HashMap<String, v8::Global<v8::Value>>,
+    ) -> Result<v8::Local<'s, v8::Value>> {
+        let value: v8::Local<v8::Value> = match self {
+            JSValue::Null => v8::null(scope).into(),
+            JSValue::Undefined => v8::undefined(scope).into(),
+            JSValue::Boolean(b) => v8::Boolean::new(scope, *b).into(),
+            JSValue::Number(n) => v8::Number::new(scope, *n).into(),
+            JSValue::String(s) => v8_string(scope, s)?.into(),
+            JSValue::BigInt(digits) => {
+                let (negative, words) = bigint_words(digits)
+                    .ok_or_else(|| anyhow!("Invalid BigInt: {}", digits))?;
+                v8::BigInt::new_from_words(scope, negative, &words)
+                    .ok_or_else(|| anyhow!("Cannot create BigInt {}", digits))?
+                    .into()
+            },
+            JSValue::Symbol(description) => {
+                let description = v8_string(scope, description)?;
+                v8::Symbol::new(scope, Some(description)).into()
+            },
+            JSValue::Array(elements) => {
+                let mut locals = Vec::with_capacity(elements.len());
+                for element in elements {
+                    locals.push(element.to_v8_value_with_refs(scope, refs)?);
+                }
+                v8::Array::new_with_elements(scope, &locals).into()
+            },
+            JSValue::Object(properties) => {
+                let object = v8::Object::new(scope);
+                for (key, value) in properties {
+                    let value = value.to_v8_value_with_refs(scope, refs)?;
+                    let key = v8_string(scope, key)?;
+                    object.set(scope, key.into(), value);
+                }
+                object.into()
+            },
+            JSValue::Function { name, .. } => {
+                let function = v8::Function::new(scope, noop_function)
+                    .ok_or_else(|| anyhow!("Cannot create function stub {}", name))?;
+                let name = v8_string(scope, name)?;
+                function.set_name(name);
+                function.into()
+            },
+            JSValue::Date(iso) => {
+                // Unparseable dates come back as `Invalid Date`
+                let millis = chrono::DateTime::parse_from_rfc3339(iso)
+                    .map_or(f64::NAN, |date| date.timestamp_millis() as f64);
+                v8::Date::new(scope, millis)
+                    .ok_or_else(|| anyhow!("Cannot create Date {}", iso))?
+                    .into()
+            },
+            JSValue::RegExp { pattern, flags } => {
+                // The bindings expose no RegExp constructor, so call the script's
+                let pattern_value = v8_string(scope, pattern)?;
+                let flags_value = v8_string(scope, flags)?;
+                let key = v8_string(scope, "RegExp")?;
+                let global = scope.get_current_context().global(scope);
+                let constructor = global.get(scope, key.into())
+                    .and_then(|constructor| v8::Local::<v8::Function>::try_from(constructor).ok())
+                    .ok_or_else(|| anyhow!("RegExp constructor not available"))?;
+                constructor.new_instance(scope, &[pattern_value.into(), flags_value.into()])
+                    .ok_or_else(|| anyhow!("Invalid RegExp /{}/{}", pattern, flags))?
+                    .into()
+            },
+            JSValue::Map(entries) => {
+                let map = v8::Map::new(scope);
+                for (key, value) in entries {
+                    let key = key.to_v8_value_with_refs(scope, refs)?;
+                    let value = value.to_v8_value_with_refs(scope, refs)?;
+                    map.set(scope, key, value);
+                }
+                map.into()
+            },
+            JSValue::Set(values) => {
+                let set = v8::Set::new(scope);
+                for value in values {
+                    let value = value.to_v8_value_with_refs(scope, refs)?;
+                    set.add(scope, value);
+                }
+                set.into()
+            },
+            JSValue::Error(message) => v8_error(scope, message),
+            JSValue::CircularReference(ref_id) => match refs.get(ref_id) {
+                Some(placeholder) => v8::Local::new(scope, placeholder),
+                None => {
+                    let placeholder: v8::Local<v8::Value> = v8::Object::new(scope).into();
+                    refs.insert(ref_id.clone(), v8::Global::new(scope, placeholder));
+                    placeholder
+                },
+            },
+            other => return Err(anyhow!("Cannot reconstruct {} value", other.type_name())),
+        };
+        Ok(value)
+    }
+
     /// Convert a V8 value to JSValue for serialization
     pub fn from_v8_value(
         scope: &mut v8::HandleScope,
@@ -46,18 +349,41 @@ impl JSValue {
         max_depth: usize,
         circular_refs: &mut HashMap<usize, String>,
     ) -> Result<Self> {
-        Self::from_v8_value_internal(scope, value, max_depth, 0, circular_refs)
+        Self::from_v8_value_with_limits(scope, value, max_depth, usize::MAX, circular_refs)
     }
 
-    fn from_v8_value_internal(
+    /// `from_v8_value` that also truncates strings and function sources longer than
+    /// `max_string_length` bytes, marking them with a trailing `…`
+    pub fn from_v8_value_with_limits(
         scope: &mut v8::HandleScope,
         value: v8::Local<v8::Value>,
         max_depth: usize,
+        max_string_length: usize,
+        circular_refs: &mut HashMap<usize, String>,
+    ) -> Result<Self> {
+        let config = SerializationConfig { max_depth, max_string_length, ..Default::default() };
+        Self::from_v8_value_with_config(scope, value, &config, circular_refs)
+    }
+
+    /// Convert a V8 value honoring every limit and capture option in `config`
+    pub fn from_v8_value_with_config(
+        scope: &mut v8::HandleScope,
+        value: v8::Local<v8::Value>,
+        config: &SerializationConfig,
+        circular_refs: &mut HashMap<usize, String>,
+    ) -> Result<Self> {
+        Self::from_v8_value_internal(scope, value, config, 0, circular_refs)
+    }
+
+    fn from_v8_value_internal(
+        scope: &mut v8::HandleScope,
+        value: v8::Local<v8::Value>,
+        config: &SerializationConfig,
         current_depth: usize,
         circular_refs: &mut HashMap<usize, String>,
     ) -> Result<Self> {
         // Prevent infinite recursion
-        if current_depth > max_depth {
+        if current_depth > config.max_depth {
             return Ok(JSValue::Error("Max depth exceeded".to_string()));
         }
 
@@ -82,7 +408,7 @@ impl JSValue {
 
         if value.is_string() {
             let string_val = value.to_rust_string_lossy(scope);
-            return Ok(JSValue::String(string_val));
+            return Ok(JSValue::String(truncate_to_byte_limit(string_val, config.max_string_length)));
         }
 
         if value.is_big_int() {
@@ -160,7 +486,7 @@ impl JSValue {
                 
                 // Try to get function source (this might not always work depending on V8 settings)
                 let source = function.to_string(scope)
-                    .map(|s| s.to_rust_string_lossy(scope));
+                    .map(|s| truncate_to_byte_limit(s.to_rust_string_lossy(scope), config.max_string_length));
 
                 return Ok(JSValue::Function {
                     name,
@@ -181,7 +507,7 @@ impl JSValue {
                         let serialized_element = Self::from_v8_value_internal(
                             scope, 
                             element, 
-                            max_depth, 
+                            config, 
                             current_depth + 1, 
                             circular_refs
                         )?;
@@ -206,16 +532,16 @@ impl JSValue {
                 for i in (0..length).step_by(2) {
                     if let (Some(key), Some(value)) = (array.get_index(scope, i), array.get_index(scope, i + 1)) {
                         let serialized_key = Self::from_v8_value_internal(
-                            scope, key, max_depth, current_depth + 1, circular_refs
+                            scope, key, config, current_depth + 1, circular_refs
                         )?;
                         let serialized_value = Self::from_v8_value_internal(
-                            scope, value, max_depth, current_depth + 1, circular_refs
+                            scope, value, config, current_depth + 1, circular_refs
                         )?;
                         entries.push((serialized_key, serialized_value));
                     }
                 }
 
-                return Ok(JSValue::Map(entries));
+                return Ok(JSValue::Map(entries).normalize());
             }
         }
 
@@ -229,7 +555,7 @@ impl JSValue {
                 for i in 0..length {
                     if let Some(element) = array.get_index(scope, i) {
                         let serialized_element = Self::from_v8_value_internal(
-                            scope, element, max_depth, current_depth + 1, circular_refs
+                            scope, element, config, current_depth + 1, circular_refs
                         )?;
                         elements.push(serialized_element);
                     }
@@ -239,6 +565,49 @@ impl JSValue {
             }
         }
 
+        if let Ok(array) = v8::Local::<v8::Uint8ClampedArray>::try_from(value) {
+            return Ok(JSValue::Uint8ClampedArray(copy_view_contents(array.into())));
+        }
+
+        if let Some(kind) = TypedArrayKind::of(value)
+            && let Ok(array) = v8::Local::<v8::TypedArray>::try_from(value) {
+            let bytes = copy_view_contents(array.into());
+            return Ok(JSValue::TypedArray { kind, elements: kind.decode(&bytes), byte_length: bytes.len() });
+        }
+
+        if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+            let byte_length = buffer.byte_length();
+            let data = (byte_length < config.max_array_length.saturating_mul(8)).then(|| {
+                use base64::Engine;
+                let bytes: Vec<u8> = buffer.get_backing_store().iter().take(byte_length).map(|b| b.get()).collect();
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            });
+            return Ok(JSValue::ArrayBuffer { byte_length, data });
+        }
+
+        if let Ok(promise) = v8::Local::<v8::Promise>::try_from(value) {
+            let state = match promise.state() {
+                v8::PromiseState::Pending => PromiseState::Pending,
+                v8::PromiseState::Fulfilled => PromiseState::Fulfilled,
+                v8::PromiseState::Rejected => PromiseState::Rejected,
+            };
+            let value = if state == PromiseState::Pending {
+                None
+            } else {
+                let result = promise.result(scope);
+                Some(Box::new(Self::from_v8_value_internal(scope, result, config, current_depth + 1, circular_refs)?))
+            };
+            return Ok(JSValue::Promise { state, value });
+        }
+
+        if config.probe_generators && value.is_generator_object() {
+            return Self::generator_from_v8(scope, value, config, current_depth, circular_refs);
+        }
+
+        if let Some(image_data) = Self::image_data_from_v8(scope, value) {
+            return Ok(image_data);
+        }
+
         // Handle generic objects
         if value.is_object() {
             if let Ok(object) = v8::Local::<v8::Object>::try_from(value) {
@@ -253,28 +622,10 @@ impl JSValue {
                 let ref_id = format!("ref_{}", object_id);
                 circular_refs.insert(object_id_key, ref_id.clone());
 
-                let mut properties = HashMap::new();
-                
-                // Get object's own property names
-                if let Some(property_names) = object.get_own_property_names(scope, v8::GetPropertyNamesArgs::default()) {
-                    let length = property_names.length();
-                    
-                    for i in 0..length {
-                        if let Some(key) = property_names.get_index(scope, i) {
-                            let key_string = key.to_rust_string_lossy(scope);
-                            
-                            if let Some(property_value) = object.get(scope, key) {
-                                let serialized_value = Self::from_v8_value_internal(
-                                    scope, 
-                                    property_value, 
-                                    max_depth, 
-                                    current_depth + 1, 
-                                    circular_refs
-                                )?;
-                                properties.insert(key_string, serialized_value);
-                            }
-                        }
-                    }
+                let mut properties = Self::own_properties_from_v8(scope, object, config, current_depth, circular_refs)?;
+                if config.capture_prototype_chain
+                    && let Some(prototype) = Self::prototype_chain_from_v8(scope, object, config, current_depth + 1, circular_refs)? {
+                    properties.insert("__proto__".to_string(), prototype);
                 }
 
                 return Ok(JSValue::Object(properties));
@@ -285,6 +636,386 @@ impl JSValue {
         Ok(JSValue::Error(format!("Unsupported value type: {}", value.type_repr())))
     }
 
+    /// Serialize the own properties of `object`, which sits at `current_depth`. With
+    /// `include_non_enumerable`, the non-enumerable ones go under a nested
+    /// `__non_enumerable__` key; with `capture_symbol_keys`, enumerable symbol-keyed ones
+    /// are included under `Symbol(description)` keys.
+    fn own_properties_from_v8(
+        scope: &mut v8::HandleScope,
+        object: v8::Local<v8::Object>,
+        config: &SerializationConfig,
+        current_depth: usize,
+        circular_refs: &mut HashMap<usize, String>,
+    ) -> Result<HashMap<String, JSValue>> {
+        let mut properties = Self::properties_from_v8(scope, object, v8::GetPropertyNamesArgs::default(), &HashMap::new(), config, current_depth, circular_refs)?;
+
+        if config.capture_symbol_keys {
+            // These bindings have no `GetOwnPropertySymbols`; a filter skipping strings does the same
+            let symbols_only = v8::GetPropertyNamesArgs {
+                property_filter: v8::PropertyFilter::ONLY_ENUMERABLE | v8::PropertyFilter::SKIP_STRINGS,
+                ..Default::default()
+            };
+            let symbol_properties = Self::properties_from_v8(scope, object, symbols_only, &properties, config, current_depth, circular_refs)?;
+            properties.extend(symbol_properties);
+        }
+
+        if config.include_non_enumerable {
+            // Symbols stay skipped, as for enumerable properties
+            let all_properties = v8::GetPropertyNamesArgs {
+                property_filter: v8::PropertyFilter::ALL_PROPERTIES | v8::PropertyFilter::SKIP_SYMBOLS,
+                ..Default::default()
+            };
+            let non_enumerable = Self::properties_from_v8(scope, object, all_properties, &properties, config, current_depth, circular_refs)?;
+            if !non_enumerable.is_empty() {
+                properties.insert("__non_enumerable__".to_string(), JSValue::Object(non_enumerable));
+            }
+        }
+
+        Ok(properties)
+    }
+
+    /// Serialize the own properties of `object` selected by `args`, except those in `skip`
+    fn properties_from_v8(
+        scope: &mut v8::HandleScope,
+        object: v8::Local<v8::Object>,
+        args: v8::GetPropertyNamesArgs,
+        skip: &HashMap<String, JSValue>,
+        config: &SerializationConfig,
+        current_depth: usize,
+        circular_refs: &mut HashMap<usize, String>,
+    ) -> Result<HashMap<String, JSValue>> {
+        let mut properties = HashMap::new();
+        if let Some(property_names) = object.get_own_property_names(scope, args) {
+            for i in 0..property_names.length() {
+                if let Some(key) = property_names.get_index(scope, i) {
+                    let key_string = match v8::Local::<v8::Symbol>::try_from(key) {
+                        Ok(symbol) => {
+                            let description = symbol.description(scope);
+                            if description.is_undefined() {
+                                "Symbol()".to_string()
+                            } else {
+                                format!("Symbol({})", description.to_rust_string_lossy(scope))
+                            }
+                        },
+                        Err(_) => key.to_rust_string_lossy(scope),
+                    };
+                    if skip.contains_key(&key_string) {
+                        continue;
+                    }
+
+                    if let Some(property_value) = object.get(scope, key) {
+                        let serialized_value = Self::from_v8_value_internal(
+                            scope,
+                            property_value,
+                            config,
+                            current_depth + 1,
+                            circular_refs
+                        )?;
+                        properties.insert(key_string, serialized_value);
+                    }
+                }
+            }
+        }
+        Ok(properties)
+    }
+
+    /// Inherited properties of `object`, one nested `__proto__` object per prototype level.
+    /// Returns `None` once the next prototype is `Object.prototype` (its own prototype is null).
+    fn prototype_chain_from_v8(
+        scope: &mut v8::HandleScope,
+        object: v8::Local<v8::Object>,
+        config: &SerializationConfig,
+        current_depth: usize,
+        circular_refs: &mut HashMap<usize, String>,
+    ) -> Result<Option<JSValue>> {
+        let Some(prototype) = object.get_prototype(scope)
+            .and_then(|prototype| v8::Local::<v8::Object>::try_from(prototype).ok()) else {
+            return Ok(None);
+        };
+        if prototype.get_prototype(scope).is_none_or(|next| next.is_null()) {
+            return Ok(None);
+        }
+        if current_depth > config.max_depth {
+            return Ok(Some(JSValue::Error("Max depth exceeded".to_string())));
+        }
+
+        // Prototypes are shared between instances, so they stay out of circular reference tracking
+        let mut properties = Self::own_properties_from_v8(scope, prototype, config, current_depth, circular_refs)?;
+        if let Some(parent) = Self::prototype_chain_from_v8(scope, prototype, config, current_depth + 1, circular_refs)? {
+            properties.insert("__proto__".to_string(), parent);
+        }
+        Ok(Some(JSValue::Object(properties)))
+    }
+
+    /// Advance a generator once with its own `next()`. A `next()` that throws is recorded
+    /// as a finished generator whose value is the error.
+    fn generator_from_v8(
+        scope: &mut v8::HandleScope,
+        value: v8::Local<v8::Value>,
+        config: &SerializationConfig,
+        current_depth: usize,
+        circular_refs: &mut HashMap<usize, String>,
+    ) -> Result<JSValue> {
+        let scope = &mut v8::TryCatch::new(scope);
+        let generator = value.to_object(scope).ok_or_else(|| anyhow!("Generator is not an object"))?;
+        let next_key = v8_string(scope, "next")?;
+        let next = generator.get(scope, next_key.into())
+            .and_then(|next| v8::Local::<v8::Function>::try_from(next).ok())
+            .ok_or_else(|| anyhow!("Generator has no next() method"))?;
+
+        let Some(result) = next.call(scope, value, &[]).and_then(|result| result.to_object(scope)) else {
+            let message = scope.exception()
+                .map(|exception| exception.to_rust_string_lossy(scope))
+                .unwrap_or_else(|| "next() failed".to_string());
+            return Ok(JSValue::Generator { done: true, value: Box::new(JSValue::Error(message)) });
+        };
+
+        let done_key = v8_string(scope, "done")?;
+        let done = result.get(scope, done_key.into()).is_some_and(|done| done.boolean_value(scope));
+        let value_key = v8_string(scope, "value")?;
+        let result_value = result.get(scope, value_key.into()).unwrap_or_else(|| v8::undefined(scope).into());
+        let value = Self::from_v8_value_internal(scope, result_value, config, current_depth + 1, circular_refs)?;
+        Ok(JSValue::Generator { done, value: Box::new(value) })
+    }
+
+    /// Recognize canvas `ImageData`-shaped objects
+    fn image_data_from_v8(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<JSValue> {
+        let object = v8::Local::<v8::Object>::try_from(value).ok()?;
+        let mut get = |name: &str| {
+            let key = v8::String::new(scope, name)?;
+            object.get(scope, key.into())
+        };
+
+        let width = get("width")?;
+        let height = get("height")?;
+        let data = get("data")?;
+        if !width.is_number() || !height.is_number() || !data.is_uint8_clamped_array() {
+            return None;
+        }
+
+        let data = v8::Local::<v8::Uint8ClampedArray>::try_from(data).ok()?;
+        Some(JSValue::ImageData {
+            width: width.uint32_value(scope)?,
+            height: height.uint32_value(scope)?,
+            data: copy_view_contents(data.into()),
+            preview_png: None,
+        })
+    }
+
+    /// Fill in `preview_png` for every `ImageData` in this value
+    pub fn attach_image_previews(&mut self) {
+        match self {
+            JSValue::ImageData { width, height, data, preview_png } => {
+                *preview_png = encode_png_thumbnail(*width, *height, data);
+            },
+            JSValue::Object(obj) => obj.values_mut().for_each(JSValue::attach_image_previews),
+            JSValue::Array(arr) | JSValue::Set(arr) => arr.iter_mut().for_each(JSValue::attach_image_previews),
+            JSValue::Map(entries) => entries.iter_mut().for_each(|(k, v)| {
+                k.attach_image_previews();
+                v.attach_image_previews();
+            }),
+            _ => {},
+        }
+    }
+
+    /// Approximate in-memory footprint in bytes: the enum itself plus owned heap data
+    pub fn estimated_heap_bytes(&self) -> usize {
+        let heap = match self {
+            JSValue::Null | JSValue::Undefined | JSValue::Boolean(_) | JSValue::Number(_) => 0,
+            JSValue::String(s) | JSValue::BigInt(s) | JSValue::Symbol(s)
+            | JSValue::Date(s) | JSValue::Error(s) | JSValue::CircularReference(s) => s.len(),
+            JSValue::Object(obj) => obj.iter().map(|(k, v)| k.len() + v.estimated_heap_bytes()).sum(),
+            JSValue::Array(arr) | JSValue::Set(arr) => arr.iter().map(JSValue::estimated_heap_bytes).sum(),
+            JSValue::Function { name, source, location } => {
+                name.len() + source.as_ref().map_or(0, String::len) + location.as_ref().map_or(0, String::len)
+            },
+            JSValue::RegExp { pattern, flags } => pattern.len() + flags.len(),
+            JSValue::Map(entries) => entries.iter().map(|(k, v)| k.estimated_heap_bytes() + v.estimated_heap_bytes()).sum(),
+            JSValue::Uint8ClampedArray(bytes) => bytes.len(),
+            JSValue::TypedArray { elements, .. } => elements.len() * std::mem::size_of::<f64>(),
+            JSValue::ArrayBuffer { data, .. } => data.as_ref().map_or(0, String::len),
+            JSValue::Promise { value, .. } => value.as_ref().map_or(0, |value| value.estimated_heap_bytes()),
+            JSValue::Generator { value, .. } => value.estimated_heap_bytes(),
+            JSValue::ImageData { data, preview_png, .. } => data.len() + preview_png.as_ref().map_or(0, String::len),
+        };
+        std::mem::size_of::<JSValue>() + heap
+    }
+
+    /// Sort `Map` entries into canonical key order so the same logical map compares equal
+    /// regardless of insertion order: null < false < true < numbers (ascending) < strings
+    /// (lexicographic) < everything else (by display string). Other values are returned as-is.
+    pub fn normalize(self) -> JSValue {
+        match self {
+            JSValue::Map(mut entries) => {
+                entries.sort_by(|(a, _), (b, _)| a.canonical_key_cmp(b));
+                JSValue::Map(entries)
+            },
+            other => other,
+        }
+    }
+
+    fn canonical_key_cmp(&self, other: &JSValue) -> std::cmp::Ordering {
+        fn rank(value: &JSValue) -> u8 {
+            match value {
+                JSValue::Null => 0,
+                JSValue::Boolean(false) => 1,
+                JSValue::Boolean(true) => 2,
+                JSValue::Number(_) => 3,
+                JSValue::String(_) => 4,
+                _ => 5,
+            }
+        }
+
+        rank(self).cmp(&rank(other)).then_with(|| match (self, other) {
+            (JSValue::Number(a), JSValue::Number(b)) => a.total_cmp(b),
+            (JSValue::String(a), JSValue::String(b)) => a.cmp(b),
+            (a, b) if rank(a) == 5 => a.to_display_string().cmp(&b.to_display_string()),
+            _ => std::cmp::Ordering::Equal,
+        })
+    }
+
+    /// Compare two values by structure rather than identity (NaN equals NaN)
+    pub fn structural_eq(&self, other: &JSValue) -> bool {
+        match (self, other) {
+            (JSValue::Null, JSValue::Null) | (JSValue::Undefined, JSValue::Undefined) => true,
+            (JSValue::Boolean(a), JSValue::Boolean(b)) => a == b,
+            (JSValue::Number(a), JSValue::Number(b)) => a.total_cmp(b).is_eq(),
+            (JSValue::String(a), JSValue::String(b))
+            | (JSValue::BigInt(a), JSValue::BigInt(b))
+            | (JSValue::Symbol(a), JSValue::Symbol(b))
+            | (JSValue::Date(a), JSValue::Date(b))
+            | (JSValue::Error(a), JSValue::Error(b))
+            | (JSValue::CircularReference(a), JSValue::CircularReference(b)) => a == b,
+            (JSValue::Object(a), JSValue::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, value)| b.get(key).is_some_and(|other| value.structural_eq(other)))
+            },
+            (JSValue::Array(a), JSValue::Array(b)) | (JSValue::Set(a), JSValue::Set(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.structural_eq(y))
+            },
+            (JSValue::Uint8ClampedArray(a), JSValue::Uint8ClampedArray(b)) => a == b,
+            (
+                JSValue::TypedArray { kind: kind_a, elements: elements_a, byte_length: length_a },
+                JSValue::TypedArray { kind: kind_b, elements: elements_b, byte_length: length_b },
+            ) => {
+                kind_a == kind_b && length_a == length_b && elements_a.len() == elements_b.len()
+                    && elements_a.iter().zip(elements_b).all(|(a, b)| a.total_cmp(b).is_eq())
+            },
+            (
+                JSValue::ArrayBuffer { byte_length: length_a, data: data_a },
+                JSValue::ArrayBuffer { byte_length: length_b, data: data_b },
+            ) => length_a == length_b && data_a == data_b,
+            (
+                JSValue::Promise { state: state_a, value: value_a },
+                JSValue::Promise { state: state_b, value: value_b },
+            ) => state_a == state_b && match (value_a, value_b) {
+                (Some(a), Some(b)) => a.structural_eq(b),
+                (a, b) => a.is_none() && b.is_none(),
+            },
+            (
+                JSValue::Generator { done: done_a, value: value_a },
+                JSValue::Generator { done: done_b, value: value_b },
+            ) => done_a == done_b && value_a.structural_eq(value_b),
+            (
+                JSValue::ImageData { width: width_a, height: height_a, data: data_a, .. },
+                JSValue::ImageData { width: width_b, height: height_b, data: data_b, .. },
+            ) => width_a == width_b && height_a == height_b && data_a == data_b,
+            (JSValue::Map(a), JSValue::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|((ka, va), (kb, vb))| ka.structural_eq(kb) && va.structural_eq(vb))
+            },
+            (
+                JSValue::Function { name: name_a, source: source_a, .. },
+                JSValue::Function { name: name_b, source: source_b, .. },
+            ) => name_a == name_b && source_a == source_b,
+            (
+                JSValue::RegExp { pattern: pattern_a, flags: flags_a },
+                JSValue::RegExp { pattern: pattern_b, flags: flags_b },
+            ) => pattern_a == pattern_b && flags_a == flags_b,
+            _ => false,
+        }
+    }
+
+    /// Type as shown to users: `typeof` for primitives, the constructor name for objects
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JSValue::Null => "null",
+            JSValue::Undefined => "undefined",
+            JSValue::Boolean(_) => "boolean",
+            JSValue::Number(_) => "number",
+            JSValue::String(_) => "string",
+            JSValue::BigInt(_) => "bigint",
+            JSValue::Symbol(_) => "symbol",
+            JSValue::Object(_) => "Object",
+            JSValue::Array(_) => "Array",
+            JSValue::Function { .. } => "Function",
+            JSValue::Date(_) => "Date",
+            JSValue::RegExp { .. } => "RegExp",
+            JSValue::Map(_) => "Map",
+            JSValue::Set(_) => "Set",
+            JSValue::Uint8ClampedArray(_) => "Uint8ClampedArray",
+            JSValue::TypedArray { kind, .. } => kind.name(),
+            JSValue::ArrayBuffer { .. } => "ArrayBuffer",
+            JSValue::Promise { .. } => "Promise",
+            JSValue::Generator { .. } => "Generator",
+            JSValue::ImageData { .. } => "ImageData",
+            JSValue::Error(_) => "Error",
+            JSValue::CircularReference(_) => "Circular",
+        }
+    }
+
+    /// Feed the value into `state` so that values equal under `structural_eq` hash alike:
+    /// object keys are visited in sorted order and numbers by their bit pattern
+    pub fn content_hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            JSValue::Null | JSValue::Undefined => {},
+            JSValue::Boolean(b) => b.hash(state),
+            JSValue::Number(n) => n.to_bits().hash(state),
+            JSValue::String(s) | JSValue::BigInt(s) | JSValue::Symbol(s) | JSValue::Date(s)
+            | JSValue::Error(s) | JSValue::CircularReference(s) => s.hash(state),
+            JSValue::Object(properties) => {
+                let mut keys: Vec<&String> = properties.keys().collect();
+                keys.sort();
+                keys.len().hash(state);
+                for key in keys {
+                    key.hash(state);
+                    properties[key].content_hash(state);
+                }
+            },
+            JSValue::Array(elements) | JSValue::Set(elements) => {
+                elements.len().hash(state);
+                elements.iter().for_each(|element| element.content_hash(state));
+            },
+            JSValue::Map(entries) => {
+                entries.len().hash(state);
+                for (key, value) in entries {
+                    key.content_hash(state);
+                    value.content_hash(state);
+                }
+            },
+            JSValue::Function { name, source, .. } => (name, source).hash(state),
+            JSValue::RegExp { pattern, flags } => (pattern, flags).hash(state),
+            JSValue::Uint8ClampedArray(data) => data.hash(state),
+            JSValue::TypedArray { kind, elements, byte_length } => {
+                (kind.name(), byte_length, elements.len()).hash(state);
+                elements.iter().for_each(|element| element.to_bits().hash(state));
+            },
+            JSValue::ArrayBuffer { byte_length, data } => (byte_length, data).hash(state),
+            JSValue::Promise { state: promise_state, value } => {
+                promise_state.as_str().hash(state);
+                if let Some(value) = value {
+                    value.content_hash(state);
+                }
+            },
+            JSValue::Generator { done, value } => {
+                done.hash(state);
+                value.content_hash(state);
+            },
+            JSValue::ImageData { width, height, data, .. } => (width, height, data).hash(state),
+        }
+    }
+
     /// Convert JSValue back to a JSON representation for display/debugging
     pub fn to_json_value(&self) -> serde_json::Value {
         match self {
@@ -344,6 +1075,44 @@ impl JSValue {
                     .collect();
                 serde_json::json!({ "type": "set", "values": json_elements })
             },
+            JSValue::Uint8ClampedArray(bytes) => {
+                serde_json::json!({ "type": "uint8clampedarray", "values": bytes })
+            },
+            JSValue::TypedArray { kind, elements, byte_length } => {
+                // Through `Number` so NaN and the infinities survive
+                let json_elements: Vec<serde_json::Value> = elements.iter()
+                    .map(|n| JSValue::Number(*n).to_json_value())
+                    .collect();
+                serde_json::json!({
+                    "type": "typedarray",
+                    "kind": kind.name(),
+                    "elements": json_elements,
+                    "byteLength": byte_length
+                })
+            },
+            JSValue::ArrayBuffer { byte_length, data } => {
+                serde_json::json!({ "type": "arraybuffer", "byteLength": byte_length, "data": data })
+            },
+            JSValue::Promise { state, value } => serde_json::json!({
+                "type": "promise",
+                "state": state.as_str(),
+                "value": value.as_ref().map(|value| value.to_json_value())
+            }),
+            JSValue::Generator { done, value } => serde_json::json!({
+                "type": "generator",
+                "done": done,
+                "value": value.to_json_value()
+            }),
+            JSValue::ImageData { width, height, data, preview_png } => {
+                // Raw pixels are omitted; the preview is the readable form
+                serde_json::json!({
+                    "type": "imagedata",
+                    "width": width,
+                    "height": height,
+                    "byte_length": data.len(),
+                    "preview_png": preview_png
+                })
+            },
             JSValue::Error(msg) => serde_json::json!({ "type": "error", "message": msg }),
             JSValue::CircularReference(ref_id) => {
                 serde_json::json!({ "type": "circular_ref", "ref": ref_id })
@@ -404,10 +1173,152 @@ impl JSValue {
             JSValue::RegExp { pattern, flags } => format!("/{}/{}", pattern, flags),
             JSValue::Map(entries) => format!("Map({} entries)", entries.len()),
             JSValue::Set(elements) => format!("Set({} values)", elements.len()),
+            JSValue::Uint8ClampedArray(bytes) => format!("Uint8ClampedArray({} bytes)", bytes.len()),
+            JSValue::TypedArray { kind, elements, .. } => format!("{}({} elements)", kind.name(), elements.len()),
+            JSValue::ArrayBuffer { byte_length, .. } => format!("ArrayBuffer({} bytes)", byte_length),
+            JSValue::Promise { state, value } => match value {
+                Some(value) => format!("Promise {{ {}: {} }}", state.as_str(), value.to_display_string()),
+                None => format!("Promise {{ <{}> }}", state.as_str()),
+            },
+            JSValue::Generator { done, value } => {
+                format!("Generator {{ done: {}, value: {} }}", done, value.to_display_string())
+            },
+            JSValue::ImageData { width, height, data, .. } => {
+                format!("ImageData({}x{} pixels, {} bytes)", width, height, data.len())
+            },
             JSValue::Error(msg) => format!("Error: {}", msg),
             JSValue::CircularReference(ref_id) => format!("[Circular: {}]", ref_id),
         }
     }
+
+    /// Render the full value, breaking containers across lines when they exceed the line width
+    pub fn pretty_print(&self, config: &PrettyPrintConfig) -> String {
+        self.pretty_print_at(config, 0)
+    }
+
+    fn pretty_print_at(&self, config: &PrettyPrintConfig, indent: usize) -> String {
+        let inline = self.to_inline_string();
+        if indent + inline.chars().count() <= config.max_line_width {
+            return inline;
+        }
+
+        let pad = " ".repeat(indent + config.indent_width);
+        let close_pad = " ".repeat(indent);
+        let child_indent = indent + config.indent_width;
+
+        match self {
+            JSValue::Object(obj) if !obj.is_empty() => {
+                let mut keys: Vec<&String> = obj.keys().collect();
+                keys.sort();
+                let lines: Vec<String> = keys.iter()
+                    .map(|k| format!("{}{}: {}", pad, k, obj[*k].pretty_print_at(config, child_indent)))
+                    .collect();
+                format!("{{\n{}\n{}}}", lines.join(",\n"), close_pad)
+            },
+            JSValue::Array(arr) | JSValue::Set(arr) if !arr.is_empty() => {
+                let lines: Vec<String> = arr.iter()
+                    .map(|v| format!("{}{}", pad, v.pretty_print_at(config, child_indent)))
+                    .collect();
+                let prefix = if matches!(self, JSValue::Set(_)) { "Set " } else { "" };
+                format!("{}[\n{}\n{}]", prefix, lines.join(",\n"), close_pad)
+            },
+            JSValue::Map(entries) if !entries.is_empty() => {
+                let lines: Vec<String> = entries.iter()
+                    .map(|(k, v)| format!("{}{} => {}", pad,
+                        k.pretty_print_at(config, child_indent), v.pretty_print_at(config, child_indent)))
+                    .collect();
+                format!("Map {{\n{}\n{}}}", lines.join(",\n"), close_pad)
+            },
+            _ => inline,
+        }
+    }
+
+    /// Single-line representation that, unlike `to_display_string`, includes every element
+    fn to_inline_string(&self) -> String {
+        match self {
+            JSValue::Object(obj) if !obj.is_empty() => {
+                let mut keys: Vec<&String> = obj.keys().collect();
+                keys.sort();
+                let props: Vec<String> = keys.iter()
+                    .map(|k| format!("{}: {}", k, obj[*k].to_inline_string()))
+                    .collect();
+                format!("{{ {} }}", props.join(", "))
+            },
+            JSValue::Array(arr) => {
+                let elements: Vec<String> = arr.iter().map(|v| v.to_inline_string()).collect();
+                format!("[{}]", elements.join(", "))
+            },
+            JSValue::Set(elements) => {
+                let elements: Vec<String> = elements.iter().map(|v| v.to_inline_string()).collect();
+                format!("Set [{}]", elements.join(", "))
+            },
+            JSValue::Map(entries) => {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(k, v)| format!("{} => {}", k.to_inline_string(), v.to_inline_string()))
+                    .collect();
+                format!("Map {{ {} }}", entries.join(", "))
+            },
+            _ => self.to_display_string(),
+        }
+    }
+}
+
+/// Cut `text` to at most `max_bytes` bytes (at a char boundary) plus a `…` marker
+fn truncate_to_byte_limit(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push('…');
+    text
+}
+
+/// Copy the bytes backing a typed array view
+fn copy_view_contents(view: v8::Local<v8::ArrayBufferView>) -> Vec<u8> {
+    let mut bytes = vec![0; view.byte_length()];
+    let copied = view.copy_contents(&mut bytes);
+    bytes.truncate(copied);
+    bytes
+}
+
+/// Longest edge of the PNG thumbnails attached to `ImageData` values
+const IMAGE_PREVIEW_SIZE: u32 = 64;
+
+/// Encode RGBA pixel data as a base64 PNG thumbnail
+fn encode_png_thumbnail(width: u32, height: u32, data: &[u8]) -> Option<String> {
+    use base64::Engine;
+
+    let image = image::RgbaImage::from_raw(width, height, data.to_vec())?;
+    let scale = (IMAGE_PREVIEW_SIZE as f64 / width.max(height).max(1) as f64).min(1.0);
+    let thumbnail = image::imageops::thumbnail(
+        &image,
+        ((width as f64 * scale) as u32).max(1),
+        ((height as f64 * scale) as u32).max(1),
+    );
+
+    let mut png = Vec::new();
+    thumbnail.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).ok()?;
+    Some(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+/// Layout options for `JSValue::pretty_print`
+#[derive(Debug, Clone)]
+pub struct PrettyPrintConfig {
+    pub max_line_width: usize,
+    pub indent_width: usize,
+}
+
+impl Default for PrettyPrintConfig {
+    fn default() -> Self {
+        Self {
+            max_line_width: 120,
+            indent_width: 2,
+        }
+    }
 }
 
 /// Configuration for value serialization
@@ -418,6 +1329,17 @@ pub struct SerializationConfig {
     pub max_array_length: usize,
     pub max_object_properties: usize,
     pub capture_function_source: bool,
+    /// Store inherited properties under a nested `__proto__` key, stopping before `Object.prototype`
+    pub capture_prototype_chain: bool,
+    /// Attach a base64 PNG thumbnail to captured `ImageData` values
+    pub generate_image_preview: bool,
+    /// Also capture non-enumerable own properties, under a nested `__non_enumerable__` key
+    pub include_non_enumerable: bool,
+    /// Also capture symbol-keyed own properties, keyed `Symbol(description)`
+    pub capture_symbol_keys: bool,
+    /// Call `next()` on generator objects to capture their next value. This advances the
+    /// generator, so the script sees one value fewer; off by default.
+    pub probe_generators: bool,
 }
 
 impl Default for SerializationConfig {
@@ -428,6 +1350,11 @@ impl Default for SerializationConfig {
             max_array_length: 100,
             max_object_properties: 50,
             capture_function_source: true,
+            capture_prototype_chain: false,
+            generate_image_preview: false,
+            include_non_enumerable: false,
+            capture_symbol_keys: false,
+            probe_generators: false,
         }
     }
 }
@@ -452,9 +1379,24 @@ impl SerializationContext {
         &mut self,
         scope: &mut v8::HandleScope,
         value: v8::Local<v8::Value>,
+    ) -> Result<JSValue> {
+        self.serialize_value_with_depth(scope, value, self.config.max_depth)
+    }
+
+    /// Serialize a V8 value with a depth limit other than the configured `max_depth`
+    pub fn serialize_value_with_depth(
+        &mut self,
+        scope: &mut v8::HandleScope,
+        value: v8::Local<v8::Value>,
+        max_depth: usize,
     ) -> Result<JSValue> {
         self.circular_refs.clear(); // Reset circular reference tracking
-        JSValue::from_v8_value(scope, value, self.config.max_depth, &mut self.circular_refs)
+        let config = SerializationConfig { max_depth, ..self.config.clone() };
+        let mut serialized = JSValue::from_v8_value_with_config(scope, value, &config, &mut self.circular_refs)?;
+        if self.config.generate_image_preview {
+            serialized.attach_image_previews();
+        }
+        Ok(serialized)
     }
 
     /// Serialize multiple values (e.g., function arguments)
@@ -467,12 +1409,15 @@ impl SerializationContext {
         let mut results = Vec::with_capacity(values.len());
         
         for value in values {
-            let serialized = JSValue::from_v8_value(
+            let mut serialized = JSValue::from_v8_value_with_config(
                 scope, 
                 *value, 
-                self.config.max_depth, 
+                &self.config,
                 &mut self.circular_refs
             )?;
+            if self.config.generate_image_preview {
+                serialized.attach_image_previews();
+            }
             results.push(serialized);
         }
         
@@ -505,4 +1450,130 @@ mod tests {
         let json = val.to_json_value();
         assert!(json.is_object());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_map_normalize_ignores_insertion_order() {
+        let key = |s: &str| JSValue::String(s.to_string());
+        let a = JSValue::Map(vec![
+            (key("b"), JSValue::Number(2.0)),
+            (JSValue::Number(10.0), JSValue::Null),
+            (key("a"), JSValue::Number(1.0)),
+            (JSValue::Boolean(true), JSValue::Undefined),
+            (JSValue::Null, JSValue::Boolean(false)),
+            (JSValue::Number(-1.0), JSValue::Null),
+        ]).normalize();
+        let b = JSValue::Map(vec![
+            (JSValue::Number(-1.0), JSValue::Null),
+            (key("a"), JSValue::Number(1.0)),
+            (JSValue::Null, JSValue::Boolean(false)),
+            (JSValue::Number(10.0), JSValue::Null),
+            (JSValue::Boolean(true), JSValue::Undefined),
+            (key("b"), JSValue::Number(2.0)),
+        ]).normalize();
+
+        assert!(a.structural_eq(&b));
+        let JSValue::Map(entries) = a else { panic!("expected a map") };
+        assert!(entries[0].0.structural_eq(&JSValue::Null));
+        assert!(entries[2].0.structural_eq(&JSValue::Number(-1.0)));
+        assert!(entries[5].0.structural_eq(&key("b")));
+    }
+
+    #[test]
+    fn test_partial_eq_is_structural() {
+        let object = |x: f64| JSValue::Object(HashMap::from([
+            ("x".to_string(), JSValue::Number(x)),
+            ("tags".to_string(), JSValue::Array(vec![JSValue::String("a".to_string()), JSValue::Null])),
+        ]));
+        assert_eq!(object(f64::NAN), object(f64::NAN));
+        assert_ne!(object(1.0), object(2.0));
+        assert_ne!(JSValue::Number(0.0), JSValue::Number(-0.0));
+        assert_ne!(JSValue::Null, JSValue::Undefined);
+
+        let map = |first: &str, second: &str| JSValue::Map(vec![
+            (JSValue::String(first.to_string()), JSValue::Number(1.0)),
+            (JSValue::String(second.to_string()), JSValue::Number(1.0)),
+        ]);
+        assert_eq!(map("a", "b"), map("a", "b"));
+        assert_ne!(map("a", "b"), map("b", "a"));
+    }
+
+    #[test]
+    fn test_truncate_to_byte_limit() {
+        assert_eq!(truncate_to_byte_limit("short".to_string(), 10), "short");
+        assert_eq!(truncate_to_byte_limit("abcdef".to_string(), 3), "abc…");
+        // "é" is two bytes; never split it
+        assert_eq!(truncate_to_byte_limit("aéb".to_string(), 2), "a…");
+        assert_eq!(truncate_to_byte_limit("🌍🌍".to_string(), 5), "🌍…");
+    }
+
+    #[test]
+    fn test_image_data_preview() {
+        let mut val = JSValue::ImageData { width: 2, height: 1, data: vec![255; 8], preview_png: None };
+        assert_eq!(val.to_display_string(), "ImageData(2x1 pixels, 8 bytes)");
+
+        val.attach_image_previews();
+        assert!(matches!(val, JSValue::ImageData { preview_png: Some(ref png), .. } if png.starts_with("iVBOR")));
+    }
+
+    #[test]
+    fn test_typed_array_decoding() {
+        let bytes: Vec<u8> = [-1i16, 300].iter().flat_map(|n| n.to_ne_bytes()).collect();
+        let elements = TypedArrayKind::Int16.decode(&bytes);
+        assert_eq!(elements, [-1.0, 300.0]);
+
+        let val = JSValue::TypedArray { kind: TypedArrayKind::Float32, elements: vec![0.5, f64::NAN], byte_length: 8 };
+        assert_eq!(val.to_display_string(), "Float32Array(2 elements)");
+        assert_eq!(val.to_json_value()["elements"][1]["value"], "NaN");
+        assert!(val.structural_eq(&val.clone()));
+        assert_eq!(TypedArrayKind::from_name("Uint32Array"), Some(TypedArrayKind::Uint32));
+    }
+
+    #[test]
+    fn test_array_buffer_json() {
+        let val = JSValue::ArrayBuffer { byte_length: 3, data: Some("AQID".to_string()) };
+        assert_eq!(val.to_display_string(), "ArrayBuffer(3 bytes)");
+        assert_eq!(val.to_json_value(), serde_json::json!({ "type": "arraybuffer", "byteLength": 3, "data": "AQID" }));
+
+        let oversized = JSValue::ArrayBuffer { byte_length: 1024, data: None };
+        assert_eq!(oversized.to_json_value()["data"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_promise_display() {
+        let pending = JSValue::Promise { state: PromiseState::Pending, value: None };
+        assert_eq!(pending.to_display_string(), "Promise { <pending> }");
+
+        let fulfilled = JSValue::Promise { state: PromiseState::Fulfilled, value: Some(Box::new(JSValue::Number(42.0))) };
+        assert_eq!(fulfilled.to_display_string(), "Promise { fulfilled: 42 }");
+        assert!(!fulfilled.structural_eq(&pending));
+
+        let rejected = JSValue::Promise {
+            state: PromiseState::Rejected,
+            value: Some(Box::new(JSValue::Error("boom".to_string()))),
+        };
+        assert_eq!(rejected.to_display_string(), "Promise { rejected: Error: boom }");
+
+        let generator = JSValue::Generator { done: false, value: Box::new(JSValue::Number(42.0)) };
+        assert_eq!(generator.to_display_string(), "Generator { done: false, value: 42 }");
+        assert_eq!(generator.type_name(), "Generator");
+    }
+
+    #[test]
+    fn test_pretty_print_respects_line_width() {
+        let val = JSValue::Array(vec![JSValue::Number(1.0), JSValue::String("two".to_string())]);
+
+        let wide = PrettyPrintConfig { max_line_width: 80, ..Default::default() };
+        assert_eq!(val.pretty_print(&wide), "[1, \"two\"]");
+
+        let narrow = PrettyPrintConfig { max_line_width: 8, ..Default::default() };
+        assert_eq!(val.pretty_print(&narrow), "[\n  1,\n  \"two\"\n]");
+    }
+
+    #[test]
+    fn test_bigint_words() {
+        assert_eq!(bigint_words("42"), Some((false, vec![42])));
+        assert_eq!(bigint_words("-5n"), Some((true, vec![5])));
+        assert_eq!(bigint_words("18446744073709551616"), Some((false, vec![0, 1])));
+        assert_eq!(bigint_words("12a"), None);
+    }
+}
@@ -0,0 +1,684 @@
+use crate::runtime::engine::VariableSnapshot;
+use crate::runtime::error::DebuggerError;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use std::cell::OnceCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+/// Leading bytes of a snapshot log written by `FileSnapshotStore`
+const FILE_MAGIC: &[u8; 4] = b"TTDL";
+/// Bumped whenever the header, the record layout or `VariableSnapshot` changes shape
+const FILE_FORMAT_VERSION: u8 = 3;
+/// Magic, version byte, the creation time as little-endian `f64` seconds and the length
+/// of the `CatMetadata` that follows (0 for a file that wasn't produced by `cat`)
+const FILE_FIXED_HEADER_LEN: u64 = 4 + 1 + 8 + 8;
+
+/// Where a snapshot log merged by `FileSnapshotStore::cat` came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeMetadata {
+    pub path: PathBuf,
+    /// Creation time from the input's header, in seconds since the epoch
+    pub created_at: f64,
+    pub snapshot_count: usize,
+}
+
+/// Header section of a `cat` output: the inputs it was merged from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CatMetadata {
+    pub input_files: Vec<RuntimeMetadata>,
+}
+
+/// What `FileSnapshotStore::cat` wrote
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CatStats {
+    pub total_snapshots: usize,
+    pub input_files: usize,
+    pub merged_size_bytes: u64,
+}
+
+/// The header every snapshot log starts with
+fn file_header(created_at: f64, metadata: Option<&CatMetadata>) -> Result<Vec<u8>> {
+    let metadata = metadata.map(bincode::serialize).transpose()?.unwrap_or_default();
+    let mut header = Vec::with_capacity(FILE_FIXED_HEADER_LEN as usize + metadata.len());
+    header.extend_from_slice(FILE_MAGIC);
+    header.push(FILE_FORMAT_VERSION);
+    header.extend_from_slice(&created_at.to_le_bytes());
+    header.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+    header.extend_from_slice(&metadata);
+    Ok(header)
+}
+
+/// Storage behind `ExecutionState::variable_snapshots`. Indices are positions among the
+/// snapshots currently held, oldest first.
+pub trait SnapshotStore {
+    fn push(&mut self, snapshot: VariableSnapshot);
+    fn get(&self, index: usize) -> Option<&VariableSnapshot>;
+    fn len(&self) -> usize;
+    fn iter(&self) -> impl Iterator<Item = &VariableSnapshot>;
+    fn clear(&mut self);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Store several snapshots at once; stores with a costly write per snapshot override this
+    fn append_batch(&mut self, snapshots: &[VariableSnapshot]) -> Result<()> {
+        for snapshot in snapshots {
+            self.push(snapshot.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Every snapshot in a `Vec`, trimmed only by the state's snapshot limit and eviction
+/// policy. Derefs to the `Vec`, so the capture pipeline can index, remove and reorder.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VecSnapshotStore(Vec<VariableSnapshot>);
+
+impl VecSnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, VariableSnapshot> {
+        self.0.iter()
+    }
+}
+
+impl SnapshotStore for VecSnapshotStore {
+    fn push(&mut self, snapshot: VariableSnapshot) {
+        self.0.push(snapshot);
+    }
+
+    fn get(&self, index: usize) -> Option<&VariableSnapshot> {
+        self.0.get(index)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &VariableSnapshot> {
+        self.0.iter()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl Deref for VecSnapshotStore {
+    type Target = Vec<VariableSnapshot>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for VecSnapshotStore {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Vec<VariableSnapshot>> for VecSnapshotStore {
+    fn from(snapshots: Vec<VariableSnapshot>) -> Self {
+        Self(snapshots)
+    }
+}
+
+impl From<VecSnapshotStore> for Vec<VariableSnapshot> {
+    fn from(store: VecSnapshotStore) -> Self {
+        store.0
+    }
+}
+
+impl<'a> IntoIterator for &'a VecSnapshotStore {
+    type Item = &'a VariableSnapshot;
+    type IntoIter = std::slice::Iter<'a, VariableSnapshot>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut VecSnapshotStore {
+    type Item = &'a mut VariableSnapshot;
+    type IntoIter = std::slice::IterMut<'a, VariableSnapshot>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+/// Keeps only the most recent `capacity` snapshots, dropping the oldest on overflow
+#[derive(Debug, Clone)]
+pub struct RingSnapshotStore {
+    pub capacity: usize,
+    pub buffer: VecDeque<VariableSnapshot>,
+}
+
+impl RingSnapshotStore {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, buffer: VecDeque::with_capacity(capacity) }
+    }
+}
+
+impl SnapshotStore for RingSnapshotStore {
+    fn push(&mut self, snapshot: VariableSnapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.buffer.len() == self.capacity {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(snapshot);
+    }
+
+    fn get(&self, index: usize) -> Option<&VariableSnapshot> {
+        self.buffer.get(index)
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &VariableSnapshot> {
+        self.buffer.iter()
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+/// Appends each snapshot to a file as it is pushed: a header, then one record per
+/// snapshot (a little-endian `u64` length followed by the bincode encoding). Only record
+/// offsets stay in memory; `get` reads a record back the first time it is asked for.
+#[derive(Debug)]
+pub struct FileSnapshotStore {
+    path: PathBuf,
+    file: File,
+    /// Creation time from the header, in seconds since the epoch
+    created_at: f64,
+    /// Set when the file was produced by `cat`
+    cat_metadata: Option<CatMetadata>,
+    /// Where the first record starts
+    header_len: u64,
+    /// File offset of each record's length prefix
+    offsets: Vec<u64>,
+    /// Records decoded by `get`, one slot per offset
+    loaded: Vec<OnceCell<VariableSnapshot>>,
+}
+
+impl FileSnapshotStore {
+    /// Create (or truncate) `path` and write the header
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::create_with_metadata(path, None)
+    }
+
+    fn create_with_metadata(path: impl AsRef<Path>, cat_metadata: Option<CatMetadata>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::options().read(true).write(true).create(true).truncate(true).open(&path)
+            .map_err(|e| anyhow!("Cannot create snapshot store {}: {}", path.display(), e))?;
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+
+        let header = file_header(created_at, cat_metadata.as_ref())?;
+        file.write_all(&header)?;
+        let header_len = header.len() as u64;
+        Ok(Self { path, file, created_at, cat_metadata, header_len, offsets: Vec::new(), loaded: Vec::new() })
+    }
+
+    /// Open an existing snapshot log, rebuilding the offset index from its records.
+    /// Later pushes append to it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::options().read(true).append(true).open(&path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(&file);
+
+        // Magic and version first: older versions have a shorter header
+        let mut prefix = [0u8; 5];
+        reader.read_exact(&mut prefix)
+            .map_err(|_| anyhow!("{} is not a snapshot store", path.display()))?;
+        if &prefix[..4] != FILE_MAGIC {
+            return Err(anyhow!("{} is not a snapshot store", path.display()));
+        }
+        if prefix[4] != FILE_FORMAT_VERSION {
+            return Err(DebuggerError::UnsupportedSchemaVersion {
+                path: path.clone(),
+                found: prefix[4],
+                expected: FILE_FORMAT_VERSION,
+            }.into());
+        }
+
+        let mut fixed = [0u8; (FILE_FIXED_HEADER_LEN - 5) as usize];
+        reader.read_exact(&mut fixed)
+            .map_err(|_| anyhow!("Truncated header in {}", path.display()))?;
+        let created_at = f64::from_le_bytes(fixed[..8].try_into()?);
+        let metadata_len = u64::from_le_bytes(fixed[8..].try_into()?);
+        let cat_metadata = if metadata_len == 0 {
+            None
+        } else {
+            let mut metadata = vec![0u8; metadata_len as usize];
+            reader.read_exact(&mut metadata)
+                .map_err(|_| anyhow!("Truncated header in {}", path.display()))?;
+            Some(bincode::deserialize(&metadata)?)
+        };
+        let header_len = FILE_FIXED_HEADER_LEN + metadata_len;
+
+        let mut offsets = Vec::new();
+        let mut offset = header_len;
+        while offset < file_len {
+            let mut length = [0u8; 8];
+            reader.read_exact(&mut length)
+                .map_err(|_| anyhow!("Truncated record at offset {} in {}", offset, path.display()))?;
+            let length = u64::from_le_bytes(length);
+            offsets.push(offset);
+            reader.seek_relative(length as i64)?;
+            offset += 8 + length;
+        }
+        if offset != file_len {
+            return Err(anyhow!("Truncated record at offset {} in {}", offsets.last().unwrap_or(&0), path.display()));
+        }
+
+        let loaded = offsets.iter().map(|_| OnceCell::new()).collect();
+        Ok(Self { path, file, created_at, cat_metadata, header_len, offsets, loaded })
+    }
+
+    /// Merge the snapshot logs in `inputs` into a new log at `output`, interleaving their
+    /// snapshots by timestamp (ties keep input order). The output header lists the inputs.
+    /// Fails with `DebuggerError::UnsupportedSchemaVersion` before writing anything if any
+    /// input uses another format version.
+    pub fn cat(inputs: &[&Path], output: &Path) -> Result<CatStats> {
+        let stores = inputs.iter().map(FileSnapshotStore::open).collect::<Result<Vec<_>>>()?;
+
+        let mut snapshots = Vec::new();
+        for store in &stores {
+            for index in 0..store.len() {
+                snapshots.push(store.read_record(store.offsets[index])?);
+            }
+        }
+        snapshots.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+
+        let metadata = CatMetadata {
+            input_files: stores.iter()
+                .map(|store| RuntimeMetadata {
+                    path: store.path.clone(),
+                    created_at: store.created_at,
+                    snapshot_count: store.len(),
+                })
+                .collect(),
+        };
+        let mut merged = Self::create_with_metadata(output, Some(metadata))?;
+        for snapshot in &snapshots {
+            merged.append(snapshot)?;
+        }
+        merged.sync()?;
+
+        Ok(CatStats {
+            total_snapshots: snapshots.len(),
+            input_files: stores.len(),
+            merged_size_bytes: merged.file.metadata()?.len(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn created_at(&self) -> f64 {
+        self.created_at
+    }
+
+    /// The inputs this file was merged from, if `cat` wrote it
+    pub fn cat_metadata(&self) -> Option<&CatMetadata> {
+        self.cat_metadata.as_ref()
+    }
+
+    /// Wait for the records written so far to reach the disk
+    pub fn sync(&self) -> Result<()> {
+        self.file.sync_data()
+            .map_err(|e| anyhow!("Cannot flush snapshot store {}: {}", self.path.display(), e))
+    }
+
+    /// Append one record, indexing it only once it is fully written
+    pub fn append(&mut self, snapshot: &VariableSnapshot) -> Result<()> {
+        self.append_batch(std::slice::from_ref(snapshot))
+    }
+
+    fn read_record(&self, offset: u64) -> Result<VariableSnapshot> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut length = [0u8; 8];
+        file.read_exact(&mut length)?;
+        let mut record = vec![0u8; u64::from_le_bytes(length) as usize];
+        file.read_exact(&mut record)?;
+        Ok(bincode::deserialize(&record)?)
+    }
+}
+
+impl SnapshotStore for FileSnapshotStore {
+    /// Write failures are logged and the snapshot is not indexed
+    fn push(&mut self, snapshot: VariableSnapshot) {
+        if let Err(e) = self.append(&snapshot) {
+            warn!(path = %self.path.display(), error = %e, "failed to write snapshot");
+        }
+    }
+
+    /// All of `snapshots` in a single write, indexed only once it succeeds
+    fn append_batch(&mut self, snapshots: &[VariableSnapshot]) -> Result<()> {
+        let mut offset = self.file.seek(SeekFrom::End(0))?;
+        let mut offsets = Vec::with_capacity(snapshots.len());
+        let mut bytes = Vec::new();
+        for snapshot in snapshots {
+            let record = bincode::serialize(snapshot)?;
+            offsets.push(offset);
+            offset += 8 + record.len() as u64;
+            bytes.extend_from_slice(&(record.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&record);
+        }
+        self.file.write_all(&bytes)?;
+
+        self.loaded.extend(offsets.iter().map(|_| OnceCell::new()));
+        self.offsets.extend(offsets);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<&VariableSnapshot> {
+        let cell = self.loaded.get(index)?;
+        if let Some(snapshot) = cell.get() {
+            return Some(snapshot);
+        }
+        match self.read_record(self.offsets[index]) {
+            Ok(snapshot) => Some(cell.get_or_init(|| snapshot)),
+            Err(e) => {
+                warn!(path = %self.path.display(), index, error = %e, "failed to read snapshot");
+                None
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &VariableSnapshot> {
+        (0..self.len()).map_while(|index| self.get(index))
+    }
+
+    /// Truncate back to just the header
+    fn clear(&mut self) {
+        if let Err(e) = self.file.set_len(self.header_len) {
+            warn!(path = %self.path.display(), error = %e, "failed to clear snapshot file");
+            return;
+        }
+        self.offsets.clear();
+        self.loaded.clear();
+    }
+}
+
+/// A snapshot store whose writes are awaited rather than blocking the calling thread
+pub trait AsyncSnapshotStore {
+    fn push(&mut self, snapshot: VariableSnapshot) -> impl Future<Output = Result<()>> + Send;
+    /// Wait until every snapshot pushed so far is on disk
+    fn flush(&mut self) -> impl Future<Output = Result<()>> + Send;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// When `AsyncFileSnapshotStore` flushes its buffered records, whichever comes first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlushPolicy {
+    pub every_snapshots: usize,
+    pub interval: Duration,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        Self { every_snapshots: 100, interval: Duration::from_secs(5) }
+    }
+}
+
+enum WriterCommand {
+    Record(Vec<u8>),
+    Flush(oneshot::Sender<std::io::Result<()>>),
+}
+
+/// `FileSnapshotStore`'s file format written from async code. Records go through a
+/// `BufWriter` owned by a writer task on its own thread, which flushes per the
+/// `FlushPolicy`. Dropping the store closes the queue and joins the writer, so every
+/// pushed snapshot reaches the file; `close` does the same and reports write errors.
+#[derive(Debug)]
+pub struct AsyncFileSnapshotStore {
+    path: PathBuf,
+    sender: Option<mpsc::Sender<WriterCommand>>,
+    writer: Option<std::thread::JoinHandle<std::io::Result<()>>>,
+    len: usize,
+}
+
+impl AsyncFileSnapshotStore {
+    /// Create (or truncate) `path`, write the header and start the writer
+    pub async fn create(path: impl AsRef<Path>, policy: FlushPolicy) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = tokio::fs::File::create(&path).await
+            .map_err(|e| anyhow!("Cannot create snapshot store {}: {}", path.display(), e))?;
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+        file.write_all(&file_header(created_at, None)?).await?;
+        let file = file.into_std().await;
+
+        let (sender, receiver) = mpsc::channel(policy.every_snapshots.max(1) * 2);
+        let writer = std::thread::Builder::new()
+            .name("snapshot-writer".to_string())
+            .spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_time()
+                    .build()?
+                    .block_on(write_records(tokio::fs::File::from_std(file), receiver, policy))
+            })?;
+        Ok(Self { path, sender: Some(sender), writer: Some(writer), len: 0 })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flush the remaining records and stop the writer, returning the first write error
+    pub fn close(mut self) -> Result<()> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.sender.take();
+        match self.writer.take().map(std::thread::JoinHandle::join) {
+            Some(Ok(result)) => result.map_err(|e| anyhow!("Failed to write {}: {}", self.path.display(), e)),
+            Some(Err(_)) => Err(anyhow!("Snapshot writer for {} panicked", self.path.display())),
+            None => Ok(()),
+        }
+    }
+
+    async fn send(&self, command: WriterCommand) -> Result<()> {
+        let sender = self.sender.as_ref().ok_or_else(|| anyhow!("Snapshot store is closed"))?;
+        sender.send(command).await
+            .map_err(|_| anyhow!("Snapshot writer for {} stopped", self.path.display()))
+    }
+}
+
+impl AsyncSnapshotStore for AsyncFileSnapshotStore {
+    async fn push(&mut self, snapshot: VariableSnapshot) -> Result<()> {
+        let record = bincode::serialize(&snapshot)?;
+        let mut bytes = Vec::with_capacity(8 + record.len());
+        bytes.extend_from_slice(&(record.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&record);
+        self.send(WriterCommand::Record(bytes)).await?;
+        self.len += 1;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        let (done, flushed) = oneshot::channel();
+        self.send(WriterCommand::Flush(done)).await?;
+        flushed.await
+            .map_err(|_| anyhow!("Snapshot writer for {} stopped", self.path.display()))?
+            .map_err(|e| anyhow!("Failed to flush {}: {}", self.path.display(), e))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for AsyncFileSnapshotStore {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            warn!(path = %self.path.display(), error = %e, "failed to finish snapshot file");
+        }
+    }
+}
+
+/// Body of `AsyncFileSnapshotStore`'s writer: append records until the queue closes,
+/// flushing every `policy.every_snapshots` records or `policy.interval`, then at the end
+async fn write_records(file: tokio::fs::File, mut receiver: mpsc::Receiver<WriterCommand>, policy: FlushPolicy) -> std::io::Result<()> {
+    let mut writer = tokio::io::BufWriter::new(file);
+    let mut unflushed = 0;
+    let mut ticker = tokio::time::interval(policy.interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            command = receiver.recv() => match command {
+                Some(WriterCommand::Record(bytes)) => {
+                    writer.write_all(&bytes).await?;
+                    unflushed += 1;
+                    if unflushed >= policy.every_snapshots {
+                        writer.flush().await?;
+                        unflushed = 0;
+                    }
+                },
+                Some(WriterCommand::Flush(done)) => {
+                    let _ = done.send(writer.flush().await);
+                    unflushed = 0;
+                },
+                None => break,
+            },
+            _ = ticker.tick(), if unflushed > 0 => {
+                writer.flush().await?;
+                unflushed = 0;
+            },
+        }
+    }
+    writer.flush().await?;
+    writer.into_inner().sync_all().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn snapshot(timestamp: f64) -> VariableSnapshot {
+        VariableSnapshot {
+            timestamp,
+            function_name: "tick".to_string(),
+            call_depth: 1,
+            variables: HashMap::new(),
+            snapshot_type: "custom".to_string(),
+            metadata: HashMap::new(),
+            trace_id: None,
+            heap_stats: None,
+        }
+    }
+
+    #[test]
+    fn test_ring_store_keeps_latest() {
+        let mut store = RingSnapshotStore::new(2);
+        for timestamp in [1.0, 2.0, 3.0] {
+            store.push(snapshot(timestamp));
+        }
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(0).map(|s| s.timestamp), Some(2.0));
+        assert_eq!(store.iter().map(|s| s.timestamp).collect::<Vec<_>>(), [2.0, 3.0]);
+        store.clear();
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_async_file_store_is_readable_after_close() {
+        let path = std::env::temp_dir().join(format!("ttd_async_store_{}.ttdl", std::process::id()));
+        let policy = FlushPolicy { every_snapshots: 2, interval: Duration::from_secs(60) };
+        let mut store = AsyncFileSnapshotStore::create(&path, policy).await.unwrap();
+        for timestamp in [1.0, 2.0, 3.0] {
+            store.push(snapshot(timestamp)).await.unwrap();
+        }
+        store.flush().await.unwrap();
+        assert_eq!(FileSnapshotStore::open(&path).unwrap().len(), 3);
+
+        store.push(snapshot(4.0)).await.unwrap();
+        drop(store);
+        let reopened = FileSnapshotStore::open(&path).unwrap();
+        assert_eq!(reopened.iter().map(|s| s.timestamp).collect::<Vec<_>>(), [1.0, 2.0, 3.0, 4.0]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_roundtrip() {
+        let path = std::env::temp_dir().join(format!("ttd_store_{}.ttdl", std::process::id()));
+        let mut store = FileSnapshotStore::create(&path).unwrap();
+        for timestamp in [1.0, 2.0, 3.0] {
+            store.push(snapshot(timestamp));
+        }
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(1).map(|s| s.timestamp), Some(2.0));
+        assert!(store.get(3).is_none());
+
+        let reopened = FileSnapshotStore::open(&path).unwrap();
+        assert_eq!(reopened.created_at(), store.created_at());
+        assert_eq!(reopened.iter().map(|s| s.timestamp).collect::<Vec<_>>(), [1.0, 2.0, 3.0]);
+
+        store.clear();
+        assert!(FileSnapshotStore::open(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_cat_interleaves_by_timestamp() {
+        let dir = std::env::temp_dir().join(format!("ttd_cat_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b, merged) = (dir.join("a.ttd"), dir.join("b.ttd"), dir.join("merged.ttd"));
+        let mut store_a = FileSnapshotStore::create(&a).unwrap();
+        [1.0, 4.0].into_iter().for_each(|timestamp| store_a.push(snapshot(timestamp)));
+        let mut store_b = FileSnapshotStore::create(&b).unwrap();
+        [2.0, 3.0, 5.0].into_iter().for_each(|timestamp| store_b.push(snapshot(timestamp)));
+
+        let stats = FileSnapshotStore::cat(&[&a, &b], &merged).unwrap();
+        assert_eq!((stats.total_snapshots, stats.input_files), (5, 2));
+        assert_eq!(stats.merged_size_bytes, std::fs::metadata(&merged).unwrap().len());
+        let output = FileSnapshotStore::open(&merged).unwrap();
+        assert_eq!(output.iter().map(|s| s.timestamp).collect::<Vec<_>>(), [1.0, 2.0, 3.0, 4.0, 5.0]);
+        let inputs = &output.cat_metadata().unwrap().input_files;
+        assert_eq!(inputs.iter().map(|input| input.snapshot_count).collect::<Vec<_>>(), [2, 3]);
+
+        // A log from another format version is rejected before anything is written
+        let mut bytes = std::fs::read(&b).unwrap();
+        bytes[4] = FILE_FORMAT_VERSION - 1;
+        std::fs::write(&b, bytes).unwrap();
+        std::fs::remove_file(&merged).unwrap();
+        let error = FileSnapshotStore::cat(&[&a, &b], &merged).unwrap_err();
+        assert!(matches!(error.downcast_ref::<DebuggerError>(),
+            Some(DebuggerError::UnsupportedSchemaVersion { found, .. }) if *found == FILE_FORMAT_VERSION - 1));
+        assert!(!merged.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{Result, anyhow};
+use sourcemap::SourceMap;
+use std::collections::HashMap;
+
+/// A location in the original (pre-bundling) source. Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub original_file: String,
+    pub original_line: u32,
+    pub original_column: u32,
+}
+
+/// Source maps registered for generated files, keyed by the map's `file` field
+#[derive(Debug, Default)]
+pub struct SourceMapRegistry {
+    maps: HashMap<String, SourceMap>,
+}
+
+impl SourceMapRegistry {
+    /// Parse a V3 source map and register it for the generated file it names,
+    /// replacing any earlier map for that file. Returns the generated file name.
+    pub fn register(&mut self, map_json: &str) -> Result<String> {
+        let map = SourceMap::from_slice(map_json.as_bytes())
+            .map_err(|e| anyhow!("Invalid source map: {}", e))?;
+        let file = map.get_file()
+            .ok_or_else(|| anyhow!("Source map has no `file` naming the generated script"))?
+            .to_string();
+        self.maps.insert(file.clone(), map);
+        Ok(file)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    /// Map a 1-based generated `line`/`col` in `file` back to the original source.
+    /// `file` may be the script URL V8 reports, which ends with the map's relative `file`.
+    /// A position between mappings resolves to the closest mapping before it on the
+    /// same line, or the first mapping on the line when there is none before it.
+    pub fn resolve(&self, file: &str, line: u32, col: u32) -> Option<SourcePosition> {
+        let map = self.maps.get(file).or_else(|| self.maps.iter()
+            .find(|(generated, _)| file.strip_suffix(generated.as_str())
+                .is_some_and(|prefix| prefix.ends_with('/')))
+            .map(|(_, map)| map))?;
+        let (line, col) = (line.checked_sub(1)?, col.saturating_sub(1));
+
+        let token = map.lookup_token(line, col)
+            .filter(|token| token.get_dst_line() == line)
+            .or_else(|| map.tokens().find(|token| token.get_dst_line() == line))?;
+
+        Some(SourcePosition {
+            original_file: token.get_source()?.to_string(),
+            original_line: token.get_src_line() + 1,
+            original_column: token.get_src_col() + 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_generated_position() {
+        // bundle.js line 1: col 0 -> src/a.js 1:0, col 9 -> src/a.js 3:2
+        // bundle.js line 2: col 4 -> src/b.js 6:0
+        let map = r#"{"version":3,"file":"bundle.js","sources":["src/a.js","src/b.js"],"names":[],"mappings":"AAAA,SAEE;ICGF"}"#;
+        let mut registry = SourceMapRegistry::default();
+        assert_eq!(registry.register(map).unwrap(), "bundle.js");
+
+        let at = |line, col| registry.resolve("bundle.js", line, col).map(|p| (p.original_file, p.original_line, p.original_column));
+        assert_eq!(at(1, 1), Some(("src/a.js".to_string(), 1, 1)));
+        assert_eq!(at(1, 12), Some(("src/a.js".to_string(), 3, 3)));
+        // Before the first mapping on line 2
+        assert_eq!(at(2, 1), Some(("src/b.js".to_string(), 6, 1)));
+        assert!(registry.resolve("other.js", 1, 1).is_none());
+        // Script URLs from V8 stack frames
+        assert_eq!(registry.resolve("file:///app/dist/bundle.js", 2, 1).map(|p| p.original_line), Some(6));
+        assert!(registry.resolve("file:///app/dist/mybundle.js", 2, 1).is_none());
+        assert!(registry.register(r#"{"version":3,"sources":[],"names":[],"mappings":""}"#).is_err());
+    }
+}
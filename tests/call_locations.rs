@@ -0,0 +1,63 @@
+//! Runs the debugger binary on small scripts and checks where their calls were recorded
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A fresh directory under the system temp dir for one test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ttd_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the debugger on `script` with `args`, asserting that it succeeds
+fn run_debugger(script: &Path, args: &[&Path]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_time_travel_debugger"))
+        .arg(script)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{} failed: {}", script.display(), String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn source_map_resolves_recorded_call_sites() {
+    let dir = scratch_dir("source_map");
+    let script = dir.join("bundle.js");
+    // Line 3 of bundle.js maps to line 6 of src/math.js
+    std::fs::write(&script, "\
+timeDebugger.registerSourceMap({ version: 3, file: 'bundle.js', sources: ['src/math.js'], names: [], mappings: ';;AAKA' });
+const add = timeDebugger.captureFunction((a, b) => a + b, 'add');
+add(1, 2);
+").unwrap();
+    let export = dir.join("trace.json");
+
+    run_debugger(&script, &[Path::new("--export"), &export, Path::new("--format"), Path::new("json")]);
+
+    let trace: serde_json::Value = serde_json::from_slice(&std::fs::read(&export).unwrap()).unwrap();
+    let call = trace["function_call_history"].as_array().unwrap().iter()
+        .find(|call| call["name"] == "add")
+        .expect("add was not recorded");
+    assert_eq!(call["file_location"], "src/math.js");
+    assert_eq!(call["line_number"], 6);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn coverage_report_counts_calls_per_line() {
+    let dir = scratch_dir("coverage");
+    let script = dir.join("loop.js");
+    std::fs::write(&script, "\
+const square = timeDebugger.captureFunction((n) => n * n, 'square');
+for (let i = 0; i < 3; i++) {
+  square(i);
+}
+square(10);
+").unwrap();
+    let report = dir.join("coverage.json");
+
+    run_debugger(&script, &[Path::new("--coverage-report"), &report]);
+
+    let report: serde_json::Value = serde_json::from_slice(&std::fs::read(&report).unwrap()).unwrap();
+    assert_eq!(report["lines"], serde_json::json!({ "3": 3, "5": 1 }));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
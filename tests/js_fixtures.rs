@@ -0,0 +1,51 @@
+//! Runs every `NN_*.js` fixture under tests/js_fixtures through the debugger binary. A fixture
+//! throws on a mismatch, so each one must exit successfully.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The numbered fixtures in `group`, in order; shared helpers like assert.js are skipped
+fn fixtures(group: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/js_fixtures").join(group);
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "js")
+            && path.file_name().unwrap().to_string_lossy().split_once('_')
+                .is_some_and(|(number, _)| number.parse::<u32>().is_ok()))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+fn run_group(group: &str) {
+    let fixtures = fixtures(group);
+    assert!(!fixtures.is_empty(), "no fixtures in {}", group);
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        // Fixtures that save files write them under their working directory
+        let dir = std::env::temp_dir().join(format!("ttd_fixture_{}_{}", std::process::id(),
+            fixture.file_stem().unwrap().to_string_lossy()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_time_travel_debugger"))
+            .arg(fixture)
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        if !output.status.success() {
+            failures.push(format!("{} ({}):\n{}{}", fixture.display(), output.status,
+                String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    assert!(failures.is_empty(), "{} of {} fixtures failed:\n{}", failures.len(), fixtures.len(), failures.join("\n"));
+}
+
+#[test]
+fn serialization_fixtures_pass() {
+    run_group("serialization");
+}
+
+#[test]
+fn replay_fixtures_pass() {
+    run_group("replay");
+}
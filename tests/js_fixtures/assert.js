@@ -0,0 +1,29 @@
+// Shared helpers for the fixtures; tests/js_fixtures.rs runs every NN_*.js under this directory.
+// Run one by hand with: time_travel_debugger tests/js_fixtures/<group>/<fixture>.js
+
+export function deepEqual(actual, expected, path = '$') {
+    if (Object.is(actual, expected)) {
+        return;
+    }
+    if (typeof actual !== 'object' || typeof expected !== 'object' || actual === null || expected === null) {
+        throw new Error(`${path}: expected ${JSON.stringify(expected)}, got ${JSON.stringify(actual)}`);
+    }
+    if (Array.isArray(actual) !== Array.isArray(expected)) {
+        throw new Error(`${path}: expected ${JSON.stringify(expected)}, got ${JSON.stringify(actual)}`);
+    }
+
+    const actualKeys = Object.keys(actual).sort();
+    const expectedKeys = Object.keys(expected).sort();
+    if (actualKeys.join(',') !== expectedKeys.join(',')) {
+        throw new Error(`${path}: expected keys [${expectedKeys}], got [${actualKeys}]`);
+    }
+    for (const key of expectedKeys) {
+        deepEqual(actual[key], expected[key], `${path}.${key}`);
+    }
+}
+
+// Serialize `value` and compare against the expected JSON form
+export function checkSerialized(name, value, expected) {
+    deepEqual(timeDebugger.serialize(value), expected);
+    console.log(`✅ ${name}`);
+}
@@ -0,0 +1,27 @@
+import { deepEqual } from '../assert.js';
+
+timeDebugger.captureScope('injectFixture', 'custom', {
+    state: { n: 1, items: [true, null, 'x'] },
+    when: new Date(0),
+    pattern: /a+b/gi,
+    lookup: new Map([['k', 1n]]),
+});
+const [{ index }] = timeDebugger.querySnapshots({ function: 'injectFixture' });
+
+timeDebugger.injectValue('restored', index, 'state');
+deepEqual(globalThis.restored, { n: 1, items: [true, null, 'x'] });
+
+timeDebugger.injectValue('restoredDate', index, 'when');
+if (!(globalThis.restoredDate instanceof Date) || globalThis.restoredDate.getTime() !== 0) {
+    throw new Error(`expected Date(0) back, got ${globalThis.restoredDate}`);
+}
+timeDebugger.injectValue('restoredPattern', index, 'pattern');
+if (String(globalThis.restoredPattern) !== '/a+b/gi') {
+    throw new Error(`expected /a+b/gi back, got ${globalThis.restoredPattern}`);
+}
+
+timeDebugger.injectValue('restoredLookup', index, 'lookup');
+if (globalThis.restoredLookup.get('k') !== 1n) {
+    throw new Error('expected Map { k => 1n } back');
+}
+console.log('✅ injectValue');
@@ -0,0 +1,25 @@
+import { deepEqual } from '../assert.js';
+
+timeDebugger.captureScope('checkpointFixture', 'custom', {
+    cart: { items: ['apple', 'pear'], total: 3.5 },
+});
+const [{ index }] = timeDebugger.querySnapshots({ function: 'checkpointFixture' });
+
+timeDebugger.saveSnapshot('checkpoint', index, '.snapshots');
+const restored = await timeDebugger.loadSnapshot('./.snapshots/checkpoint.ttd');
+if (restored === index) {
+    throw new Error('expected the loaded snapshot to be appended, not to replace the original');
+}
+timeDebugger.injectValue('restoredCart', restored, 'cart');
+deepEqual(globalThis.restoredCart, { items: ['apple', 'pear'], total: 3.5 });
+
+let denied = false;
+try {
+    timeDebugger.loadSnapshot('../outside.ttd');
+} catch {
+    denied = true;
+}
+if (!denied) {
+    throw new Error('expected a path outside the working directory to be refused');
+}
+console.log('✅ saveSnapshot / loadSnapshot');
@@ -0,0 +1,3 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('undefined', undefined, { type: 'undefined' });
@@ -0,0 +1,4 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('true', true, true);
+checkSerialized('false', false, false);
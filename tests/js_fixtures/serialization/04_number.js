@@ -0,0 +1,5 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('integer', 42, 42);
+checkSerialized('negative float', -3.5, -3.5);
+checkSerialized('zero', 0, 0);
@@ -0,0 +1,3 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('NaN', NaN, { type: 'number', value: 'NaN' });
@@ -0,0 +1,4 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('Infinity', Infinity, { type: 'number', value: 'Infinity' });
+checkSerialized('-Infinity', -Infinity, { type: 'number', value: '-Infinity' });
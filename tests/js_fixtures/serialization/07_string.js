@@ -0,0 +1,5 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('string', 'hello', 'hello');
+checkSerialized('empty string', '', '');
+checkSerialized('unicode string', 'héllo 🌍', 'héllo 🌍');
@@ -0,0 +1,3 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('bigint', 12345678901234567890n, { type: 'bigint', value: '12345678901234567890' });
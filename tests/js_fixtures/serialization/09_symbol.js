@@ -0,0 +1,3 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('symbol', Symbol('tag'), { type: 'symbol', description: 'tag' });
@@ -0,0 +1,4 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('array', [1, 'two', null, undefined], [1, 'two', null, { type: 'undefined' }]);
+checkSerialized('empty array', [], []);
@@ -0,0 +1,8 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('object', { name: 'Ada', age: 36, tags: ['math'], address: { city: 'London' } }, {
+    name: 'Ada',
+    age: 36,
+    tags: ['math'],
+    address: { city: 'London' },
+});
@@ -0,0 +1,14 @@
+import { deepEqual } from '../assert.js';
+
+// Deeper than SerializationConfig::max_depth (10): the cut-off level becomes an error value
+let nested = 'leaf';
+for (let i = 0; i < 12; i++) {
+    nested = { child: nested };
+}
+
+let serialized = timeDebugger.serialize(nested);
+for (let i = 0; i <= 10; i++) {
+    serialized = serialized.child;
+}
+deepEqual(serialized, { type: 'error', message: 'Max depth exceeded' });
+console.log('✅ max depth exceeded');
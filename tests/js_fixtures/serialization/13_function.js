@@ -0,0 +1,16 @@
+import { checkSerialized } from '../assert.js';
+
+function add(a, b) { return a + b; }
+
+checkSerialized('named function', add, {
+    type: 'function',
+    name: 'add',
+    source: 'function add(a, b) { return a + b; }',
+    location: null,
+});
+checkSerialized('anonymous arrow', (() => () => 1)(), {
+    type: 'function',
+    name: 'anonymous',
+    source: '() => 1',
+    location: null,
+});
@@ -0,0 +1,7 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('epoch', new Date(0), { type: 'date', value: '1970-01-01T00:00:00.000Z' });
+checkSerialized('date', new Date(Date.UTC(2024, 1, 29, 12, 30, 15, 250)), {
+    type: 'date',
+    value: '2024-02-29T12:30:15.250Z',
+});
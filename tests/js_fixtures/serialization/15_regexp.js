@@ -0,0 +1,4 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('regexp', /ab+c/gi, { type: 'regexp', pattern: 'ab+c', flags: 'gi' });
+checkSerialized('regexp with slash', /a\/b/, { type: 'regexp', pattern: 'a\\/b', flags: '' });
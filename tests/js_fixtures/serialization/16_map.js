@@ -0,0 +1,9 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('map', new Map([[1, 'one']]), { type: 'map', entries: [[1, 'one']] });
+
+// Entries are normalized into canonical key order, whatever the insertion order
+checkSerialized('map key order', new Map([['b', 2], [true, 'yes'], [1, 'one'], [null, 0]]), {
+    type: 'map',
+    entries: [[null, 0], [true, 'yes'], [1, 'one'], ['b', 2]],
+});
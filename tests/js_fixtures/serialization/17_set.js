@@ -0,0 +1,3 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('set', new Set([3, 'x', 3]), { type: 'set', values: [3, 'x'] });
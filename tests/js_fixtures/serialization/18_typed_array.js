@@ -0,0 +1,6 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('Uint8ClampedArray', new Uint8ClampedArray([0, 128, 300, -5]), {
+    type: 'uint8clampedarray',
+    values: [0, 128, 255, 0],
+});
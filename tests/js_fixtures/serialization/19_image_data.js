@@ -0,0 +1,12 @@
+import { checkSerialized } from '../assert.js';
+
+// Anything shaped like ImageData is captured as one (previews are off by default)
+const image = { width: 2, height: 1, data: new Uint8ClampedArray(8) };
+
+checkSerialized('ImageData', image, {
+    type: 'imagedata',
+    width: 2,
+    height: 1,
+    byte_length: 8,
+    preview_png: null,
+});
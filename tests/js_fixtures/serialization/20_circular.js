@@ -0,0 +1,12 @@
+import { deepEqual } from '../assert.js';
+
+const node = { name: 'root' };
+node.self = node;
+
+const serialized = timeDebugger.serialize(node);
+deepEqual(serialized.name, 'root');
+deepEqual(serialized.self.type, 'circular_ref');
+if (!serialized.self.ref.startsWith('ref_')) {
+    throw new Error(`unexpected circular reference id: ${serialized.self.ref}`);
+}
+console.log('✅ circular reference');
@@ -0,0 +1,22 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('Int16Array', new Int16Array([-1, 300]), {
+    type: 'typedarray',
+    kind: 'Int16Array',
+    elements: [-1, 300],
+    byteLength: 4,
+});
+
+checkSerialized('Float32Array', new Float32Array([0.5, NaN]), {
+    type: 'typedarray',
+    kind: 'Float32Array',
+    elements: [0.5, { type: 'number', value: 'NaN' }],
+    byteLength: 8,
+});
+
+checkSerialized('Uint32Array', new Uint32Array([4294967295]), {
+    type: 'typedarray',
+    kind: 'Uint32Array',
+    elements: [4294967295],
+    byteLength: 4,
+});
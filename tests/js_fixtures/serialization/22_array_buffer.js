@@ -0,0 +1,14 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('ArrayBuffer', new Uint8Array([1, 2, 3]).buffer, {
+    type: 'arraybuffer',
+    byteLength: 3,
+    data: 'AQID',
+});
+
+// Default max_array_length is 100, so buffers of 800 bytes or more drop their content
+checkSerialized('oversized ArrayBuffer', new ArrayBuffer(800), {
+    type: 'arraybuffer',
+    byteLength: 800,
+    data: null,
+});
@@ -0,0 +1,21 @@
+import { checkSerialized } from '../assert.js';
+
+checkSerialized('pending Promise', new Promise(() => {}), {
+    type: 'promise',
+    state: 'pending',
+    value: null,
+});
+
+checkSerialized('fulfilled Promise', Promise.resolve(42), {
+    type: 'promise',
+    state: 'fulfilled',
+    value: 42,
+});
+
+const rejected = Promise.reject('nope');
+rejected.catch(() => {});
+checkSerialized('rejected Promise', rejected, {
+    type: 'promise',
+    state: 'rejected',
+    value: 'nope',
+});
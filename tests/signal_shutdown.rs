@@ -0,0 +1,37 @@
+//! Runs the debugger binary on a script that never finishes and stops it with SIGTERM
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+#[test]
+fn sigterm_saves_partial_trace_and_exits_143() {
+    let dir = std::env::temp_dir().join(format!("ttd_sigterm_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("spin.js");
+    std::fs::write(&script, "timeDebugger.captureVariable('started', true);\nconsole.log('spinning');\nwhile (true) {}\n").unwrap();
+    let crash_output = dir.join("partial.ttd");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_time_travel_debugger"))
+        .arg(&script)
+        .arg("--crash-output")
+        .arg(&crash_output)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Keep reading stdout until the script is stuck in its loop; the reader stays open
+    // so the debugger's own output after the signal doesn't hit a closed pipe
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    assert!(lines.by_ref().map_while(Result::ok).any(|line| line.contains("spinning")));
+
+    // SAFETY: plain kill(2) on the child we spawned
+    assert_eq!(unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) }, 0);
+    let status = child.wait().unwrap();
+    lines.for_each(drop);
+
+    assert_eq!(status.code(), Some(143));
+    assert!(crash_output.exists(), "no partial trace at {}", crash_output.display());
+    std::fs::remove_dir_all(&dir).unwrap();
+}